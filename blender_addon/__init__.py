@@ -26,6 +26,13 @@ def generate_deduped_names():
         name_occurrences[name] += 1
         yield display_name
 
+def is_joint_body(blender_object):
+    for other_object in bpy.data.objects:
+        sim_settings = other_object.sim_settings
+        if sim_settings.is_joint and blender_object in (sim_settings.joint_first_body, sim_settings.joint_second_body):
+            return True
+    return False
+
 def register():
     bpy.utils.register_class(PerigeeGltfExportProperties)
     bpy.types.Scene.PerigeeGltfExportProperties = bpy.props.PointerProperty(type=PerigeeGltfExportProperties)
@@ -117,12 +124,37 @@ class glTF2ExportUserExtension:
                     optimized_shape = "SPHERE"
                 gltf2_object.extras["simSettings"]["physics"]["optimizedShape"] = optimized_shape
             elif blender_object.type == "MESH":
-                gltf2_object.extras["simSettings"]["physics"]["baseScale"] = [blender_object.dimensions[0] / blender_object.matrix_world.to_scale()[0], blender_object.dimensions[1] / blender_object.matrix_world.to_scale()[1], blender_object.dimensions[2] / blender_object.matrix_world.to_scale()[2]]
+                # Blender's Z axis is glTF's Y (up) axis
+                world_scale = blender_object.matrix_world.to_scale()
+                gltf2_object.extras["simSettings"]["physics"]["baseScale"] = [
+                    blender_object.dimensions[0] / world_scale[0],
+                    blender_object.dimensions[2] / world_scale[2],
+                    blender_object.dimensions[1] / world_scale[1]
+                ]
                 if gltf2_object.extras["simSettings"]["physics"]["bodyType"] == "DYNAMIC":
                     gltf2_object.extras["simSettings"]["physics"]["isAnonymous"] = False
+                # Joints find their bodies by name
+                if is_joint_body(blender_object):
+                    gltf2_object.extras["simSettings"]["physics"]["isAnonymous"] = False
             else:
                 pass
     
+    def gather_animation_hook(self, gltf2_animation, blender_action, blender_object, export_settings):
+        if self.properties.enabled:
+            render_settings = bpy.context.scene.render
+            frames_per_second = render_settings.fps / render_settings.fps_base
+            if gltf2_animation.extras is None:
+                gltf2_animation.extras = {}
+            # Pose markers let the engine trigger listeners (footsteps, hitboxes, etc)
+            # using names instead of frame numbers
+            gltf2_animation.extras["markers"] = [
+                {
+                    "name": marker.name,
+                    "time": marker.frame / frames_per_second
+                }
+                for marker in blender_action.pose_markers
+            ]
+
     def gather_scene_hook(self, gltf2_scene, blender_scene, export_settings):
         if self.properties.enabled:
             if gltf2_scene.extras is None:
@@ -1,5 +1,33 @@
 import bpy
 
+# Blender is Z-up and glTF is Y-up, so an object's local axes
+# are converted the same way its transform is on export
+GLTF_JOINT_AXES = {
+    "X": [1, 0, 0],
+    "Y": [0, 0, -1],
+    "Z": [0, 1, 0],
+}
+
+GLTF_COMBINE_RULES = [
+    ("AVERAGE", "Average", "Use the average of both objects' coefficients.", "", 1),
+    ("MIN", "Min", "Use the smaller of both objects' coefficients.", "", 2),
+    ("MULTIPLY", "Multiply", "Use the product of both objects' coefficients.", "", 3),
+    ("MAX", "Max", "Use the larger of both objects' coefficients.", "", 4)
+]
+
+def groups_to_bits(groups):
+    bits = 0
+    for index, is_set in enumerate(groups):
+        if is_set:
+            bits |= 1 << index
+    return bits
+
+def groups_from_bits(bits):
+    return [bool(bits & (1 << index)) for index in range(32)]
+
+def is_mesh_object(self, blender_object):
+    return blender_object.type == "MESH"
+
 class ObjectSimulationSettings(bpy.types.PropertyGroup):
     is_graphics_object: bpy.props.BoolProperty(name="Enable Graphics", default=True)
     is_physics_object: bpy.props.BoolProperty(name="Enable Physics")
@@ -13,10 +41,96 @@ class ObjectSimulationSettings(bpy.types.PropertyGroup):
         ("NONE", "None", "Have the simulation build a triangle mesh of this object. Note: This uses more memory than optimized shapes.", "", 1),
         ("CONVEX_MESH", "Convex Mesh", "Have the simulation build a convex mesh of this object.", "", 2),
         ("CUBOID", "Cuboid", "Have the simulation use its intrinsic cuboid representation for this object.", "", 3),
-        ("SPHERE", "Sphere", "Have the simulation use its intrinsic sphere representation for this object.", "", 4)
+        ("SPHERE", "Sphere", "Have the simulation use its intrinsic sphere representation for this object.", "", 4),
+        ("CAPSULE", "Capsule", "Have the simulation use its intrinsic capsule representation for this object. The capsule stands along the object's up axis.", "", 5),
+        ("CYLINDER", "Cylinder", "Have the simulation use its intrinsic cylinder representation for this object. The cylinder stands along the object's up axis.", "", 6),
+        ("CONE", "Cone", "Have the simulation use its intrinsic cone representation for this object. The cone points along the object's up axis.", "", 7),
+        ("COMPOUND", "Compound", "Have the simulation combine the shapes of this object's physics-enabled child meshes into one collider.", "", 8),
+        ("CONVEX_DECOMPOSITION", "Convex Decomposition", "Have the simulation split this object into convex pieces. Note: This is slow to build for detailed meshes.", "", 9)
     ])
+    friction: bpy.props.FloatProperty(name="Friction", min=0, default=0.5, precision=3)
+    friction_combine_rule: bpy.props.EnumProperty(name="Friction Combine Rule", description="How this object's friction is combined with the friction of objects it touches.", default="AVERAGE", items=GLTF_COMBINE_RULES)
+    restitution: bpy.props.FloatProperty(name="Restitution", description="How bouncy this object is.", min=0, default=0, precision=3)
+    restitution_combine_rule: bpy.props.EnumProperty(name="Restitution Combine Rule", description="How this object's restitution is combined with the restitution of objects it touches.", default="AVERAGE", items=GLTF_COMBINE_RULES)
+    use_density: bpy.props.BoolProperty(name="Use Density", description="Compute this object's mass from its density and volume instead of using its mass.")
+    density: bpy.props.FloatProperty(name="Density", min=0.00001, soft_min=0.00001, default=1, precision=4)
+    collision_memberships: bpy.props.BoolVectorProperty(name="Collision Memberships", description="The collision groups this object belongs to.", size=32, default=[True] * 32)
+    collision_filter: bpy.props.BoolVectorProperty(name="Collision Filter", description="The collision groups this object collides with.", size=32, default=[True] * 32)
+    solver_memberships: bpy.props.BoolVectorProperty(name="Solver Memberships", description="The solver groups this object belongs to.", size=32, default=[True] * 32)
+    solver_filter: bpy.props.BoolVectorProperty(name="Solver Filter", description="The solver groups whose contacts push this object.", size=32, default=[True] * 32)
+    collision_layer: bpy.props.StringProperty(name="Collision Layer", description="The name of a collision layer from the engine's physics config. Overrides the collision groups when set.")
+    ccd_enabled: bpy.props.BoolProperty(name="Continuous Collision Detection", description="Keep this object from passing through thin objects when moving fast.")
     is_point_of_interest: bpy.props.BoolProperty(name="Mark as Point of Interest", description="Store this object's location and rotation in the Perigee glTF using the object's name for use in-engine.")
     is_anonymous: bpy.props.BoolProperty(name="Make Anonymous", description="Don't make this object referenceable by name in the Perigee engine. This saves memory.", default=True)
+    is_joint: bpy.props.BoolProperty(name="Enable Joint", description="Connect two physics objects with a joint anchored at this object.")
+    joint_type: bpy.props.EnumProperty(name="Joint Type", default="REVOLUTE", items=[
+        ("FIXED", "Fixed", "The objects can't move relative to each other.", "", 1),
+        ("REVOLUTE", "Revolute", "The objects rotate relative to each other around the joint axis, like a hinge.", "", 2),
+        ("PRISMATIC", "Prismatic", "The objects slide relative to each other along the joint axis, like a piston.", "", 3),
+        ("SPHERICAL", "Spherical", "The objects rotate freely relative to each other around the joint, like a ball and socket.", "", 4)
+    ])
+    joint_first_body: bpy.props.PointerProperty(name="First Body", type=bpy.types.Object, poll=is_mesh_object)
+    joint_second_body: bpy.props.PointerProperty(name="Second Body", type=bpy.types.Object, poll=is_mesh_object)
+    joint_axis: bpy.props.EnumProperty(name="Joint Axis", description="The local axis of this object that revolute and prismatic joints rotate around or slide along.", default="Z", items=[
+        ("X", "X", "", "", 1),
+        ("Y", "Y", "", "", 2),
+        ("Z", "Z", "", "", 3)
+    ])
+    joint_use_limits: bpy.props.BoolProperty(name="Limit Motion")
+    joint_limit_min: bpy.props.FloatProperty(name="Min", description="Radians for rotations or meters for translations.", default=-1)
+    joint_limit_max: bpy.props.FloatProperty(name="Max", description="Radians for rotations or meters for translations.", default=1)
+    joint_use_motor: bpy.props.BoolProperty(name="Enable Motor")
+    joint_motor_target_position: bpy.props.FloatProperty(name="Target Position")
+    joint_motor_target_velocity: bpy.props.FloatProperty(name="Target Velocity")
+    joint_motor_stiffness: bpy.props.FloatProperty(name="Stiffness", min=0, description="Set to 0 to only drive the joint's velocity.")
+    joint_motor_damping: bpy.props.FloatProperty(name="Damping", min=0, default=1)
+    joint_motor_max_force: bpy.props.FloatProperty(name="Max Force", min=0, description="Set to 0 for an unlimited force.")
+
+    def joint_to_dict(self):
+        if not self.is_joint:
+            return None
+        return {
+            "enabled": True,
+            "jointType": self.joint_type,
+            "firstBody": self.joint_first_body.name if self.joint_first_body else "",
+            "secondBody": self.joint_second_body.name if self.joint_second_body else "",
+            "axis": GLTF_JOINT_AXES[self.joint_axis],
+            "limits": {
+                "min": self.joint_limit_min,
+                "max": self.joint_limit_max
+            } if self.joint_use_limits else None,
+            "motor": {
+                "targetPosition": self.joint_motor_target_position,
+                "targetVelocity": self.joint_motor_target_velocity,
+                "stiffness": self.joint_motor_stiffness,
+                "damping": self.joint_motor_damping,
+                "maxForce": self.joint_motor_max_force if self.joint_motor_max_force > 0 else None
+            } if self.joint_use_motor else None,
+        }
+
+    def joint_from_dict(self, joint_dict):
+        self.is_joint = joint_dict is not None and joint_dict["enabled"]
+        if joint_dict is None:
+            return
+        self.joint_type = joint_dict["jointType"]
+        self.joint_first_body = bpy.data.objects.get(joint_dict["firstBody"])
+        self.joint_second_body = bpy.data.objects.get(joint_dict["secondBody"])
+        for axis_name, gltf_axis in GLTF_JOINT_AXES.items():
+            if joint_dict.get("axis") == gltf_axis:
+                self.joint_axis = axis_name
+        limits = joint_dict.get("limits")
+        self.joint_use_limits = limits is not None
+        if limits is not None:
+            self.joint_limit_min = limits["min"]
+            self.joint_limit_max = limits["max"]
+        motor = joint_dict.get("motor")
+        self.joint_use_motor = motor is not None
+        if motor is not None:
+            self.joint_motor_target_position = motor.get("targetPosition", 0)
+            self.joint_motor_target_velocity = motor.get("targetVelocity", 0)
+            self.joint_motor_stiffness = motor.get("stiffness", 0)
+            self.joint_motor_damping = motor.get("damping", 0)
+            self.joint_motor_max_force = motor.get("maxForce") or 0
 
     def to_dict(self):
         return {
@@ -28,9 +142,25 @@ class ObjectSimulationSettings(bpy.types.PropertyGroup):
                 "bodyType": self.body_type,
                 "mass": self.mass,
                 "optimizedShape": self.optimized_shape,
-                "isAnonymous": self.is_anonymous
+                "isAnonymous": self.is_anonymous,
+                "friction": self.friction,
+                "frictionCombineRule": self.friction_combine_rule,
+                "restitution": self.restitution,
+                "restitutionCombineRule": self.restitution_combine_rule,
+                "density": self.density if self.use_density else None,
+                "collisionGroups": {
+                    "memberships": groups_to_bits(self.collision_memberships),
+                    "filter": groups_to_bits(self.collision_filter),
+                },
+                "solverGroups": {
+                    "memberships": groups_to_bits(self.solver_memberships),
+                    "filter": groups_to_bits(self.solver_filter),
+                },
+                "collisionLayer": self.collision_layer or None,
+                "ccdEnabled": self.ccd_enabled
             },
             "isPointOfInterest": self.is_point_of_interest,
+            "joint": self.joint_to_dict(),
         }
     
     def from_dict(self, new_dict):
@@ -40,7 +170,27 @@ class ObjectSimulationSettings(bpy.types.PropertyGroup):
         self.mass = new_dict["physics"]["mass"]
         self.optimized_shape = new_dict["physics"]["optimizedShape"]
         self.is_anonymous = new_dict["physics"]["isAnonymous"]
+        # Older files don't have material settings
+        self.friction = new_dict["physics"].get("friction", 0.5)
+        self.friction_combine_rule = new_dict["physics"].get("frictionCombineRule", "AVERAGE")
+        self.restitution = new_dict["physics"].get("restitution", 0)
+        self.restitution_combine_rule = new_dict["physics"].get("restitutionCombineRule", "AVERAGE")
+        density = new_dict["physics"].get("density")
+        self.use_density = density is not None
+        if density is not None:
+            self.density = density
+        all_groups = {"memberships": 0xFFFFFFFF, "filter": 0xFFFFFFFF}
+        collision_groups = new_dict["physics"].get("collisionGroups") or all_groups
+        self.collision_memberships = groups_from_bits(collision_groups["memberships"])
+        self.collision_filter = groups_from_bits(collision_groups["filter"])
+        solver_groups = new_dict["physics"].get("solverGroups") or all_groups
+        self.solver_memberships = groups_from_bits(solver_groups["memberships"])
+        self.solver_filter = groups_from_bits(solver_groups["filter"])
+        self.collision_layer = new_dict["physics"].get("collisionLayer") or ""
+        self.ccd_enabled = new_dict["physics"].get("ccdEnabled", False)
         self.is_point_of_interest = new_dict["isPointOfInterest"]
+        # Bodies that haven't been imported yet can't be resolved here
+        self.joint_from_dict(new_dict.get("joint"))
         
 class VIEW3D_PT_object_sim_settings(bpy.types.Panel):
     bl_space_type = "VIEW_3D"
@@ -68,12 +218,57 @@ class VIEW3D_PT_object_sim_settings(bpy.types.Panel):
                 layout.prop(context_object.sim_settings, "body_type")
                 layout.prop(context_object.sim_settings, "optimized_shape")
                 if context_object.sim_settings.body_type == "DYNAMIC":
-                    layout.prop(context_object.sim_settings, "mass")
+                    layout.prop(context_object.sim_settings, "use_density")
+                    if context_object.sim_settings.use_density:
+                        layout.prop(context_object.sim_settings, "density")
+                    else:
+                        layout.prop(context_object.sim_settings, "mass")
+                    layout.prop(context_object.sim_settings, "ccd_enabled")
+                layout.prop(context_object.sim_settings, "friction")
+                layout.prop(context_object.sim_settings, "friction_combine_rule")
+                layout.prop(context_object.sim_settings, "restitution")
+                layout.prop(context_object.sim_settings, "restitution_combine_rule")
+                self.draw_group_widgets(context_object, layout)
             elif context_object.type == "EMPTY":
                 layout.label(text = "Body Type: SENSOR")
+                self.draw_group_widgets(context_object, layout)
             else:
                 pass
 
+    def draw_group_widgets(self, context_object, layout):
+        layout.prop(context_object.sim_settings, "collision_layer")
+        if not context_object.sim_settings.collision_layer:
+            layout.label(text = "Collision Groups")
+            layout.prop(context_object.sim_settings, "collision_memberships", text = "Memberships")
+            layout.prop(context_object.sim_settings, "collision_filter", text = "Filter")
+        if context_object.type == "MESH":
+            layout.label(text = "Solver Groups")
+            layout.prop(context_object.sim_settings, "solver_memberships", text = "Memberships")
+            layout.prop(context_object.sim_settings, "solver_filter", text = "Filter")
+
+    def draw_joint_widgets(self, context_object, layout):
+        layout.label(text = "Joint")
+        sim_settings = context_object.sim_settings
+        layout.prop(sim_settings, "is_joint")
+        if sim_settings.is_joint:
+            layout.prop(sim_settings, "joint_type")
+            layout.prop(sim_settings, "joint_first_body")
+            layout.prop(sim_settings, "joint_second_body")
+            if sim_settings.joint_type != "FIXED":
+                if sim_settings.joint_type != "SPHERICAL":
+                    layout.prop(sim_settings, "joint_axis")
+                layout.prop(sim_settings, "joint_use_limits")
+                if sim_settings.joint_use_limits:
+                    layout.prop(sim_settings, "joint_limit_min")
+                    layout.prop(sim_settings, "joint_limit_max")
+                layout.prop(sim_settings, "joint_use_motor")
+                if sim_settings.joint_use_motor:
+                    layout.prop(sim_settings, "joint_motor_target_position")
+                    layout.prop(sim_settings, "joint_motor_target_velocity")
+                    layout.prop(sim_settings, "joint_motor_stiffness")
+                    layout.prop(sim_settings, "joint_motor_damping")
+                    layout.prop(sim_settings, "joint_motor_max_force")
+
     def draw(self, context):
         layout = self.layout
         if context.object:
@@ -85,6 +280,10 @@ class VIEW3D_PT_object_sim_settings(bpy.types.Panel):
             layout.separator()
             physics_col = layout.column(align = True)
             self.draw_physics_widgets(context.object, physics_col)
+            if context.object.type == "EMPTY":
+                layout.separator()
+                joint_col = layout.column(align = True)
+                self.draw_joint_widgets(context.object, joint_col)
         else:
             self.layout.label(text = "No object selected")
 
@@ -0,0 +1,42 @@
+//! Write the FFI manifest and TypeScript bindings of a simulation so a web
+//! host's build can check its glue code against the wasm it loads.
+//!
+//! `cargo run --example ffi_bindings -- <output directory>`
+//!
+//! A game copies this into a bin of its own and lists its own manifests.
+use perigee::ffi::manifest::FfiManifest;
+use perigee::prelude::*;
+use std::path::PathBuf;
+
+#[derive(Default)]
+pub struct Sim {
+    elapsed_seconds: f32,
+    objective: String,
+}
+
+#[ffi]
+impl Sim {
+    pub fn step(&mut self, delta_seconds: f32) {
+        self.elapsed_seconds += delta_seconds;
+    }
+
+    pub fn current_objective(&self) -> String {
+        self.objective.clone()
+    }
+
+    #[slot_return]
+    pub fn elapsed_seconds(&self) -> f64 {
+        f64::from(self.elapsed_seconds)
+    }
+}
+
+slotted_types!(u64);
+
+fn main() -> std::io::Result<()> {
+    let out_dir = PathBuf::from(std::env::args().nth(1).unwrap_or_else(|| String::from(".")));
+    let manifest = FfiManifest::with_engine(&[Sim::FFI_MANIFEST, SLOTTED_TYPES_FFI_MANIFEST])?;
+    manifest.write_files(
+        out_dir.join("perigee_ffi.json"),
+        out_dir.join("perigee_ffi.ts"),
+    )
+}
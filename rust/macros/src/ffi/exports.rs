@@ -0,0 +1,193 @@
+use crate::manifest::{
+    host_hooks_manifest_json, manifest_json, tokens_to_type_string, ManifestFunction,
+};
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input,
+    punctuated::Punctuated,
+    token::Comma,
+    Abi, FnArg, ForeignItem, Ident, ItemFn, ItemForeignMod, Pat, ReturnType, Token,
+};
+
+/// The arguments of #[ffi_export] and #[ffi_host_hooks]: `manifest = NAME`
+/// names the manifest constant and, for #[ffi_export] only,
+/// `buffer_return = string` or `buffer_return = bytes` describes what the
+/// function writes into its `FfiByteBuffer` return slot.
+#[derive(Default)]
+struct ExportArgs {
+    manifest_name: Option<Ident>,
+    buffer_return: Option<&'static str>,
+}
+
+impl Parse for ExportArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = Self::default();
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let value: Ident = input.parse()?;
+            if key == "manifest" {
+                args.manifest_name = Some(value);
+            } else if key == "buffer_return" {
+                args.buffer_return = if value == "string" {
+                    Some("string")
+                } else if value == "bytes" {
+                    Some("bytes")
+                } else {
+                    return Err(syn::Error::new_spanned(
+                        value,
+                        "`buffer_return` must be `string` or `bytes`",
+                    ));
+                };
+            } else {
+                return Err(syn::Error::new_spanned(
+                    key,
+                    "expected a `manifest = NAME` or `buffer_return = string|bytes` argument",
+                ));
+            }
+            input.parse::<Option<Token![,]>>()?;
+        }
+        Ok(args)
+    }
+}
+
+/// Describe a foreign function's signature the same way #[ffi] describes
+/// the functions it generates.
+fn manifest_function(
+    fn_name: &Ident,
+    fn_args: &Punctuated<FnArg, Comma>,
+    output: &ReturnType,
+    buffer_return: Option<&'static str>,
+) -> syn::Result<ManifestFunction> {
+    let mut args = Vec::new();
+    for fn_arg in fn_args {
+        match fn_arg {
+            FnArg::Typed(pat_type) => match &*pat_type.pat {
+                Pat::Ident(pat_ident) => args.push((
+                    pat_ident.ident.to_string(),
+                    tokens_to_type_string(&pat_type.ty),
+                )),
+                other_pat => {
+                    return Err(syn::Error::new_spanned(
+                        other_pat,
+                        "FFI functions need named arguments to be described in a manifest",
+                    ))
+                }
+            },
+            FnArg::Receiver(receiver) => {
+                return Err(syn::Error::new_spanned(
+                    receiver,
+                    "FFI functions can't take self",
+                ))
+            }
+        }
+    }
+    Ok(ManifestFunction {
+        name: fn_name.to_string(),
+        args,
+        returns: match output {
+            ReturnType::Type(_, return_type) => Some(tokens_to_type_string(return_type)),
+            ReturnType::Default => None,
+        },
+        buffer_return,
+    })
+}
+
+fn is_c_abi(abi: &Abi) -> bool {
+    abi.name.as_ref().is_none_or(|name| name.value() == "C")
+}
+
+pub fn ffi_export(args: TokenStream, input: TokenStream) -> TokenStream {
+    let ExportArgs {
+        manifest_name,
+        buffer_return,
+    } = parse_macro_input!(args as ExportArgs);
+    let item_fn = parse_macro_input!(input as ItemFn);
+
+    if !item_fn.sig.abi.as_ref().is_some_and(is_c_abi) {
+        let error = syn::Error::new_spanned(
+            item_fn.sig.fn_token,
+            "#[ffi_export] can only be used on extern \"C\" functions",
+        )
+        .to_compile_error();
+        return quote!(#item_fn #error).into();
+    }
+    let function = match manifest_function(
+        &item_fn.sig.ident,
+        &item_fn.sig.inputs,
+        &item_fn.sig.output,
+        buffer_return,
+    ) {
+        Ok(function) => function,
+        Err(error) => {
+            let error = error.to_compile_error();
+            return quote!(#item_fn #error).into();
+        }
+    };
+
+    let fn_name = &item_fn.sig.ident;
+    let manifest_name = manifest_name
+        .unwrap_or_else(|| format_ident!("{}_FFI_MANIFEST", fn_name.to_string().to_uppercase()));
+    let manifest_doc = format!(" JSON manifest of the `{}` FFI export.", fn_name);
+    let manifest = manifest_json(&[function], &[]);
+    quote! {
+        #item_fn
+
+        #[doc = #manifest_doc]
+        pub const #manifest_name: &str = #manifest;
+    }
+    .into()
+}
+
+pub fn ffi_host_hooks(args: TokenStream, input: TokenStream) -> TokenStream {
+    let ExportArgs {
+        manifest_name,
+        buffer_return,
+    } = parse_macro_input!(args as ExportArgs);
+    let foreign_mod = parse_macro_input!(input as ItemForeignMod);
+
+    let mut errors: Vec<syn::Error> = Vec::new();
+    if buffer_return.is_some() {
+        errors.push(syn::Error::new(
+            Span::call_site(),
+            "#[ffi_host_hooks] only accepts a `manifest = NAME` argument",
+        ));
+    }
+    if !is_c_abi(&foreign_mod.abi) {
+        errors.push(syn::Error::new_spanned(
+            &foreign_mod.abi,
+            "#[ffi_host_hooks] can only be used on extern \"C\" blocks",
+        ));
+    }
+    let mut host_hooks = Vec::new();
+    for foreign_item in &foreign_mod.items {
+        if let ForeignItem::Fn(foreign_fn) = foreign_item {
+            match manifest_function(
+                &foreign_fn.sig.ident,
+                &foreign_fn.sig.inputs,
+                &foreign_fn.sig.output,
+                None,
+            ) {
+                Ok(hook) => host_hooks.push(hook),
+                Err(error) => errors.push(error),
+            }
+        }
+    }
+    if !errors.is_empty() {
+        let compile_errors = errors.iter().map(syn::Error::to_compile_error);
+        return quote!(#foreign_mod #(#compile_errors)*).into();
+    }
+
+    let manifest_name = manifest_name.unwrap_or_else(|| format_ident!("HOST_HOOKS_FFI_MANIFEST"));
+    let manifest = host_hooks_manifest_json(&host_hooks);
+    quote! {
+        #foreign_mod
+
+        /// JSON manifest of the hooks the host must provide.
+        pub const #manifest_name: &str = #manifest;
+    }
+    .into()
+}
@@ -1,6 +1,74 @@
 use proc_macro2::Span;
-use quote::ToTokens;
-use syn::{Attribute, Expr, Ident, PathSegment, Stmt};
+use syn::{
+    Attribute, FnArg, GenericArgument, GenericParam, Ident, Pat, PathArguments, PathSegment,
+    ReturnType, Signature, Type, TypePath,
+};
+
+/// Where a public method of an #[ffi] impl block can be called from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FfiExposure {
+    RustAndFfi,
+    /// Marked with #[ffi_only]
+    FfiOnly,
+    /// Marked with #[ffi_never]
+    RustOnly,
+}
+
+/// How a string or byte return value is written into an `FfiByteBuffer` slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferReturn {
+    /// `String` or `&str`, written as UTF-8 and freed by the host with `free_bytes`
+    String,
+    /// `Vec<u8>` or `&[u8]`, freed by the host with `free_bytes`
+    Bytes,
+}
+
+impl BufferReturn {
+    pub fn manifest_name(&self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Bytes => "bytes",
+        }
+    }
+}
+
+fn is_u8(ty: &Type) -> bool {
+    matches!(ty, Type::Path(type_path) if type_path.path.is_ident("u8"))
+}
+
+/// Whether a return type is marshalled through an `FfiByteBuffer` slot.
+pub fn buffer_return_kind(return_type: &Type) -> Option<BufferReturn> {
+    match return_type {
+        Type::Reference(rfrnce) => match &*rfrnce.elem {
+            elem if is_str(elem) => Some(BufferReturn::String),
+            Type::Slice(slice) if is_u8(&slice.elem) => Some(BufferReturn::Bytes),
+            _ => None,
+        },
+        Type::Path(TypePath { qself: None, path }) => {
+            let last_segment = path.segments.last()?;
+            if last_segment.ident == "String"
+                && matches!(last_segment.arguments, PathArguments::None)
+            {
+                return Some(BufferReturn::String);
+            }
+            if last_segment.ident != "Vec" {
+                return None;
+            }
+            match &last_segment.arguments {
+                PathArguments::AngleBracketed(generic_args) if generic_args.args.len() == 1 => {
+                    match generic_args.args.first() {
+                        Some(GenericArgument::Type(elem)) if is_u8(elem) => {
+                            Some(BufferReturn::Bytes)
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
 
 pub fn attribute_name(attr: &Attribute) -> Ident {
     attr.path
@@ -14,21 +82,118 @@ pub fn attribute_name(attr: &Attribute) -> Ident {
         .clone()
 }
 
-pub fn replace_self_with_sim(statements: &mut Vec<Stmt>) {
-    for stmt in statements {
-        let stmt_tokens = stmt.to_token_stream().to_string().replace("self", "sim");
-        let new_stmt: Stmt = match syn::parse_str(&stmt_tokens) {
-            Ok(a) => a,
-            Err(e) => {
-                if &e.to_string() == "unexpected end of input, expected semicolon" {
-                    let expr: Expr = syn::parse_str(&stmt_tokens).unwrap();
-                    Stmt::Expr(expr)
-                } else {
-                    println!("{}", e.to_string());
-                    panic!();
+/// Whether a type is a plain path like `f32` or `Vec3` without
+/// generic arguments, which can be passed across the FFI boundary.
+fn is_plain_path(ty: &Type) -> bool {
+    match ty {
+        Type::Path(TypePath { qself: None, path }) => path
+            .segments
+            .iter()
+            .all(|segment| matches!(segment.arguments, PathArguments::None)),
+        _ => false,
+    }
+}
+
+fn is_str(ty: &Type) -> bool {
+    matches!(ty, Type::Path(type_path) if type_path.path.is_ident("str"))
+}
+
+const NEVER_HINT: &str = "Mark the method #[ffi_never] to keep it Rust-only";
+
+/// Check that a method's signature can be exported by #[ffi].
+pub fn validate_exported_signature(sig: &Signature, slot_return: bool) -> syn::Result<()> {
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(syn::Error::new_spanned(
+            asyncness,
+            format!("async methods can't be exported over FFI. {}", NEVER_HINT),
+        ));
+    }
+    if let Some(variadic) = &sig.variadic {
+        return Err(syn::Error::new_spanned(
+            variadic,
+            "variadic methods can't be exported over FFI",
+        ));
+    }
+    if let Some(generic_param) = sig
+        .generics
+        .params
+        .iter()
+        .find(|param| !matches!(param, GenericParam::Lifetime(_)))
+    {
+        return Err(syn::Error::new_spanned(
+            generic_param,
+            format!("generic methods can't be exported over FFI. {}", NEVER_HINT),
+        ));
+    }
+
+    for arg in sig.inputs.iter() {
+        match arg {
+            FnArg::Receiver(receiver) => {
+                if receiver.reference.is_none() {
+                    return Err(syn::Error::new_spanned(
+                        receiver,
+                        "methods exported over FFI must take `&self` or `&mut self`",
+                    ));
+                }
+            }
+            FnArg::Typed(typed_arg) => {
+                if !matches!(&*typed_arg.pat, Pat::Ident(_)) {
+                    return Err(syn::Error::new_spanned(
+                        &typed_arg.pat,
+                        "arguments of methods exported over FFI must be plain identifiers",
+                    ));
+                }
+                let is_supported = match &*typed_arg.ty {
+                    Type::Reference(rfrnce) => {
+                        (is_str(&rfrnce.elem) && rfrnce.mutability.is_none())
+                            || is_plain_path(&rfrnce.elem)
+                    }
+                    ty => is_plain_path(ty),
+                };
+                if !is_supported {
+                    return Err(syn::Error::new_spanned(
+                        &typed_arg.ty,
+                        format!(
+                            "unsupported FFI argument type. Use primitives, `&str`, `&T` or `&mut T`. {}",
+                            NEVER_HINT
+                        ),
+                    ));
                 }
             }
-        };
-        stmt.clone_from(&new_stmt);
+        }
+    }
+
+    match &sig.output {
+        ReturnType::Default if slot_return => Err(syn::Error::new_spanned(
+            sig,
+            "#[slot_return] used on a method with no return type",
+        )),
+        ReturnType::Default => Ok(()),
+        ReturnType::Type(_, return_type) => {
+            if buffer_return_kind(return_type).is_some() {
+                return Ok(());
+            }
+            let is_supported = match &**return_type {
+                Type::Reference(rfrnce) => slot_return && is_plain_path(&rfrnce.elem),
+                Type::Tuple(tuple) => tuple.elems.is_empty(),
+                ty => is_plain_path(ty),
+            };
+            if is_supported {
+                Ok(())
+            } else if matches!(&**return_type, Type::Reference(_)) {
+                Err(syn::Error::new_spanned(
+                    return_type,
+                    "references can only be returned over FFI with #[slot_return]",
+                ))
+            } else {
+                Err(syn::Error::new_spanned(
+                    return_type,
+                    format!(
+                        "unsupported FFI return type. Return `T`, `String`, `&str`, `Vec<u8>` or `&[u8]`, or use #[slot_return] for `&T`. {}",
+                        NEVER_HINT
+                    ),
+                ))
+            }
+        }
     }
 }
@@ -1,26 +1,50 @@
+use crate::ffi::helpers::{
+    attribute_name, buffer_return_kind, validate_exported_signature, BufferReturn, FfiExposure,
+};
+use crate::manifest::{manifest_json, tokens_to_type_string, ManifestFunction};
+use crate::shared::{generate_type_slotting_functions, perigee_crate_path, slotted_types_manifest};
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
-use quote::{quote, ToTokens};
+use quote::{format_ident, quote};
 use syn::{
     parse::{Parse, ParseStream},
-    token::{Colon, Comma, Brace},
-    Block,
-    ReturnType,
-    Stmt,
-    PatType,
-    Visibility,
-    FnArg,
-    Expr,
-    parse_macro_input,
+    parse_macro_input, parse_quote,
     punctuated::Punctuated,
-    AttrStyle, ImplItem, Pat, Type,
-    parse_quote, PathArguments
+    token::{Brace, Colon, Comma},
+    AttrStyle, Block, Expr, FnArg, Ident, ImplItem, ImplItemMethod, Pat, PatType, PathArguments,
+    ReturnType, Stmt, Token, Type, Visibility,
 };
-use crate::shared::generate_type_slotting_functions;
-use crate::ffi::helpers::{attribute_name, replace_self_with_sim};
 
+pub mod exports;
 mod helpers;
 
+/// The arguments of #[ffi]. `#[ffi(manifest = NAME)]` names the manifest
+/// constant, so that a type can have several #[ffi] impl blocks.
+struct FfiArgs {
+    manifest_name: Ident,
+}
+
+impl Parse for FfiArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.is_empty() {
+            return Ok(Self {
+                manifest_name: format_ident!("FFI_MANIFEST"),
+            });
+        }
+        let key: Ident = input.parse()?;
+        if key != "manifest" {
+            return Err(syn::Error::new_spanned(
+                key,
+                "#[ffi] only accepts a `manifest = NAME` argument",
+            ));
+        }
+        input.parse::<Token![=]>()?;
+        let manifest_name = input.parse()?;
+        input.parse::<Option<Token![,]>>()?;
+        Ok(Self { manifest_name })
+    }
+}
+
 struct FfiImplBlock {
     item: syn::ItemImpl,
 }
@@ -33,237 +57,312 @@ impl Parse for FfiImplBlock {
     }
 }
 
-pub fn ffi(_args: TokenStream, input: TokenStream) -> TokenStream {
-    let mut ffi_impl = parse_macro_input!(input as FfiImplBlock);
+/// Read and strip the #[ffi_only], #[ffi_never] and #[slot_return] markers of a method.
+fn take_ffi_markers(fn_details: &mut ImplItemMethod) -> syn::Result<(FfiExposure, bool)> {
+    let mut exposure = FfiExposure::RustAndFfi;
+    let mut slot_return = false;
+    let mut marker_error: Option<syn::Error> = None;
+    fn_details.attrs.retain(|attr| {
+        if !matches!(attr.style, AttrStyle::Outer) {
+            return true;
+        }
+        let attribute_name = attribute_name(attr);
+        let marked_exposure = if attribute_name == "ffi_only" {
+            FfiExposure::FfiOnly
+        } else if attribute_name == "ffi_never" {
+            FfiExposure::RustOnly
+        } else if attribute_name == "slot_return" {
+            slot_return = true;
+            return false;
+        } else {
+            return true;
+        };
+        if exposure != FfiExposure::RustAndFfi && marker_error.is_none() {
+            marker_error = Some(syn::Error::new_spanned(
+                attr,
+                "#[ffi_only] and #[ffi_never] can't be combined",
+            ));
+        }
+        exposure = marked_exposure;
+        false
+    });
+    match marker_error {
+        Some(error) => Err(error),
+        None => Ok((exposure, slot_return)),
+    }
+}
 
-    let mut expanded_impl_internals = TokenStream::new();
-    let mut ffi_code = TokenStream::new();
+pub fn ffi(args: TokenStream, input: TokenStream) -> TokenStream {
+    let FfiArgs { manifest_name } = parse_macro_input!(args as FfiArgs);
+    let mut ffi_impl = parse_macro_input!(input as FfiImplBlock);
+    let perigee = perigee_crate_path();
 
     let mut bare_sim_type = *ffi_impl.item.self_ty.clone();
     if let Type::Path(bare_sim_type_path) = &mut bare_sim_type {
         for path_seg in bare_sim_type_path.path.segments.iter_mut() {
             path_seg.arguments = PathArguments::None;
         }
+    } else {
+        return syn::Error::new_spanned(
+            &ffi_impl.item.self_ty,
+            "#[ffi] can only be used on impl blocks for named types",
+        )
+        .to_compile_error()
+        .into();
+    }
+    if let Some((_, trait_path, _)) = &ffi_impl.item.trait_ {
+        return syn::Error::new_spanned(trait_path, "#[ffi] can't be used on trait impl blocks")
+            .to_compile_error()
+            .into();
     }
 
+    let mut ffi_code = TokenStream2::new();
     let mut types_to_slot: Vec<Type> = Vec::new();
+    let mut manifest_functions: Vec<ManifestFunction> = Vec::new();
+    let mut errors: Vec<syn::Error> = Vec::new();
 
     for impl_item in ffi_impl.item.items.iter_mut() {
-        if let ImplItem::Method(ref mut fn_details) = impl_item {
-            // Only use process public functions
-            if !matches!(fn_details.vis, Visibility::Public(_)) {
+        let fn_details = match impl_item {
+            ImplItem::Method(fn_details) => fn_details,
+            _ => continue,
+        };
+
+        let (exposure, slot_return) = match take_ffi_markers(fn_details) {
+            Ok(markers) => markers,
+            Err(error) => {
+                errors.push(error);
                 continue;
             }
+        };
+        let is_public = matches!(fn_details.vis, Visibility::Public(_));
+        if exposure == FfiExposure::RustOnly || (!is_public && exposure != FfiExposure::FfiOnly) {
+            continue;
+        }
+        if let Err(error) = validate_exported_signature(&fn_details.sig, slot_return) {
+            errors.push(error);
+            continue;
+        }
 
-            let mut slot_return = false;
-            for attr in &mut fn_details.attrs {
-                if matches!(attr.style, AttrStyle::Outer) {
-                    let attribute_name = attribute_name(&attr);
-                    if attribute_name == "slot_return" {
-                        slot_return = true;
-                    }
+        let mut ffi_fn_statements: Vec<Stmt> = Vec::new();
+        let mut invocation_args: Vec<Expr> = Vec::new();
+        let mut updated_fn_args: Punctuated<FnArg, Comma> = Punctuated::new();
+        for arg in fn_details.sig.inputs.iter() {
+            let updated_arg = match arg {
+                // &self -> sim: *const Sim, &mut self -> sim: *mut Sim
+                FnArg::Receiver(receiver_details) => {
+                    let (sim_deref_stmt, sim_ptr_type): (Stmt, Type) = if receiver_details
+                        .mutability
+                        .is_some()
+                    {
+                        (
+                            parse_quote!(let sim = unsafe { #perigee::ffi::from_mut_ptr(sim_ptr) };),
+                            parse_quote!(*mut #bare_sim_type),
+                        )
+                    } else {
+                        (
+                            parse_quote!(let sim = unsafe { #perigee::ffi::from_ptr(sim_ptr) };),
+                            parse_quote!(*const #bare_sim_type),
+                        )
+                    };
+                    ffi_fn_statements.push(sim_deref_stmt);
+                    FnArg::Typed(PatType {
+                        attrs: Vec::new(),
+                        pat: Box::new(Pat::Verbatim(quote!(sim_ptr))),
+                        colon_token: Colon::default(),
+                        ty: Box::new(sim_ptr_type),
+                    })
                 }
-            }
-
-            // include the original function if we don't *only* want ffi code
-            let original_fn_details = fn_details.clone();
-            expanded_impl_internals
-                .extend::<TokenStream>(original_fn_details.into_token_stream().into());
-
-            let mut ffi_fn_statements: Vec<Stmt> = Vec::new();
-            let mut invocation_args = Vec::new();
-            let updated_fn_args_iter = fn_details.sig.inputs.iter().map(|arg| {
-                    match &arg {
-                        // &self -> sim: *const Sim, &mut self -> sim: *mut Sim
-                        FnArg::Receiver(receiver_details) => {
-                            let is_ref = receiver_details.reference.is_some();
-                            let is_mut = receiver_details.mutability.is_some();
-                            if !is_ref {
-                                // arg.span().unwrap().error("Found non-ref receiver argument. This may cause undefined behavior in FFI contexts.").emit();
-                            }
-
-                            let new_self_arg = if is_mut {
-                                let sim_deref_stmt: Stmt = parse_quote!(
-                                    let sim = unsafe { from_mut_ptr(sim_ptr) };
-                                );
-                                ffi_fn_statements.push(sim_deref_stmt);
-                                PatType { attrs: Vec::new(), pat: Box::new(Pat::Verbatim(quote!(sim_ptr))), colon_token: Colon::default(), ty: Box::new(Type::Verbatim(quote!(*mut #bare_sim_type)
-                                ))}
-                            } else {
-                                let sim_deref_stmt: Stmt = parse_quote!(
-                                    let sim = unsafe { from_ptr(sim_ptr) };
-                                );
-                                ffi_fn_statements.push(sim_deref_stmt);
-                                PatType { attrs: Vec::new(), pat: Box::new(Pat::Verbatim(quote!(sim_ptr))), colon_token: Colon::default(), ty: Box::new(Type::Verbatim(quote!(*const #bare_sim_type)
-                                ))}
-                            };
-                        
-                            FnArg::Typed(new_self_arg)
+                // &str -> *const CString, &T -> *const T, &mut T -> *mut T, T otherwise
+                FnArg::Typed(typed_arg) => {
+                    let mut modified_pattype = typed_arg.clone();
+                    let var_name = *typed_arg.pat.clone();
+                    if let Type::Reference(rfrnce) = &*typed_arg.ty {
+                        let elem = &rfrnce.elem;
+                        let is_str = matches!(&**elem, Type::Path(type_path) if type_path.path.is_ident("str"));
+                        if is_str {
+                            let mut cstring_deref_statements: Vec<Stmt> = parse_quote!(
+                                let #var_name = unsafe { #perigee::ffi::from_cstring_ptr(#var_name) };
+                                let #var_name = #var_name
+                                    .to_str()
+                                    .expect("Could not convert CStr to &str. Likely not valid UTF-8");
+                            );
+                            ffi_fn_statements.append(&mut cstring_deref_statements);
+                            modified_pattype.ty = parse_quote!(*const std::ffi::CString);
+                        } else if rfrnce.mutability.is_some() {
+                            ffi_fn_statements.push(
+                                parse_quote!(let #var_name = unsafe { #perigee::ffi::from_mut_ptr(#var_name) };),
+                            );
+                            modified_pattype.ty = parse_quote!(*mut #elem);
+                        } else {
+                            ffi_fn_statements.push(
+                                parse_quote!(let #var_name = unsafe { #perigee::ffi::from_ptr(#var_name) };),
+                            );
+                            modified_pattype.ty = parse_quote!(*const #elem);
                         }
-                        // &T -> *const T, &mut T -> *mut T, &str -> *const CString, T otherwise (Warning: don't use anything other than primitive types as fn args)
-                        FnArg::Typed(typed_arg) => {
-                            let mut modified_pattype = typed_arg.clone();
-                            let original_type = modified_pattype.ty.clone();
-
-                            let new_type = match *original_type {
-                                Type::Reference(rfrnce) => {
-                                    if let Type::Verbatim(type_tokens) = *rfrnce.elem {  
-                                        let var_name = *modified_pattype.pat.clone();
-                                        Type::Verbatim(if rfrnce.mutability.is_some() {
-                                            let type_deref_statement: Stmt = parse_quote!(
-                                                let #var_name = from_mut_ptr(#var_name);
-                                            );
-                                            ffi_fn_statements.push(type_deref_statement);
-                                            quote!(*mut #type_tokens)
-                                        } else {
-                                            let type_deref_statement: Stmt = parse_quote!(
-                                                let #var_name = from_ptr(#var_name);
-                                            );
-                                            ffi_fn_statements.push(type_deref_statement);
-                                            quote!(*const #type_tokens)
-                                        })
-                                    } else if let Type::Path(type_path) = *rfrnce.elem {
-                                        if let Some(tpath) = type_path.path.get_ident() {
-                                            if tpath == "str" {
-                                                let var_name = *modified_pattype.pat.clone();
-                                                let mut cstring_deref_statements: Vec<Stmt> = parse_quote!(
-                                                    let #var_name = unsafe { from_cstring_ptr(#var_name) };
-                                                    let #var_name = #var_name
-                                                        .to_str()
-                                                        .expect("Could not convert CStr to &str. Likely not valid UTF-8");
-                                                );
-                                                ffi_fn_statements.append(&mut cstring_deref_statements);
-                                                Type::Verbatim(quote!(*const std::ffi::CString))
-                                            } else {
-                                                Type::Path(type_path)
-                                            }
-                                        } else {
-                                            Type::Path(type_path)
-                                        }
-                                    } else {
-                                        // typed_arg.span().unwrap().error("Found reference to complex type. Please just use &T instead of &[T] or whatever you used.").emit();
-                                        Type::Reference(rfrnce)
-                                    }
-                                }
-                                Type::Verbatim(tokens) => {
-                                    Type::Verbatim(tokens)
-                                }
-                                _ => {
-                                    // typed_arg.span().unwrap().warning("Unsure how to prepare this type for FFI. Leaving it as-is.").emit();
-                                    *original_type
-                                }
-                            };
-                            modified_pattype.ty = Box::new(new_type);
-
-                            let arg_pat: Pat = *modified_pattype.pat.clone();
-                            let expr: Expr = parse_quote!(#arg_pat);
-                            invocation_args.push(expr);
-
-                            FnArg::Typed(modified_pattype)
-                        },
                     }
-            });
+                    invocation_args.push(parse_quote!(#var_name));
+                    FnArg::Typed(modified_pattype)
+                }
+            };
+            updated_fn_args.push(updated_arg);
+        }
 
-            let mut updated_fn_args: Punctuated<FnArg, Comma> = Punctuated::from_iter(updated_fn_args_iter);
+        let mut manifest_args: Vec<(String, String)> = updated_fn_args
+            .iter()
+            .filter_map(|arg| match arg {
+                FnArg::Typed(typed_arg) => Some((
+                    tokens_to_type_string(&typed_arg.pat),
+                    tokens_to_type_string(&typed_arg.ty),
+                )),
+                FnArg::Receiver(_) => None,
+            })
+            .collect();
 
-            // add invocation and maybe append a let statement and some slot insertion code
-            let invocation_args: Punctuated<Expr, Comma> = Punctuated::from_iter(invocation_args.into_iter());
-            let has_return = !matches!(fn_details.sig.output, ReturnType::Default);
-            let fn_name = fn_details.sig.ident.clone();
-            let mut must_deref_return = false;
-            let mut output_fn_return_type: Option<ReturnType> = None;
-            if has_return {
-                if slot_return {
-                    if let ReturnType::Type(_, boxed_type) = &fn_details.sig.output {
-                        let return_type = *boxed_type.clone();
-                        let return_type: Type = match return_type {
-                            Type::Verbatim(_) | Type::Path(_) => return_type,
-                            Type::Reference(rfrnce) => {
-                                must_deref_return = true;
-                                *rfrnce.elem.clone()
-                            },
-                            _ => {
-                                // fn_details.sig.output.span().unwrap().error(format!("Only types (T) and references to types (&T) can be returned from FFI functions. Found {:?}", return_type.clone())).emit();
-                                Type::Verbatim(quote!())
-                            }
+        // Call the original method through the dereferenced pointers, then
+        // maybe write its return value into a slot
+        let invocation_args: Punctuated<Expr, Comma> = Punctuated::from_iter(invocation_args);
+        let fn_name = fn_details.sig.ident.clone();
+        let invocation: Expr = if fn_details.sig.receiver().is_some() {
+            parse_quote!(<#bare_sim_type>::#fn_name(sim, #invocation_args))
+        } else {
+            parse_quote!(<#bare_sim_type>::#fn_name(#invocation_args))
+        };
+        let mut output_fn_return_type: Option<ReturnType> = None;
+        let buffer_return = match &fn_details.sig.output {
+            ReturnType::Type(_, return_type) => buffer_return_kind(return_type),
+            ReturnType::Default => None,
+        };
+        match &fn_details.sig.output {
+            // Strings and bytes are written into a pointer + length slot that the
+            // host reads from and then frees with free_bytes. Strings are written
+            // as their UTF-8 bytes without a nul terminator, so they can contain nul.
+            ReturnType::Type(_, _) if buffer_return.is_some() => {
+                ffi_fn_statements.push(parse_quote!(
+                    let returned_value = #invocation;
+                ));
+                ffi_fn_statements.push(match buffer_return {
+                    Some(BufferReturn::String) => parse_quote!(
+                        let returned_value: Box<[u8]> = Box::from(returned_value.as_bytes());
+                    ),
+                    _ => parse_quote!(
+                        let returned_value: Box<[u8]> = returned_value.into();
+                    ),
+                });
+                ffi_fn_statements.push(parse_quote!(
+                    let len = returned_value.len();
+                ));
+                ffi_fn_statements.push(parse_quote!(
+                    let ptr = Box::into_raw(returned_value) as *mut u8;
+                ));
+                ffi_fn_statements.push(parse_quote!(
+                    unsafe {
+                        *return_slot = #perigee::ffi::bytes::FfiByteBuffer { ptr, len };
+                    };
+                ));
+                updated_fn_args.push(parse_quote!(
+                    return_slot: *mut #perigee::ffi::bytes::FfiByteBuffer
+                ));
+                // Named like the engine's own buffer-returning functions
+                manifest_args.push((
+                    String::from("return_slot"),
+                    String::from("*mut FfiByteBuffer"),
+                ));
+            }
+            ReturnType::Type(_, boxed_type) if slot_return => {
+                let (return_type, must_deref_return) = match &**boxed_type {
+                    Type::Reference(rfrnce) => (*rfrnce.elem.clone(), true),
+                    return_type => (return_type.clone(), false),
+                };
+                if !types_to_slot.contains(&return_type) {
+                    types_to_slot.push(return_type.clone());
+                }
+                ffi_fn_statements.push(parse_quote!(
+                    let slotted_value = #invocation;
+                ));
+                ffi_fn_statements.push(if must_deref_return {
+                    parse_quote!(
+                        unsafe {
+                            *return_slot = *slotted_value;
                         };
-                        let new_return_type = return_type.clone();
-                        if !types_to_slot.contains(&new_return_type) {
-                            types_to_slot.push(new_return_type);
-                        }
-                        ffi_fn_statements.push(parse_quote!(
-                            let slotted_value = sim.#fn_name(#invocation_args);
-                        ));
-
-                        ffi_fn_statements.push(if must_deref_return {
-                            parse_quote!(
-                                unsafe {
-                                    *return_slot = *slotted_value;
-                                };
-                            )
-                        } else {
-                            parse_quote!(
-                                unsafe {
-                                    *return_slot = slotted_value;
-                                };
-                            )
-                        });
-                        updated_fn_args.push(parse_quote!(return_slot: *mut #return_type));
-                    } else {
-                        // fn_details.sig.output.span().unwrap().error("#[slot_return] used on function with no return type").emit();
-                    }
+                    )
                 } else {
-                    // leave the return alone if we don't slot it
-                    output_fn_return_type = Some(fn_details.sig.output.clone());
-                    let mut original_statements = fn_details.block.stmts.clone();
-                    replace_self_with_sim(&mut original_statements);
-                    ffi_fn_statements.append(&mut original_statements);
+                    parse_quote!(
+                        unsafe {
+                            *return_slot = slotted_value;
+                        };
+                    )
+                });
+                updated_fn_args.push(parse_quote!(return_slot: *mut #return_type));
+                manifest_args.push((
+                    String::from("return_slot"),
+                    tokens_to_type_string(quote!(*mut #return_type)),
+                ));
+            }
+            output => {
+                // leave the return alone if we don't slot it
+                if !matches!(output, ReturnType::Default) {
+                    output_fn_return_type = Some(output.clone());
                 }
-            } else {
-                let mut original_statements = fn_details.block.stmts.clone();
-                replace_self_with_sim(&mut original_statements);
-                ffi_fn_statements.append(&mut original_statements);
+                ffi_fn_statements.push(Stmt::Expr(invocation));
             }
-
-            // Create block and add to ffi function
-            let fn_body_block = Block {
-                brace_token: Brace::default(),
-                stmts: ffi_fn_statements,
-            };
-            let ffi_fn_definition = quote!(
-                #[no_mangle]
-                pub extern "C" fn #fn_name(#updated_fn_args) #output_fn_return_type
-                #fn_body_block
-            );
-            ffi_code.extend::<TokenStream>(
-                ffi_fn_definition.into()
-            );
         }
-    }
 
-    let type_slotting_code = generate_type_slotting_functions(&types_to_slot);
-    ffi_code.extend(type_slotting_code);
+        manifest_functions.push(ManifestFunction {
+            name: fn_name.to_string(),
+            args: manifest_args,
+            returns: match &output_fn_return_type {
+                Some(ReturnType::Type(_, return_type)) => Some(tokens_to_type_string(return_type)),
+                _ => None,
+            },
+            buffer_return: buffer_return.map(|kind| kind.manifest_name()),
+        });
 
-    let mut expanded_impl_internals: TokenStream2 = expanded_impl_internals.into();
-    let ffi_code: TokenStream2 = ffi_code.into();
-    // dbg!(quote!(#expanded_impl_internals).to_string());
-    quote! {
-        impl<'a> #bare_sim_type <'a> {
-            #expanded_impl_internals
-        }
+        // Create block and add to ffi function
+        let fn_body_block = Block {
+            brace_token: Brace::default(),
+            stmts: ffi_fn_statements,
+        };
+        ffi_code.extend(quote!(
+            #[no_mangle]
+            #[allow(clippy::not_unsafe_ptr_arg_deref)]
+            pub extern "C" fn #fn_name(#updated_fn_args) #output_fn_return_type
+            #fn_body_block
+        ));
 
-        unsafe fn from_mut_ptr<'a, T>(ptr: *mut T) -> &'a mut T {
-            &mut *ptr
+        // Methods only meant for the host stay callable by their wrappers but
+        // are hidden from Rust callers outside of this module
+        if exposure == FfiExposure::FfiOnly {
+            fn_details.vis = Visibility::Inherited;
+            fn_details.attrs.push(parse_quote!(#[allow(dead_code)]));
         }
+    }
 
-        unsafe fn from_ptr<'a, T>(ptr: *const T) -> &'a T {
-            &*ptr
+    if !errors.is_empty() {
+        let compile_errors = errors.iter().map(syn::Error::to_compile_error);
+        let original_impl = ffi_impl.item;
+        return quote! {
+            #original_impl
+            #(#compile_errors)*
         }
+        .into();
+    }
 
-        unsafe fn from_cstring_ptr<'a>(cstr_ptr: *const std::ffi::CString) -> &'a std::ffi::CStr {
-            std::ffi::CStr::from_ptr(cstr_ptr as *mut i8)
-        }
+    let manifest = manifest_json(&manifest_functions, &slotted_types_manifest(&types_to_slot));
+    let type_slotting_code: TokenStream2 = generate_type_slotting_functions(&types_to_slot).into();
 
+    // The impl block keeps its attributes, generics and where clause
+    let mut item_impl = ffi_impl.item;
+    item_impl.items.insert(
+        0,
+        parse_quote!(
+            /// JSON manifest of every function `#[ffi]` exported from this impl block.
+            pub const #manifest_name: &'static str = #manifest;
+        ),
+    );
+    quote! {
+        #item_impl
         #ffi_code
-    }.into()
+        #type_slotting_code
+    }
+    .into()
 }
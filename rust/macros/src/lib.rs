@@ -1,6 +1,7 @@
 use proc_macro::TokenStream;
 
 mod ffi;
+mod manifest;
 mod shared;
 mod slotted_types;
 
@@ -9,11 +10,34 @@ pub fn slotted_types(input: TokenStream) -> TokenStream {
     slotted_types::slotted_types(input)
 }
 
+/// Export the public methods of an impl block as `extern "C"` functions and
+/// describe them in a JSON manifest constant named `FFI_MANIFEST`. Use
+/// `#[ffi(manifest = NAME)]` to name the constant something else, e.g. when
+/// a type has several #[ffi] impl blocks.
 #[proc_macro_attribute]
 pub fn ffi(args: TokenStream, input: TokenStream) -> TokenStream {
     ffi::ffi(args, input)
 }
 
+/// Describe an `extern "C"` function exported by hand in a JSON manifest
+/// constant named after it, e.g. `ALLOC_BYTES_FFI_MANIFEST` for
+/// `alloc_bytes`. Use `#[ffi_export(buffer_return = string)]` or
+/// `#[ffi_export(buffer_return = bytes)]` for functions that write into an
+/// `FfiByteBuffer` return slot, and `manifest = NAME` to name the constant.
+#[proc_macro_attribute]
+pub fn ffi_export(args: TokenStream, input: TokenStream) -> TokenStream {
+    ffi::exports::ffi_export(args, input)
+}
+
+/// Describe the functions of an `extern "C"` block, which the host must
+/// provide, in a JSON manifest constant named `HOST_HOOKS_FFI_MANIFEST`.
+/// A `#[cfg]` on the block also removes the constant, so leave the block
+/// unconditional and gate the code calling the hooks instead.
+#[proc_macro_attribute]
+pub fn ffi_host_hooks(args: TokenStream, input: TokenStream) -> TokenStream {
+    ffi::exports::ffi_host_hooks(args, input)
+}
+
 /// This is a marker attribute used by #[ffi]. It's a no-op.
 #[proc_macro_attribute]
 pub fn slot_return(_args: TokenStream, input: TokenStream) -> TokenStream {
@@ -21,12 +45,16 @@ pub fn slot_return(_args: TokenStream, input: TokenStream) -> TokenStream {
 }
 
 /// This is a marker attribute used by #[ffi]. It's a no-op.
+///
+/// The method is exported to the host but hidden from Rust callers.
 #[proc_macro_attribute]
 pub fn ffi_only(_args: TokenStream, input: TokenStream) -> TokenStream {
     input
 }
 
 /// This is a marker attribute used by #[ffi]. It's a no-op.
+///
+/// The method stays Rust-only and no extern wrapper is generated for it.
 #[proc_macro_attribute]
 pub fn ffi_never(_args: TokenStream, input: TokenStream) -> TokenStream {
     input
@@ -0,0 +1,111 @@
+use quote::ToTokens;
+
+/// An exported function as it appears in the JSON manifest read by
+/// host glue-code generators.
+pub struct ManifestFunction {
+    pub name: String,
+    pub args: Vec<(String, String)>,
+    pub returns: Option<String>,
+    /// Whether a string or bytes are written into an `FfiByteBuffer` return slot
+    pub buffer_return: Option<&'static str>,
+}
+
+/// A slotted type along with its allocation and deallocation functions.
+pub struct ManifestSlottedType {
+    pub type_name: String,
+    pub allocate: String,
+    pub free: String,
+}
+
+/// Render tokens the way they'd be written by hand so hosts
+/// can match on type names.
+pub fn tokens_to_type_string(tokens: impl ToTokens) -> String {
+    tokens
+        .into_token_stream()
+        .to_string()
+        .replace("* mut ", "*mut ")
+        .replace("* const ", "*const ")
+        .replace("& mut ", "&mut ")
+        .replace("& ", "&")
+        .replace(" :: ", "::")
+        .replace(":: ", "::")
+        .replace(" < ", "<")
+        .replace(" >", ">")
+        .replace(" ,", ",")
+        .replace("[ ", "[")
+        .replace(" ]", "]")
+        .replace(" ;", ";")
+}
+
+fn json_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn functions_to_json(functions: &[ManifestFunction]) -> String {
+    let functions_json: Vec<String> = functions
+        .iter()
+        .map(|function| {
+            let args_json: Vec<String> = function
+                .args
+                .iter()
+                .map(|(arg_name, arg_type)| {
+                    format!(
+                        "{{\"name\":{},\"type\":{}}}",
+                        json_string(arg_name),
+                        json_string(arg_type)
+                    )
+                })
+                .collect();
+            let returns_json = match &function.returns {
+                Some(return_type) => json_string(return_type),
+                None => String::from("null"),
+            };
+            let buffer_return_json = match function.buffer_return {
+                Some(buffer_return) => json_string(buffer_return),
+                None => String::from("null"),
+            };
+            format!(
+                "{{\"name\":{},\"args\":[{}],\"returns\":{},\"buffer_return\":{}}}",
+                json_string(&function.name),
+                args_json.join(","),
+                returns_json,
+                buffer_return_json
+            )
+        })
+        .collect();
+    format!("[{}]", functions_json.join(","))
+}
+
+fn slotted_types_to_json(slotted_types: &[ManifestSlottedType]) -> String {
+    let slotted_types_json: Vec<String> = slotted_types
+        .iter()
+        .map(|slotted_type| {
+            format!(
+                "{{\"type\":{},\"allocate\":{},\"free\":{}}}",
+                json_string(&slotted_type.type_name),
+                json_string(&slotted_type.allocate),
+                json_string(&slotted_type.free)
+            )
+        })
+        .collect();
+    format!("[{}]", slotted_types_json.join(","))
+}
+
+/// Serialize exported functions and slotted types into the manifest
+/// format understood by `perigee::ffi::manifest::FfiManifest`.
+pub fn manifest_json(
+    functions: &[ManifestFunction],
+    slotted_types: &[ManifestSlottedType],
+) -> String {
+    format!(
+        "{{\"functions\":{},\"slotted_types\":{}}}",
+        functions_to_json(functions),
+        slotted_types_to_json(slotted_types)
+    )
+}
+
+/// Serialize the hooks a host must provide into the manifest
+/// format understood by `perigee::ffi::manifest::FfiManifest`.
+pub fn host_hooks_manifest_json(host_hooks: &[ManifestFunction]) -> String {
+    format!("{{\"host_hooks\":{}}}", functions_to_json(host_hooks))
+}
@@ -1,20 +1,61 @@
 use proc_macro::TokenStream;
-use proc_macro2::Span;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use proc_macro_crate::{crate_name, FoundCrate};
 use quote::{quote, quote_spanned, ToTokens};
 use syn::{spanned::Spanned, Ident, Type};
 
-pub fn generate_type_slotting_functions(types_to_slot: &Vec<Type>) -> TokenStream {
+use crate::manifest::{tokens_to_type_string, ManifestSlottedType};
+
+/// The path generated code uses to refer to the perigee crate, which
+/// is `crate` within perigee's library and may be renamed by dependents.
+pub fn perigee_crate_path() -> TokenStream2 {
+    match crate_name("perigee") {
+        // perigee's examples and integration tests are in perigee's package
+        // but link to its library like any other crate
+        Ok(FoundCrate::Itself) if std::env::var("CARGO_CRATE_NAME").as_deref() != Ok("perigee") => {
+            quote!(::perigee)
+        }
+        Ok(FoundCrate::Itself) => quote!(crate),
+        Ok(FoundCrate::Name(name)) => {
+            let crate_ident = Ident::new(&name, Span::call_site());
+            quote!(::#crate_ident)
+        }
+        Err(_) => quote!(::perigee),
+    }
+}
+
+fn lowercase_type_name(type_to_slot: &Type) -> String {
+    type_to_slot
+        .clone()
+        .into_token_stream()
+        .to_string()
+        .to_lowercase()
+        .replace(['[', ']', ';', ' '], "")
+        .replace(['<', '>', ','], "_")
+        .replace("()", "empty")
+}
+
+/// The manifest entries describing the functions generated
+/// by [generate_type_slotting_functions].
+pub fn slotted_types_manifest(types_to_slot: &[Type]) -> Vec<ManifestSlottedType> {
+    types_to_slot
+        .iter()
+        .map(|type_to_slot| {
+            let lowercase_type_name = lowercase_type_name(type_to_slot);
+            ManifestSlottedType {
+                type_name: tokens_to_type_string(type_to_slot),
+                allocate: format!("allocate_{}_space", lowercase_type_name),
+                free: format!("free_{}_space", lowercase_type_name),
+            }
+        })
+        .collect()
+}
+
+pub fn generate_type_slotting_functions(types_to_slot: &[Type]) -> TokenStream {
     let mut final_expansion = TokenStream::new();
 
-    for (i, type_to_slot) in types_to_slot.iter().enumerate() {
-        let lowercase_type_name = type_to_slot
-            .clone()
-            .into_token_stream()
-            .to_string()
-            .to_lowercase()
-            .replace(&['[', ']', ';', ' '], "")
-            .replace(&['<', '>', ','], "_")
-            .replace("()", "empty");
+    for type_to_slot in types_to_slot.iter() {
+        let lowercase_type_name = lowercase_type_name(type_to_slot);
         let alloc_fn_name = Ident::new(
             &format!("allocate_{}_space", lowercase_type_name),
             Span::call_site(),
@@ -24,17 +65,20 @@ pub fn generate_type_slotting_functions(types_to_slot: &Vec<Type>) -> TokenStrea
             Span::call_site(),
         );
 
-        let copy_assertion_struct_name =
-            Ident::new(&format!("_AssertCopy{}", i), Span::call_site());
-        let default_assertion_struct_name =
-            Ident::new(&format!("_AssertDefault{}", i), Span::call_site());
-
+        // Scoped in anonymous constants so that several expansions in
+        // one module don't define the same assertion structs
         let assert_type_impls_copy = quote_spanned! {type_to_slot.span()=>
-            struct #copy_assertion_struct_name where #type_to_slot: std::marker::Copy;
+            const _: () = {
+                #[allow(dead_code)]
+                struct AssertCopy where #type_to_slot: std::marker::Copy;
+            };
         };
 
         let assert_type_impls_default = quote_spanned! {type_to_slot.span()=>
-            struct #default_assertion_struct_name where #type_to_slot: std::default::Default;
+            const _: () = {
+                #[allow(dead_code)]
+                struct AssertDefault where #type_to_slot: std::default::Default;
+            };
         };
 
         let type_name_identifier = Ident::new(&lowercase_type_name, Span::call_site());
@@ -49,13 +93,14 @@ pub fn generate_type_slotting_functions(types_to_slot: &Vec<Type>) -> TokenStrea
             }
 
             #[no_mangle]
+            #[allow(clippy::not_unsafe_ptr_arg_deref)]
             pub extern "C" fn #free_fn_name(#type_name_identifier: *mut #type_to_slot) {
-                unsafe { Box::from_raw(#type_name_identifier) };
+                drop(unsafe { Box::from_raw(#type_name_identifier) });
             }
         };
 
         final_expansion.extend::<TokenStream>(type_expansion.into());
     }
 
-    TokenStream::from(final_expansion)
+    final_expansion
 }
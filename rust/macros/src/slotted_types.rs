@@ -1,10 +1,12 @@
 use proc_macro::TokenStream;
+use quote::quote;
 use syn::{
     parse::{Parse, ParseStream},
     parse_macro_input, Token, Type,
 };
 
-use crate::shared::generate_type_slotting_functions;
+use crate::manifest::manifest_json;
+use crate::shared::{generate_type_slotting_functions, slotted_types_manifest};
 
 struct SlottedTypes {
     types_to_slot: Vec<Type>,
@@ -28,5 +30,14 @@ impl Parse for SlottedTypes {
 pub fn slotted_types(input: TokenStream) -> TokenStream {
     let SlottedTypes { types_to_slot } = parse_macro_input!(input);
 
-    generate_type_slotting_functions(&types_to_slot)
+    let manifest = manifest_json(&[], &slotted_types_manifest(&types_to_slot));
+    let mut expansion = generate_type_slotting_functions(&types_to_slot);
+    expansion.extend::<TokenStream>(
+        quote! {
+            /// JSON manifest of the slotting functions generated by `slotted_types!`.
+            pub const SLOTTED_TYPES_FFI_MANIFEST: &str = #manifest;
+        }
+        .into(),
+    );
+    expansion
 }
@@ -1,6 +1,8 @@
 // Much of this code is adapted from
 // https://{github|gitlab}.com/aunyks/gltf-animation
 
+use crate::math::Transform3;
+use crate::perigee_gltf::extras::GltfAnimationExtras;
 use crate::perigee_gltf::util::access_gltf_bytes;
 use crate::time::PassiveClock;
 use gltf::{
@@ -8,8 +10,9 @@ use gltf::{
     animation::{Interpolation, Property as GltfProperty},
     Gltf,
 };
-use rapier3d::na::{Quaternion, UnitQuaternion, Vector3};
-use std::collections::HashMap;
+use rapier3d::na::{Isometry3, Quaternion, UnitQuaternion, Vector3};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::{Add, Mul};
 use thiserror::Error;
 
 pub fn inverse_lerp(a: f32, b: f32, value: f32) -> f32 {
@@ -29,6 +32,8 @@ pub enum AnimationCreationError {
     NoBinaryBlob,
     #[error("could not get accessor bytes")]
     CouldntAccessBytes,
+    #[error("invalid JSON stored in glTF animation extras")]
+    InvalidPerigeeExtrasData,
 }
 
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
@@ -36,13 +41,81 @@ pub enum ChannelType {
     Translation,
     Scale,
     Rotation,
+    MorphTargetWeights,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl ChannelType {
+    /// The number of floats that make up a single sampler output element
+    /// for this channel. This is `None` for morph target weights, since the
+    /// element size depends on the number of morph targets of the animated mesh.
+    fn components_per_element(&self) -> Option<usize> {
+        match self {
+            Self::Translation | Self::Scale => Some(3),
+            Self::Rotation => Some(4),
+            Self::MorphTargetWeights => None,
+        }
+    }
+
+    fn property_from_components(&self, components: &[f32]) -> AnimatedProperty {
+        match self {
+            Self::Translation => AnimatedProperty::Translation(Vector3::new(
+                components[0],
+                components[1],
+                components[2],
+            )),
+            Self::Scale => {
+                AnimatedProperty::Scale(Vector3::new(components[0], components[1], components[2]))
+            }
+            Self::Rotation => AnimatedProperty::Rotation(UnitQuaternion::from_quaternion(
+                Quaternion::new(components[3], components[0], components[1], components[2]),
+            )),
+            Self::MorphTargetWeights => AnimatedProperty::MorphTargetWeights(components.to_vec()),
+        }
+    }
+
+    fn tangent_from_components(&self, components: &[f32]) -> PropertyTangent {
+        match self {
+            Self::Translation | Self::Scale => {
+                PropertyTangent::Vector(Vector3::new(components[0], components[1], components[2]))
+            }
+            // Tangents aren't rotations, so they mustn't be normalized
+            Self::Rotation => PropertyTangent::Quaternion(Quaternion::new(
+                components[3],
+                components[0],
+                components[1],
+                components[2],
+            )),
+            Self::MorphTargetWeights => PropertyTangent::Weights(components.to_vec()),
+        }
+    }
+}
+
+/// The in or out tangent of a cubic spline keyframe.
+#[derive(Debug, Clone)]
+pub enum PropertyTangent {
+    Vector(Vector3<f32>),
+    Quaternion(Quaternion<f32>),
+    Weights(Vec<f32>),
+}
+
+/// Evaluate a cubic Hermite spline segment using the coefficients
+/// calculated in [AnimatedProperty::cubic_spline].
+fn hermite<T>(start: T, start_tangent: T, end: T, end_tangent: T, coefficients: [f32; 4]) -> T
+where
+    T: Mul<f32, Output = T> + Add<Output = T> + Copy,
+{
+    start * coefficients[0]
+        + start_tangent * coefficients[1]
+        + end * coefficients[2]
+        + end_tangent * coefficients[3]
+}
+
+#[derive(Debug, Clone)]
 pub enum AnimatedProperty {
     Translation(Vector3<f32>),
     Scale(Vector3<f32>),
     Rotation(UnitQuaternion<f32>),
+    MorphTargetWeights(Vec<f32>),
 }
 
 impl AnimatedProperty {
@@ -61,6 +134,13 @@ impl AnimatedProperty {
         }
     }
 
+    pub fn inner_weights(&self) -> Option<&[f32]> {
+        match self {
+            Self::MorphTargetWeights(weights) => Some(weights),
+            _ => None,
+        }
+    }
+
     pub fn lerp(&self, other: &Self, t: f32) -> Self {
         match (self, other) {
             (Self::Translation(lhs_vec3), Self::Translation(rhs_vec3)) => {
@@ -72,17 +152,114 @@ impl AnimatedProperty {
             (Self::Rotation(lhs_quat), Self::Rotation(rhs_quat)) => {
                 Self::Rotation(lhs_quat.slerp(rhs_quat, t))
             }
+            (Self::MorphTargetWeights(lhs_weights), Self::MorphTargetWeights(rhs_weights)) => {
+                Self::MorphTargetWeights(
+                    lhs_weights
+                        .iter()
+                        .zip(rhs_weights)
+                        .map(|(lhs_weight, rhs_weight)| lhs_weight + (rhs_weight - lhs_weight) * t)
+                        .collect(),
+                )
+            }
             _ => {
                 panic!("Can't lerp between two different animation properties!")
             }
         }
     }
+
+    /// Interpolate between this property and `other` along the cubic spline
+    /// described by this property's out-tangent and `other`'s in-tangent.
+    /// `keyframe_delta` is the number of seconds between the two keyframes.
+    ///
+    /// See the [glTF spec](https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html#interpolation-cubic)
+    /// for details.
+    pub fn cubic_spline(
+        &self,
+        out_tangent: &PropertyTangent,
+        other: &Self,
+        in_tangent: &PropertyTangent,
+        t: f32,
+        keyframe_delta: f32,
+    ) -> Self {
+        let t_squared = t * t;
+        let t_cubed = t_squared * t;
+        let coefficients = [
+            2.0 * t_cubed - 3.0 * t_squared + 1.0,
+            (t_cubed - 2.0 * t_squared + t) * keyframe_delta,
+            -2.0 * t_cubed + 3.0 * t_squared,
+            (t_cubed - t_squared) * keyframe_delta,
+        ];
+        match (self, out_tangent, other, in_tangent) {
+            (
+                Self::Translation(lhs_vec3),
+                PropertyTangent::Vector(out_vec3),
+                Self::Translation(rhs_vec3),
+                PropertyTangent::Vector(in_vec3),
+            ) => Self::Translation(hermite(
+                *lhs_vec3,
+                *out_vec3,
+                *rhs_vec3,
+                *in_vec3,
+                coefficients,
+            )),
+            (
+                Self::Scale(lhs_vec3),
+                PropertyTangent::Vector(out_vec3),
+                Self::Scale(rhs_vec3),
+                PropertyTangent::Vector(in_vec3),
+            ) => Self::Scale(hermite(
+                *lhs_vec3,
+                *out_vec3,
+                *rhs_vec3,
+                *in_vec3,
+                coefficients,
+            )),
+            (
+                Self::Rotation(lhs_quat),
+                PropertyTangent::Quaternion(out_quat),
+                Self::Rotation(rhs_quat),
+                PropertyTangent::Quaternion(in_quat),
+            ) => Self::Rotation(UnitQuaternion::from_quaternion(hermite(
+                *lhs_quat.quaternion(),
+                *out_quat,
+                *rhs_quat.quaternion(),
+                *in_quat,
+                coefficients,
+            ))),
+            (
+                Self::MorphTargetWeights(lhs_weights),
+                PropertyTangent::Weights(out_weights),
+                Self::MorphTargetWeights(rhs_weights),
+                PropertyTangent::Weights(in_weights),
+            ) => Self::MorphTargetWeights(
+                lhs_weights
+                    .iter()
+                    .zip(out_weights)
+                    .zip(rhs_weights.iter().zip(in_weights))
+                    .map(|((lhs_weight, out_weight), (rhs_weight, in_weight))| {
+                        hermite(
+                            *lhs_weight,
+                            *out_weight,
+                            *rhs_weight,
+                            *in_weight,
+                            coefficients,
+                        )
+                    })
+                    .collect(),
+            ),
+            _ => {
+                panic!("Can't interpolate between two different animation properties!")
+            }
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Keyframe {
     timestamp: f32,
     property: AnimatedProperty,
+    in_tangent: Option<PropertyTangent>,
+    out_tangent: Option<PropertyTangent>,
 }
 
 impl Keyframe {
@@ -90,6 +267,23 @@ impl Keyframe {
         Self {
             timestamp,
             property,
+            in_tangent: None,
+            out_tangent: None,
+        }
+    }
+
+    /// Create a keyframe for a cubic spline channel.
+    pub fn with_tangents(
+        timestamp: f32,
+        property: AnimatedProperty,
+        in_tangent: PropertyTangent,
+        out_tangent: PropertyTangent,
+    ) -> Self {
+        Self {
+            timestamp,
+            property,
+            in_tangent: Some(in_tangent),
+            out_tangent: Some(out_tangent),
         }
     }
 
@@ -98,12 +292,38 @@ impl Keyframe {
     }
 
     pub fn property(&self) -> AnimatedProperty {
-        self.property
+        self.property.clone()
     }
 
     pub fn lerp_property(&self, other: &Self, timestamp: f32) -> AnimatedProperty {
         let t = inverse_lerp(self.timestamp, other.timestamp(), timestamp);
-        self.property.lerp(&other.property(), t)
+        self.property.lerp(&other.property, t)
+    }
+
+    pub fn in_tangent(&self) -> Option<&PropertyTangent> {
+        self.in_tangent.as_ref()
+    }
+
+    pub fn out_tangent(&self) -> Option<&PropertyTangent> {
+        self.out_tangent.as_ref()
+    }
+
+    /// Evaluate the cubic spline between this keyframe and `other`. Falls back
+    /// to linear interpolation if either keyframe is missing its tangents.
+    pub fn cubic_spline_property(&self, other: &Self, timestamp: f32) -> AnimatedProperty {
+        match (self.out_tangent(), other.in_tangent()) {
+            (Some(out_tangent), Some(in_tangent)) => {
+                let t = inverse_lerp(self.timestamp, other.timestamp(), timestamp);
+                self.property.cubic_spline(
+                    out_tangent,
+                    &other.property,
+                    in_tangent,
+                    t,
+                    other.timestamp() - self.timestamp,
+                )
+            }
+            _ => self.lerp_property(other, timestamp),
+        }
     }
 }
 
@@ -145,10 +365,10 @@ impl AnimationChannel {
             .expect("Could not get first keyframe");
         let last_keyframe = self.keyframes.last().expect("Could not get last keyframe");
         if timestamp < first_keyframe.timestamp() {
-            return (None, Some(&first_keyframe));
+            return (None, Some(first_keyframe));
         }
         if timestamp > last_keyframe.timestamp() {
-            return (Some(&last_keyframe), None);
+            return (Some(last_keyframe), None);
         }
         let mut early_index: usize = 0;
         let mut late_index = self.keyframes.len() - 1;
@@ -206,14 +426,28 @@ impl AnimationChannel {
                 }
                 early_bound_frame.property()
             }
-            Interpolation::Linear => early_bound_frame.lerp_property(&late_bound_frame, timestamp),
+            Interpolation::Linear => early_bound_frame.lerp_property(late_bound_frame, timestamp),
             Interpolation::CubicSpline => {
-                early_bound_frame.lerp_property(&late_bound_frame, timestamp)
+                early_bound_frame.cubic_spline_property(late_bound_frame, timestamp)
             }
         }
     }
 }
 
+/// Details about an animation frame that triggered a listener.
+#[derive(Debug, Clone, Copy)]
+pub struct AnimationFrameEvent<'a> {
+    pub animation_name: &'a str,
+    pub frame: u32,
+    /// The name of the marker the listener was registered with, if any.
+    pub marker: Option<&'a str>,
+}
+
+struct FrameListener {
+    marker: Option<String>,
+    callback: Box<dyn FnMut(&AnimationFrameEvent)>,
+}
+
 /// A playable animation capable of having translation, rotation, and scale channels.
 pub struct Animation {
     passive_timer: PassiveClock,
@@ -221,10 +455,43 @@ pub struct Animation {
     duration: f32,
     target_channels: HashMap<String, HashMap<ChannelType, AnimationChannel>>,
     name: String,
-    frame_listeners: HashMap<u32, Box<dyn FnMut()>>,
+    markers: HashMap<String, f32>,
+    frame_listeners: BTreeMap<u32, Vec<FrameListener>>,
 }
 
 impl Animation {
+    /// Create an animation from channels that have already been built,
+    /// keyed by the name of the target they animate.
+    pub fn new(
+        name: &str,
+        target_channels: HashMap<String, HashMap<ChannelType, AnimationChannel>>,
+    ) -> Self {
+        let mut max_channel_duration: f32 = 0.0;
+        let mut max_channel_frames = 0;
+        for channel in target_channels
+            .values()
+            .flat_map(|channels| channels.values())
+        {
+            max_channel_duration = max_channel_duration.max(channel.duration());
+            max_channel_frames = max_channel_frames.max(channel.keyframes().len());
+        }
+        let fps = if max_channel_duration > 0.0 {
+            (max_channel_frames as f32 / max_channel_duration).round() as u32
+        } else {
+            0
+        };
+
+        Self {
+            passive_timer: PassiveClock::default(),
+            target_channels,
+            fps,
+            duration: max_channel_duration,
+            name: String::from(name),
+            markers: HashMap::new(),
+            frame_listeners: BTreeMap::new(),
+        }
+    }
+
     pub fn from_gltf(gltf: &Gltf, anim_name: &str) -> Result<Self, AnimationCreationError> {
         let gltf_bytes = match &gltf.blob {
             Some(bytes) => bytes,
@@ -233,6 +500,14 @@ impl Animation {
         for anim in gltf.animations() {
             if let Some(anim_name_candidate) = anim.name() {
                 if anim_name_candidate == anim_name {
+                    let anim_extras: GltfAnimationExtras = match anim.extras() {
+                        Some(extra_data) => match serde_json::from_str(extra_data.get()) {
+                            Ok(extras) => extras,
+                            Err(_) => return Err(AnimationCreationError::InvalidPerigeeExtrasData),
+                        },
+                        None => GltfAnimationExtras::default(),
+                    };
+
                     let mut max_channel_duration = 0.0;
                     let mut max_channel_frames = 0;
 
@@ -241,17 +516,11 @@ impl Animation {
                         HashMap<ChannelType, AnimationChannel>,
                     > = HashMap::new();
                     for channel in anim.channels() {
-                        if matches!(
-                            channel.target().property(),
-                            GltfProperty::MorphTargetWeights
-                        ) {
-                            continue;
-                        }
                         let channel_sampler = channel.sampler();
 
                         let sampler_input = channel_sampler.input();
                         let sampler_input_bytes = if let Ok(sampler_input_bytes) =
-                            access_gltf_bytes(&gltf_bytes, &sampler_input)
+                            access_gltf_bytes(gltf_bytes, &sampler_input)
                         {
                             sampler_input_bytes
                         } else {
@@ -291,96 +560,98 @@ impl Animation {
 
                         let sampler_output = channel_sampler.output();
                         let sampler_output_bytes = if let Ok(sampler_output_bytes) =
-                            access_gltf_bytes(&gltf_bytes, &sampler_output)
+                            access_gltf_bytes(gltf_bytes, &sampler_output)
                         {
                             sampler_output_bytes
                         } else {
                             return Err(AnimationCreationError::CouldntAccessBytes);
                         };
 
-                        let mut keyframe_properties: Vec<AnimatedProperty> =
-                            Vec::with_capacity(keyframe_timestamps.len());
-
-                        let channel_type: ChannelType;
-                        match channel.target().property() {
-                            GltfProperty::Translation => {
-                                channel_type = ChannelType::Translation;
-                                let flattened_vec3s: Vec<f32> = sampler_output_bytes
-                                    .chunks_exact(4)
-                                    .map(|f32_bytes| {
-                                        let f32_byte_array: [u8; 4] = f32_bytes[0..4]
-                                            .try_into()
-                                            .expect(
-                                            "Could not convert f32 byte slice into f32 byte array",
-                                        );
-                                        f32::from_le_bytes(f32_byte_array)
-                                    })
-                                    .collect();
-                                flattened_vec3s.chunks_exact(3).for_each(|vec3| {
-                                    keyframe_properties.push(AnimatedProperty::Translation(
-                                        Vector3::new(vec3[0], vec3[1], vec3[2]),
-                                    ))
-                                });
-                            }
-                            GltfProperty::Scale => {
-                                channel_type = ChannelType::Scale;
-                                let flattened_vec3s: Vec<f32> = sampler_output_bytes
-                                    .chunks_exact(4)
-                                    .map(|f32_bytes| {
-                                        let f32_byte_array: [u8; 4] = f32_bytes[0..4]
-                                            .try_into()
-                                            .expect(
-                                            "Could not convert f32 byte slice into f32 byte array",
-                                        );
-                                        f32::from_le_bytes(f32_byte_array)
-                                    })
-                                    .collect();
-                                flattened_vec3s.chunks_exact(3).for_each(|vec3| {
-                                    keyframe_properties.push(AnimatedProperty::Scale(Vector3::new(
-                                        vec3[0], vec3[1], vec3[2],
-                                    )))
-                                });
-                            }
-                            GltfProperty::Rotation => {
-                                channel_type = ChannelType::Rotation;
-                                let flattened_vec4s: Vec<f32> = sampler_output_bytes
-                                    .chunks_exact(4)
-                                    .map(|f32_bytes| {
-                                        let f32_byte_array: [u8; 4] = f32_bytes[0..4]
-                                            .try_into()
-                                            .expect(
-                                            "Could not convert f32 byte slice into f32 byte array",
-                                        );
-                                        f32::from_le_bytes(f32_byte_array)
-                                    })
-                                    .collect();
-                                flattened_vec4s.chunks_exact(4).for_each(|vec4| {
-                                    keyframe_properties.push(AnimatedProperty::Rotation(
-                                        UnitQuaternion::from_quaternion(Quaternion::new(
-                                            vec4[3], vec4[0], vec4[1], vec4[2],
-                                        )),
-                                    ))
-                                });
-                            }
-                            _ => {
-                                unreachable!();
+                        let sampler_output_floats: Vec<f32> = sampler_output_bytes
+                            .chunks_exact(4)
+                            .map(|f32_bytes| {
+                                let f32_byte_array: [u8; 4] = f32_bytes[0..4]
+                                    .try_into()
+                                    .expect("Could not convert f32 byte slice into f32 byte array");
+                                f32::from_le_bytes(f32_byte_array)
+                            })
+                            .collect();
+
+                        let channel_type = match channel.target().property() {
+                            GltfProperty::Translation => ChannelType::Translation,
+                            GltfProperty::Scale => ChannelType::Scale,
+                            GltfProperty::Rotation => ChannelType::Rotation,
+                            GltfProperty::MorphTargetWeights => ChannelType::MorphTargetWeights,
+                        };
+                        let is_cubic_spline =
+                            matches!(channel_sampler.interpolation(), Interpolation::CubicSpline);
+                        let components_per_element = match channel_type.components_per_element() {
+                            Some(components) => components,
+                            None => {
+                                // Every keyframe has one weight per morph target (or three
+                                // times that for cubic splines), so infer the target count
+                                let elements_per_keyframe = if is_cubic_spline { 3 } else { 1 };
+                                let output_elements =
+                                    keyframe_timestamps.len() * elements_per_keyframe;
+                                if sampler_output_floats.len().checked_rem(output_elements)
+                                    != Some(0)
+                                {
+                                    return Err(AnimationCreationError::MismatchedKeyframes);
+                                }
+                                sampler_output_floats.len() / output_elements
                             }
                         };
-
-                        if keyframe_timestamps.len() % keyframe_properties.len() != 0 {
+                        if components_per_element == 0 {
                             return Err(AnimationCreationError::MismatchedKeyframes);
                         }
+                        let output_elements: Vec<&[f32]> = sampler_output_floats
+                            .chunks_exact(components_per_element)
+                            .collect();
 
-                        let frames_per_property =
-                            keyframe_timestamps.len() / keyframe_properties.len();
-                        let mut keyframes: Vec<Keyframe> =
-                            Vec::with_capacity(keyframe_properties.len());
-                        for i in (0..keyframe_timestamps.len()).step_by(frames_per_property) {
-                            keyframes.push(Keyframe::new(
-                                keyframe_timestamps[i],
-                                keyframe_properties[i / frames_per_property],
-                            ));
-                        }
+                        let keyframes: Vec<Keyframe> = if is_cubic_spline {
+                            // Cubic spline samplers store an (in-tangent, value, out-tangent)
+                            // triplet for every keyframe timestamp
+                            if output_elements.len() != keyframe_timestamps.len() * 3 {
+                                return Err(AnimationCreationError::MismatchedKeyframes);
+                            }
+                            keyframe_timestamps
+                                .iter()
+                                .zip(output_elements.chunks_exact(3))
+                                .map(|(timestamp, triplet)| {
+                                    Keyframe::with_tangents(
+                                        *timestamp,
+                                        channel_type.property_from_components(triplet[1]),
+                                        channel_type.tangent_from_components(triplet[0]),
+                                        channel_type.tangent_from_components(triplet[2]),
+                                    )
+                                })
+                                .collect()
+                        } else {
+                            let keyframe_properties: Vec<AnimatedProperty> = output_elements
+                                .iter()
+                                .map(|element| channel_type.property_from_components(element))
+                                .collect();
+
+                            if keyframe_timestamps
+                                .len()
+                                .checked_rem(keyframe_properties.len())
+                                != Some(0)
+                            {
+                                return Err(AnimationCreationError::MismatchedKeyframes);
+                            }
+
+                            let frames_per_property =
+                                keyframe_timestamps.len() / keyframe_properties.len();
+                            let mut keyframes: Vec<Keyframe> =
+                                Vec::with_capacity(keyframe_properties.len());
+                            for i in (0..keyframe_timestamps.len()).step_by(frames_per_property) {
+                                keyframes.push(Keyframe::new(
+                                    keyframe_timestamps[i],
+                                    keyframe_properties[i / frames_per_property].clone(),
+                                ));
+                            }
+                            keyframes
+                        };
 
                         let new_channel =
                             AnimationChannel::new(keyframes, channel_sampler.interpolation());
@@ -401,12 +672,17 @@ impl Animation {
                         fps: (max_channel_frames as f32 / max_channel_duration).round() as u32,
                         duration: max_channel_duration,
                         name: String::from(anim_name),
-                        frame_listeners: HashMap::new(),
+                        markers: anim_extras
+                            .markers
+                            .into_iter()
+                            .map(|marker| (marker.name, marker.time))
+                            .collect(),
+                        frame_listeners: BTreeMap::new(),
                     });
                 }
             }
         }
-        return Err(AnimationCreationError::NameNotFound);
+        Err(AnimationCreationError::NameNotFound)
     }
 
     pub fn fps(&self) -> u32 {
@@ -417,14 +693,88 @@ impl Animation {
         (timestamp * self.fps() as f32).round() as u32
     }
 
-    pub fn on_frame(&mut self, frame: u32, listener: impl FnMut() + 'static) {
-        self.frame_listeners.insert(frame, Box::new(listener));
+    fn add_frame_listener(
+        &mut self,
+        frame: u32,
+        marker: Option<String>,
+        listener: impl FnMut(&AnimationFrameEvent) + 'static,
+    ) {
+        self.frame_listeners
+            .entry(frame)
+            .or_default()
+            .push(FrameListener {
+                marker,
+                callback: Box::new(listener),
+            });
+    }
+
+    /// Call `listener` whenever playback passes the provided frame. Any number
+    /// of listeners can be registered for the same frame.
+    pub fn on_frame(&mut self, frame: u32, listener: impl FnMut(&AnimationFrameEvent) + 'static) {
+        self.add_frame_listener(frame, None, listener);
+    }
+
+    /// Call `listener` whenever playback passes the frame at the provided timestamp (in seconds).
+    pub fn on_time(
+        &mut self,
+        timestamp: f32,
+        listener: impl FnMut(&AnimationFrameEvent) + 'static,
+    ) {
+        self.add_frame_listener(self.frame_at_time(timestamp), None, listener);
+    }
+
+    /// Call `listener` whenever playback passes the named marker. Returns false
+    /// if the animation has no marker with that name.
+    pub fn on_marker(
+        &mut self,
+        marker_name: &str,
+        listener: impl FnMut(&AnimationFrameEvent) + 'static,
+    ) -> bool {
+        let marker_time = match self.marker_time(marker_name) {
+            Some(time) => time,
+            None => return false,
+        };
+        self.add_frame_listener(
+            self.frame_at_time(marker_time),
+            Some(String::from(marker_name)),
+            listener,
+        );
+        true
+    }
+
+    /// Name a point on the timeline (in seconds) so listeners can be registered to it.
+    pub fn add_marker(&mut self, marker_name: impl Into<String>, timestamp: f32) {
+        self.markers.insert(marker_name.into(), timestamp);
+    }
+
+    pub fn marker_time(&self, marker_name: &str) -> Option<f32> {
+        self.markers.get(marker_name).copied()
+    }
+
+    pub fn markers(&self) -> &HashMap<String, f32> {
+        &self.markers
+    }
+
+    fn notify_listeners(listeners: &mut [FrameListener], animation_name: &str, frame: u32) {
+        for listener in listeners {
+            let event = AnimationFrameEvent {
+                animation_name,
+                frame,
+                marker: listener.marker.as_deref(),
+            };
+            (listener.callback)(&event);
+        }
     }
 
     pub fn name(&self) -> &String {
         &self.name
     }
 
+    /// The animation's channels, keyed by the name of the target they animate.
+    pub fn target_channels(&self) -> &HashMap<String, HashMap<ChannelType, AnimationChannel>> {
+        &self.target_channels
+    }
+
     pub fn duration(&self) -> f32 {
         self.duration
     }
@@ -441,7 +791,7 @@ impl Animation {
         if delta_seconds >= 0.0 {
             self.update_forward(delta_seconds);
         } else {
-            self.update_reverse(delta_seconds);
+            self.update_reverse(delta_seconds.abs());
         }
     }
 
@@ -450,15 +800,12 @@ impl Animation {
         self.passive_timer.tick(delta_seconds);
         let new_frame = self.frame_at_time(self.timeline_position());
 
-        for (listener_frame, listener) in self.frame_listeners.iter_mut() {
-            if listener_frame <= &old_frame {
-                continue;
-            }
-            if listener_frame > &new_frame {
-                continue;
-            }
-
-            listener();
+        if new_frame <= old_frame {
+            return;
+        }
+        for (listener_frame, listeners) in self.frame_listeners.range_mut(old_frame + 1..=new_frame)
+        {
+            Self::notify_listeners(listeners, &self.name, *listener_frame);
         }
     }
 
@@ -467,60 +814,254 @@ impl Animation {
         self.passive_timer.tick_reverse(delta_seconds);
         let new_frame = self.frame_at_time(self.timeline_position());
 
-        for (listener_frame, listener) in self.frame_listeners.iter_mut() {
-            if listener_frame >= &old_frame {
-                continue;
-            }
-            if listener_frame < &new_frame {
-                continue;
-            }
-
-            listener();
+        if new_frame >= old_frame {
+            return;
+        }
+        for (listener_frame, listeners) in
+            self.frame_listeners.range_mut(new_frame..old_frame).rev()
+        {
+            Self::notify_listeners(listeners, &self.name, *listener_frame);
         }
     }
 
     pub fn current_translation(&self, target_name: &str) -> Option<Vector3<f32>> {
         match self.target_channels.get(target_name) {
-            Some(channels) => match channels.get(&ChannelType::Translation) {
-                Some(channel) => Some(
-                    channel
-                        .property_at(self.passive_timer.elapsed().as_secs_f32())
-                        .inner_vector()
-                        .expect("Tried to get inner vector of Translation property but it doesn't exist"),
-                ),
-                None => None,
-            },
+            Some(channels) => channels.get(&ChannelType::Translation).map(|channel| {
+                channel
+                    .property_at(self.passive_timer.elapsed().as_secs_f32())
+                    .inner_vector()
+                    .expect(
+                        "Tried to get inner vector of Translation property but it doesn't exist",
+                    )
+            }),
             None => None,
         }
     }
 
     pub fn current_scale(&self, target_name: &str) -> Option<Vector3<f32>> {
         match self.target_channels.get(target_name) {
-            Some(channels) => match channels.get(&ChannelType::Scale) {
-                Some(channel) => Some(
-                    channel
-                        .property_at(self.passive_timer.elapsed().as_secs_f32())
-                        .inner_vector()
-                        .expect("Tried to get inner vector of Scale property but it doesn't exist"),
-                ),
-                None => None,
-            },
+            Some(channels) => channels.get(&ChannelType::Scale).map(|channel| {
+                channel
+                    .property_at(self.passive_timer.elapsed().as_secs_f32())
+                    .inner_vector()
+                    .expect("Tried to get inner vector of Scale property but it doesn't exist")
+            }),
             None => None,
         }
     }
 
     pub fn current_rotation(&self, target_name: &str) -> Option<UnitQuaternion<f32>> {
         match self.target_channels.get(target_name) {
-            Some(channels) => match channels.get(&ChannelType::Rotation) {
-                Some(channel) => Some(
-                    channel
-                        .property_at(self.passive_timer.elapsed().as_secs_f32())
-                        .inner_quaternion()
-                        .expect("Tried to get inner quaternion of Rotation property but it doesn't exist"),
-                ),
-                None => None,
-            },
+            Some(channels) => channels.get(&ChannelType::Rotation).map(|channel| {
+                channel
+                    .property_at(self.passive_timer.elapsed().as_secs_f32())
+                    .inner_quaternion()
+                    .expect(
+                        "Tried to get inner quaternion of Rotation property but it doesn't exist",
+                    )
+            }),
             None => None,
         }
     }
+
+    /// The target's local transform at the current timeline position. Any
+    /// translation, rotation or scale this animation doesn't drive is taken from
+    /// `rest_transform`.
+    pub fn current_transform(
+        &self,
+        target_name: &str,
+        rest_transform: &Transform3<f32>,
+    ) -> Transform3<f32> {
+        let translation = self
+            .current_translation(target_name)
+            .unwrap_or(rest_transform.isometry().translation.vector);
+        let rotation = self
+            .current_rotation(target_name)
+            .unwrap_or(rest_transform.isometry().rotation);
+        let scale = self
+            .current_scale(target_name)
+            .unwrap_or(*rest_transform.scale());
+        Transform3::from_parts(Isometry3::from_parts(translation.into(), rotation), scale)
+    }
+
+    /// The blend shape weights of the target's mesh at the current
+    /// timeline position, one per morph target. Returns `None` if the
+    /// target has no weights channel or the channel holds other properties.
+    pub fn current_morph_weights(&self, target_name: &str) -> Option<Vec<f32>> {
+        let channel = self
+            .target_channels
+            .get(target_name)?
+            .get(&ChannelType::MorphTargetWeights)?;
+        match channel.property_at(self.passive_timer.elapsed().as_secs_f32()) {
+            AnimatedProperty::MorphTargetWeights(weights) => Some(weights),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cubic_spline_with_flat_tangents_eases() {
+        let flat_tangent = PropertyTangent::Vector(Vector3::zeros());
+        let channel = AnimationChannel::new(
+            vec![
+                Keyframe::with_tangents(
+                    0.0,
+                    AnimatedProperty::Translation(Vector3::zeros()),
+                    flat_tangent.clone(),
+                    flat_tangent.clone(),
+                ),
+                Keyframe::with_tangents(
+                    2.0,
+                    AnimatedProperty::Translation(Vector3::new(4.0, 0.0, 0.0)),
+                    flat_tangent.clone(),
+                    flat_tangent.clone(),
+                ),
+            ],
+            Interpolation::CubicSpline,
+        );
+
+        let halfway = channel.property_at(1.0).inner_vector().unwrap();
+        assert!((halfway.x - 2.0).abs() < 1e-5);
+        // Flat tangents ease in, so a quarter of the way through
+        // we've covered less than a quarter of the distance
+        let quarter_way = channel.property_at(0.5).inner_vector().unwrap();
+        assert!((quarter_way.x - 0.625).abs() < 1e-5);
+        let end = channel.property_at(2.0).inner_vector().unwrap();
+        assert!((end.x - 4.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn cubic_spline_with_linear_tangents_matches_lerp() {
+        // The curve's slope is 2 units per second
+        let tangent = PropertyTangent::Vector(Vector3::new(0.0, 2.0, 0.0));
+        let start = Keyframe::with_tangents(
+            0.0,
+            AnimatedProperty::Translation(Vector3::zeros()),
+            tangent.clone(),
+            tangent.clone(),
+        );
+        let end = Keyframe::with_tangents(
+            2.0,
+            AnimatedProperty::Translation(Vector3::new(0.0, 4.0, 0.0)),
+            tangent.clone(),
+            tangent.clone(),
+        );
+        for timestamp in [0.25, 0.5, 1.0, 1.5] {
+            let spline = start
+                .cubic_spline_property(&end, timestamp)
+                .inner_vector()
+                .unwrap();
+            let lerped = start.lerp_property(&end, timestamp).inner_vector().unwrap();
+            assert!((spline - lerped).norm() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn cubic_spline_rotations_are_normalized() {
+        let tangent = PropertyTangent::Quaternion(Quaternion::new(0.3, 0.1, 0.5, 0.0));
+        let start = Keyframe::with_tangents(
+            0.0,
+            AnimatedProperty::Rotation(UnitQuaternion::identity()),
+            tangent.clone(),
+            tangent.clone(),
+        );
+        let end = Keyframe::with_tangents(
+            1.0,
+            AnimatedProperty::Rotation(UnitQuaternion::from_euler_angles(0.0, 1.5, 0.0)),
+            tangent.clone(),
+            tangent.clone(),
+        );
+        let rotation = start
+            .cubic_spline_property(&end, 0.4)
+            .inner_quaternion()
+            .unwrap();
+        assert!((rotation.quaternion().norm() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn morph_target_weights_interpolate_per_target() {
+        let channel = AnimationChannel::new(
+            vec![
+                Keyframe::new(0.0, AnimatedProperty::MorphTargetWeights(vec![0.0, 1.0])),
+                Keyframe::new(1.0, AnimatedProperty::MorphTargetWeights(vec![1.0, 0.0])),
+            ],
+            Interpolation::Linear,
+        );
+        let weights = channel.property_at(0.25);
+        let weights = weights.inner_weights().unwrap();
+        assert!((weights[0] - 0.25).abs() < 1e-5);
+        assert!((weights[1] - 0.75).abs() < 1e-5);
+
+        // A weights channel holding other properties is skipped rather than panicking
+        let animation = Animation::new(
+            "Blink",
+            HashMap::from([(
+                String::from("Face"),
+                HashMap::from([(
+                    ChannelType::MorphTargetWeights,
+                    AnimationChannel::new(
+                        vec![Keyframe::new(
+                            0.0,
+                            AnimatedProperty::Translation(Vector3::zeros()),
+                        )],
+                        Interpolation::Linear,
+                    ),
+                )]),
+            )]),
+        );
+        assert_eq!(animation.current_morph_weights("Face"), None);
+    }
+
+    #[test]
+    fn every_frame_listener_fires_with_marker_context() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let mut animation = Animation::new(
+            "Walk",
+            HashMap::from([(
+                String::from("Body"),
+                HashMap::from([(
+                    ChannelType::Translation,
+                    AnimationChannel::new(
+                        (0..=10)
+                            .map(|frame| {
+                                Keyframe::new(
+                                    frame as f32 / 10.0,
+                                    AnimatedProperty::Translation(Vector3::zeros()),
+                                )
+                            })
+                            .collect(),
+                        Interpolation::Linear,
+                    ),
+                )]),
+            )]),
+        );
+        animation.add_marker("footstep_left", 0.3);
+
+        let fired: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
+        let fired_by_frame = fired.clone();
+        animation.on_frame(3, move |event| {
+            fired_by_frame
+                .borrow_mut()
+                .push(format!("{} frame {}", event.animation_name, event.frame));
+        });
+        let fired_by_marker = fired.clone();
+        assert!(animation.on_marker("footstep_left", move |event| {
+            fired_by_marker
+                .borrow_mut()
+                .push(String::from(event.marker.unwrap()));
+        }));
+        assert!(!animation.on_marker("footstep_right", |_| {}));
+
+        animation.update(0.5);
+        assert_eq!(
+            *fired.borrow(),
+            vec![String::from("Walk frame 3"), String::from("footstep_left")]
+        );
+    }
 }
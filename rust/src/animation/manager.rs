@@ -2,14 +2,36 @@ use std::collections::hash_map::IntoIter;
 use std::collections::HashMap;
 
 use gltf::Gltf;
+use rapier3d::na::{Isometry3, Vector3};
 
 use crate::animation::asset::Animation;
-use crate::ffi::{loop_animation, stop_animation};
+use crate::event_channel::EventChannel;
+use crate::ffi::{loop_animation, play_animation, stop_animation};
+use crate::math::Transform3;
+use log::warn;
 
-#[derive(PartialEq, Eq)]
-enum RepeatMode {
+/// How an animation behaves once its timeline reaches an end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepeatMode {
+    /// Wrap around to the other end of the timeline.
     Loop,
+    /// Keep advancing the timeline without any end behavior.
     None,
+    /// Stop playing and deactivate at the end of the timeline.
+    Once,
+    /// Reverse playback direction at each end of the timeline.
+    PingPong,
+    /// Hold the final pose at the end of the timeline while staying active.
+    Clamp,
+}
+
+/// Events emitted by the [AnimationManager](crate::animation::AnimationManager)
+/// as animations play.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnimationEvent {
+    /// The named animation reached the end of its timeline. This is only
+    /// emitted for animations played with [RepeatMode::Once] or [RepeatMode::Clamp].
+    Finished(String),
 }
 
 /// An animation asset with additional playback details.
@@ -17,7 +39,12 @@ pub struct DetailedAnimation {
     pub animation: Animation,
     pub time_scale: f32,
     pub is_active: bool,
+    /// How much this animation contributes when it's blended
+    /// with other active animations driving the same target.
+    pub weight: f32,
     repeat_mode: RepeatMode,
+    /// Either 1.0 or -1.0. Flipped at each end of the timeline when ping-ponging.
+    playback_direction: f32,
 }
 
 impl DetailedAnimation {
@@ -26,16 +53,123 @@ impl DetailedAnimation {
             animation: anim,
             time_scale: 1.0,
             is_active: false,
+            weight: 1.0,
             repeat_mode: RepeatMode::None,
+            playback_direction: 1.0,
+        }
+    }
+
+    pub fn repeat_mode(&self) -> RepeatMode {
+        self.repeat_mode
+    }
+
+    fn restart(&mut self, repeat_mode: RepeatMode) {
+        self.is_active = true;
+        self.repeat_mode = repeat_mode;
+        self.playback_direction = 1.0;
+        let start_position = if self.time_scale < 0.0 {
+            self.animation.duration()
+        } else {
+            0.0
+        };
+        self.animation.set_timeline_position(start_position);
+    }
+
+    /// Advance the animation by `delta_seconds` (already scaled by the time scale),
+    /// applying its repeat mode. Returns whether the animation reached the end
+    /// of its timeline.
+    fn advance(&mut self, delta_seconds: f32) -> bool {
+        let animation = &mut self.animation;
+        let duration = animation.duration();
+        let timeline_pos = animation.timeline_position();
+        match self.repeat_mode {
+            RepeatMode::Loop => {
+                let new_timeline_pos = timeline_pos + delta_seconds;
+                if new_timeline_pos > duration {
+                    animation.set_timeline_position(new_timeline_pos - duration);
+                }
+                if new_timeline_pos <= 0.0 {
+                    animation.set_timeline_position(duration - new_timeline_pos);
+                }
+                animation.update(delta_seconds);
+                false
+            }
+            RepeatMode::None => {
+                animation.update(delta_seconds);
+                false
+            }
+            RepeatMode::Once | RepeatMode::Clamp => {
+                let new_timeline_pos = timeline_pos + delta_seconds;
+                if delta_seconds >= 0.0 && new_timeline_pos >= duration {
+                    if timeline_pos >= duration {
+                        // Already holding the final pose
+                        return false;
+                    }
+                    animation.update(duration - timeline_pos);
+                    true
+                } else if delta_seconds < 0.0 && new_timeline_pos <= 0.0 {
+                    if timeline_pos <= 0.0 {
+                        return false;
+                    }
+                    animation.update(-timeline_pos);
+                    true
+                } else {
+                    animation.update(delta_seconds);
+                    false
+                }
+            }
+            RepeatMode::PingPong => {
+                let delta_seconds = delta_seconds * self.playback_direction;
+                let new_timeline_pos = timeline_pos + delta_seconds;
+                if new_timeline_pos > duration {
+                    animation.update(duration - timeline_pos);
+                    self.playback_direction = -self.playback_direction;
+                    animation.update(-(new_timeline_pos - duration).min(duration));
+                } else if new_timeline_pos < 0.0 {
+                    animation.update(-timeline_pos);
+                    self.playback_direction = -self.playback_direction;
+                    animation.update((-new_timeline_pos).min(duration));
+                } else {
+                    animation.update(delta_seconds);
+                }
+                false
+            }
         }
     }
 }
 
+/// A transition of blend weight from one animation to another, starting
+/// from the weights the animations had when it began.
+struct Crossfade {
+    from: String,
+    /// `None` when `from` is only fading out.
+    to: Option<String>,
+    from_start_weight: f32,
+    to_start_weight: f32,
+    duration: f32,
+    elapsed: f32,
+}
+
+impl Crossfade {
+    fn involves(&self, anim_name: &str) -> bool {
+        self.from == anim_name || self.to.as_deref() == Some(anim_name)
+    }
+
+    fn progress(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+        (self.elapsed / self.duration).clamp(0.0, 1.0)
+    }
+}
+
 /// An animation mixer / manager capable of playing multiple simulataneous
 /// animations with varying playback rates (including reverse playback).
 #[derive(Default)]
 pub struct AnimationManager {
     map: HashMap<String, DetailedAnimation>,
+    crossfades: Vec<Crossfade>,
+    events: EventChannel<AnimationEvent>,
 }
 
 impl IntoIterator for AnimationManager {
@@ -88,45 +222,392 @@ impl AnimationManager {
         self.map.remove(name)
     }
 
+    /// Events emitted as animations play, such as an animation finishing.
+    /// Drain these each frame with [get_message](crate::event_channel::EventChannel::get_message).
+    pub fn events(&self) -> &EventChannel<AnimationEvent> {
+        &self.events
+    }
+
     pub fn update(&mut self, delta_seconds: f32) {
-        for detailed_animation in self.map.values_mut().filter(|danim| danim.is_active) {
-            let animation = &mut detailed_animation.animation;
-            if detailed_animation.repeat_mode == RepeatMode::Loop {
-                let new_timeline_pos =
-                    animation.timeline_position() + delta_seconds * detailed_animation.time_scale;
-                if new_timeline_pos > animation.duration() {
-                    animation.set_timeline_position(new_timeline_pos - animation.duration());
+        for (anim_name, detailed_animation) in
+            self.map.iter_mut().filter(|(_, danim)| danim.is_active)
+        {
+            let reached_end =
+                detailed_animation.advance(delta_seconds * detailed_animation.time_scale);
+            if reached_end {
+                if detailed_animation.repeat_mode == RepeatMode::Once {
+                    detailed_animation.is_active = false;
                 }
-                if new_timeline_pos <= 0.0 {
-                    animation.set_timeline_position(animation.duration() - new_timeline_pos);
+                self.events
+                    .send(AnimationEvent::Finished(anim_name.clone()));
+            }
+        }
+        self.update_crossfades(delta_seconds.abs());
+    }
+
+    fn update_crossfades(&mut self, delta_seconds: f32) {
+        for crossfade in self.crossfades.iter_mut() {
+            crossfade.elapsed += delta_seconds;
+        }
+        let crossfades = std::mem::take(&mut self.crossfades);
+        for crossfade in crossfades {
+            self.apply_crossfade(&crossfade);
+            if crossfade.progress() < 1.0 {
+                self.crossfades.push(crossfade);
+            }
+        }
+    }
+
+    fn apply_crossfade(&mut self, crossfade: &Crossfade) {
+        let progress = crossfade.progress();
+        if let Some(to_anim) = crossfade.to.as_ref().and_then(|to| self.map.get_mut(to)) {
+            to_anim.weight =
+                crossfade.to_start_weight + (1.0 - crossfade.to_start_weight) * progress;
+        }
+        if let Some(from_anim) = self.map.get_mut(&crossfade.from) {
+            if progress >= 1.0 {
+                from_anim.is_active = false;
+                from_anim.weight = 1.0;
+            } else {
+                from_anim.weight = crossfade.from_start_weight * (1.0 - progress);
+            }
+        }
+    }
+
+    /// The weight the named animation currently contributes to the blend.
+    fn blend_weight(&self, anim_name: &str) -> f32 {
+        match self.map.get(anim_name) {
+            Some(detailed_anim) if detailed_anim.is_active => detailed_anim.weight,
+            _ => 0.0,
+        }
+    }
+
+    /// Blend from one animation to another over `duration` seconds. The `to` animation
+    /// is activated with its current repeat mode and its weight rises from its current
+    /// weight (0 if it's inactive) to 1 while the `from` animation's falls from its current
+    /// weight to 0. Once the fade completes the `from` animation is deactivated.
+    ///
+    /// A crossfade replaces any crossfade already in progress for either animation.
+    /// The other animations of a replaced crossfade fade out over the same `duration`.
+    pub fn crossfade(&mut self, from: &str, to: &str, duration: f32) {
+        if !self.map.contains_key(from) || !self.map.contains_key(to) {
+            warn!(
+                "Can't crossfade from animation {} to {}. One of them doesn't exist",
+                from, to
+            );
+            return;
+        }
+        // Crossfades already involving these animations are replaced, picking up
+        // from the weights they've blended the animations to so far
+        let (superseded, crossfades): (Vec<Crossfade>, Vec<Crossfade>) =
+            std::mem::take(&mut self.crossfades)
+                .into_iter()
+                .partition(|crossfade| crossfade.involves(from) || crossfade.involves(to));
+        self.crossfades = crossfades;
+
+        let mut new_crossfades = vec![Crossfade {
+            from: String::from(from),
+            to: Some(String::from(to)),
+            from_start_weight: self.blend_weight(from),
+            to_start_weight: self.blend_weight(to),
+            duration,
+            elapsed: 0.0,
+        }];
+        for crossfade in superseded {
+            for anim_name in std::iter::once(crossfade.from).chain(crossfade.to) {
+                let already_fading = new_crossfades
+                    .iter()
+                    .any(|new_crossfade| new_crossfade.involves(&anim_name));
+                if !already_fading && self.blend_weight(&anim_name) > 0.0 {
+                    new_crossfades.push(Crossfade {
+                        from_start_weight: self.blend_weight(&anim_name),
+                        from: anim_name,
+                        to: None,
+                        to_start_weight: 0.0,
+                        duration,
+                        elapsed: 0.0,
+                    });
                 }
             }
-            animation.update(delta_seconds * detailed_animation.time_scale);
+        }
+        if let Some(to_anim) = self.map.get_mut(to) {
+            to_anim.is_active = true;
+        }
+
+        for crossfade in new_crossfades {
+            self.apply_crossfade(&crossfade);
+            if crossfade.progress() < 1.0 {
+                self.crossfades.push(crossfade);
+            }
         }
     }
 
+    /// Whether the named animation is currently part of a crossfade.
+    pub fn is_crossfading(&self, anim_name: &str) -> bool {
+        self.crossfades
+            .iter()
+            .any(|crossfade| crossfade.involves(anim_name))
+    }
+
+    /// The pose of the named target after blending every active animation that drives it
+    /// using their weights. Translation and scale are averaged and rotations are slerped.
+    /// Returns `None` if no active animation drives the target.
+    pub fn sampled_transform(&self, target_name: &str) -> Option<Transform3<f32>> {
+        self.sampled_transform_from_rest(target_name, &Transform3::identity())
+    }
+
+    /// Like [sampled_transform](Self::sampled_transform), but any translation, rotation
+    /// or scale that no active animation drives is taken from `rest_transform` instead.
+    pub fn sampled_transform_from_rest(
+        &self,
+        target_name: &str,
+        rest_transform: &Transform3<f32>,
+    ) -> Option<Transform3<f32>> {
+        // Blend in a stable order so that results don't depend on HashMap iteration order
+        let mut anim_names: Vec<&String> = self
+            .map
+            .iter()
+            .filter(|(_, danim)| danim.is_active && danim.weight > 0.0)
+            .map(|(name, _)| name)
+            .collect();
+        anim_names.sort();
+
+        let mut translation_sum = Vector3::zeros();
+        let mut translation_weight = 0.0;
+        let mut scale_sum = Vector3::zeros();
+        let mut scale_weight = 0.0;
+        let mut rotation = rest_transform.isometry().rotation;
+        let mut rotation_weight = 0.0;
+        for anim_name in anim_names {
+            let detailed_animation = &self.map[anim_name];
+            let animation = &detailed_animation.animation;
+            let weight = detailed_animation.weight;
+            if let Some(translation) = animation.current_translation(target_name) {
+                translation_sum += translation * weight;
+                translation_weight += weight;
+            }
+            if let Some(scale) = animation.current_scale(target_name) {
+                scale_sum += scale * weight;
+                scale_weight += weight;
+            }
+            if let Some(anim_rotation) = animation.current_rotation(target_name) {
+                rotation_weight += weight;
+                rotation = rotation.slerp(&anim_rotation, weight / rotation_weight);
+            }
+        }
+
+        if translation_weight <= 0.0 && scale_weight <= 0.0 && rotation_weight <= 0.0 {
+            return None;
+        }
+        let translation = if translation_weight > 0.0 {
+            translation_sum / translation_weight
+        } else {
+            rest_transform.isometry().translation.vector
+        };
+        let scale = if scale_weight > 0.0 {
+            scale_sum / scale_weight
+        } else {
+            *rest_transform.scale()
+        };
+        Some(Transform3::from_parts(
+            Isometry3::from_parts(translation.into(), rotation),
+            scale,
+        ))
+    }
+
     pub fn loop_animation(&mut self, anim_name: &str, scene_object_name: Option<&str>) {
         if let Some(detailed_anim) = self.get_mut(anim_name) {
-            let scene_object_name = if let Some(name) = scene_object_name {
-                name
-            } else {
-                ""
-            };
+            let scene_object_name = scene_object_name.unwrap_or_default();
             detailed_anim.is_active = true;
             detailed_anim.repeat_mode = RepeatMode::Loop;
             loop_animation(scene_object_name, anim_name, detailed_anim.time_scale);
         }
     }
 
+    /// Play the named animation from the start of its timeline (or the end, if its
+    /// time scale is negative) once. It's deactivated when it finishes.
+    pub fn play_animation(&mut self, anim_name: &str, scene_object_name: Option<&str>) {
+        if let Some(detailed_anim) = self.get_mut(anim_name) {
+            let scene_object_name = scene_object_name.unwrap_or_default();
+            detailed_anim.restart(RepeatMode::Once);
+            play_animation(scene_object_name, anim_name, detailed_anim.time_scale);
+        }
+    }
+
+    /// Play the named animation from the start of its timeline (or the end, if its
+    /// time scale is negative) and hold its final pose once it finishes.
+    pub fn clamp_animation(&mut self, anim_name: &str, scene_object_name: Option<&str>) {
+        if let Some(detailed_anim) = self.get_mut(anim_name) {
+            let scene_object_name = scene_object_name.unwrap_or_default();
+            detailed_anim.restart(RepeatMode::Clamp);
+            play_animation(scene_object_name, anim_name, detailed_anim.time_scale);
+        }
+    }
+
+    /// Repeatedly play the named animation forwards then backwards.
+    ///
+    /// Note: There's no ping-pong interface hook, so the interface is told to loop the animation.
+    pub fn ping_pong_animation(&mut self, anim_name: &str, scene_object_name: Option<&str>) {
+        if let Some(detailed_anim) = self.get_mut(anim_name) {
+            let scene_object_name = scene_object_name.unwrap_or_default();
+            detailed_anim.is_active = true;
+            detailed_anim.repeat_mode = RepeatMode::PingPong;
+            detailed_anim.playback_direction = 1.0;
+            loop_animation(scene_object_name, anim_name, detailed_anim.time_scale);
+        }
+    }
+
     pub fn stop_animation(&mut self, anim_name: &str, scene_object_name: Option<&str>) {
         if let Some(detailed_anim) = self.get_mut(anim_name) {
-            let scene_object_name = if let Some(name) = scene_object_name {
-                name
-            } else {
-                ""
-            };
+            let scene_object_name = scene_object_name.unwrap_or_default();
             detailed_anim.is_active = false;
             stop_animation(scene_object_name, anim_name);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{AnimatedProperty, AnimationChannel, ChannelType, Keyframe};
+    use gltf::animation::Interpolation;
+
+    fn constant_translation_animation(name: &str, translation: Vector3<f32>) -> Animation {
+        let channel = AnimationChannel::new(
+            vec![
+                Keyframe::new(0.0, AnimatedProperty::Translation(translation)),
+                Keyframe::new(1.0, AnimatedProperty::Translation(translation)),
+            ],
+            Interpolation::Linear,
+        );
+        Animation::new(
+            name,
+            HashMap::from([(
+                String::from("Door"),
+                HashMap::from([(ChannelType::Translation, channel)]),
+            )]),
+        )
+    }
+
+    #[test]
+    fn crossfade_blends_and_deactivates_source() {
+        let mut manager = AnimationManager::new();
+        manager.add(constant_translation_animation("Closed", Vector3::zeros()));
+        manager.add(constant_translation_animation(
+            "Open",
+            Vector3::new(2.0, 0.0, 0.0),
+        ));
+        manager.loop_animation("Closed", None);
+
+        manager.crossfade("Closed", "Open", 1.0);
+        manager.update(0.25);
+        let door_transform = manager.sampled_transform("Door").unwrap();
+        assert!((door_transform.isometry().translation.x - 0.5).abs() < 1e-5);
+        assert!(manager.is_crossfading("Open"));
+
+        manager.update(1.0);
+        assert!(!manager.is_crossfading("Open"));
+        assert!(!manager.get("Closed").unwrap().is_active);
+        let door_transform = manager.sampled_transform("Door").unwrap();
+        assert!((door_transform.isometry().translation.x - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn superseding_crossfades_start_from_blended_weights() {
+        let mut manager = AnimationManager::new();
+        manager.add(constant_translation_animation("Closed", Vector3::zeros()));
+        manager.add(constant_translation_animation(
+            "Ajar",
+            Vector3::new(2.0, 0.0, 0.0),
+        ));
+        manager.add(constant_translation_animation(
+            "Open",
+            Vector3::new(4.0, 0.0, 0.0),
+        ));
+        manager.loop_animation("Closed", None);
+        let door_x = |manager: &AnimationManager| {
+            manager
+                .sampled_transform("Door")
+                .unwrap()
+                .isometry()
+                .translation
+                .x
+        };
+
+        manager.crossfade("Closed", "Ajar", 1.0);
+        manager.update(0.5);
+        assert!((door_x(&manager) - 1.0).abs() < 1e-5);
+
+        // The pose doesn't jump when a crossfade is replaced mid-fade
+        manager.crossfade("Ajar", "Open", 1.0);
+        assert!((manager.get("Ajar").unwrap().weight - 0.5).abs() < 1e-5);
+        assert!((door_x(&manager) - 1.0).abs() < 1e-5);
+        assert!(manager.is_crossfading("Closed"));
+
+        // Both earlier animations fade out alongside the new crossfade
+        manager.update(0.5);
+        assert!((manager.get("Closed").unwrap().weight - 0.25).abs() < 1e-5);
+        assert!((manager.get("Ajar").unwrap().weight - 0.25).abs() < 1e-5);
+        assert!((door_x(&manager) - 2.5).abs() < 1e-5);
+
+        manager.update(0.6);
+        assert!(!manager.is_crossfading("Closed"));
+        assert!(!manager.get("Closed").unwrap().is_active);
+        assert!(!manager.get("Ajar").unwrap().is_active);
+        assert!((door_x(&manager) - 4.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sampled_transform_of_unanimated_target_is_none() {
+        let mut manager = AnimationManager::new();
+        manager.add(constant_translation_animation("Closed", Vector3::zeros()));
+        manager.loop_animation("Closed", None);
+        assert!(manager.sampled_transform("Window").is_none());
+    }
+
+    #[test]
+    fn play_once_finishes_and_deactivates() {
+        let mut manager = AnimationManager::new();
+        manager.add(constant_translation_animation("Attack", Vector3::zeros()));
+        manager.play_animation("Attack", None);
+
+        manager.update(0.6);
+        assert!(manager.events().get_message().is_err());
+        manager.update(0.6);
+        assert_eq!(
+            manager.events().get_message(),
+            Ok(AnimationEvent::Finished(String::from("Attack")))
+        );
+        let attack = manager.get("Attack").unwrap();
+        assert!(!attack.is_active);
+        assert!((attack.animation.timeline_position() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn clamp_holds_final_pose_and_finishes_once() {
+        let mut manager = AnimationManager::new();
+        manager.add(constant_translation_animation("Open", Vector3::zeros()));
+        manager.clamp_animation("Open", None);
+
+        manager.update(1.5);
+        manager.update(1.5);
+        assert_eq!(
+            manager.events().get_message(),
+            Ok(AnimationEvent::Finished(String::from("Open")))
+        );
+        assert!(manager.events().get_message().is_err());
+        assert!(manager.get("Open").unwrap().is_active);
+    }
+
+    #[test]
+    fn ping_pong_reverses_at_the_end() {
+        let mut manager = AnimationManager::new();
+        manager.add(constant_translation_animation("Bob", Vector3::zeros()));
+        manager.ping_pong_animation("Bob", None);
+
+        manager.update(0.75);
+        manager.update(0.5);
+        let bob = manager.get("Bob").unwrap();
+        assert!((bob.animation.timeline_position() - 0.75).abs() < 1e-5);
+    }
+}
@@ -1,5 +1,7 @@
 mod asset;
 mod manager;
+mod skeleton;
 
 pub use crate::animation::asset::*;
 pub use crate::animation::manager::*;
+pub use crate::animation::skeleton::*;
@@ -0,0 +1,419 @@
+use crate::animation::asset::Animation;
+use crate::animation::manager::AnimationManager;
+use crate::math::Transform3;
+use crate::perigee_gltf::util::{access_gltf_bytes, node_local_transform};
+use gltf::{Gltf, Node};
+use rapier3d::na::{Matrix4, Vector3};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SkeletonCreationError {
+    #[error("could not find skin name in glTF document")]
+    NameNotFound,
+    #[error("could not find a binary blob in glTF document")]
+    NoBinaryBlob,
+    #[error("could not get accessor bytes")]
+    CouldntAccessBytes,
+    #[error("skin doesn't have an inverse bind matrix for every joint")]
+    MismatchedInverseBindMatrices,
+    #[error("skin joint nodes must have names")]
+    UnnamedJoint,
+}
+
+/// A single bone of a [Skeleton].
+#[derive(Debug, Clone)]
+pub struct Joint {
+    name: String,
+    parent: Option<usize>,
+    rest_transform: Transform3<f32>,
+    inverse_bind_matrix: Matrix4<f32>,
+}
+
+impl Joint {
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    /// The index of this joint's parent joint in its [Skeleton], if it has one.
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+
+    /// The joint's transform relative to its parent when it isn't animated.
+    pub fn rest_transform(&self) -> &Transform3<f32> {
+        &self.rest_transform
+    }
+
+    pub fn inverse_bind_matrix(&self) -> &Matrix4<f32> {
+        &self.inverse_bind_matrix
+    }
+}
+
+/// A joint hierarchy loaded from a glTF skin. Skeletons evaluate the world-space
+/// transforms of their joints from animation poses so that hit volumes and
+/// attachment points can follow bones.
+#[derive(Debug, Clone)]
+pub struct Skeleton {
+    name: String,
+    /// Ordered so that every joint comes after its parent.
+    joints: Vec<Joint>,
+    joint_indices: HashMap<String, usize>,
+    /// The global transform of the node above each root joint, keyed by joint index.
+    root_parent_transforms: HashMap<usize, Transform3<f32>>,
+    /// The inverse of the global transform of the node the skin is attached to.
+    inverse_mesh_matrix: Matrix4<f32>,
+}
+
+fn transform_matrix(transform: &Transform3<f32>) -> Matrix4<f32> {
+    transform.isometry().to_homogeneous() * Matrix4::new_nonuniform_scaling(transform.scale())
+}
+
+impl Skeleton {
+    /// Load the named skin from a glTF. `parent_transform` places the whole
+    /// scene in the world, just like in
+    /// [load_from_gltf](crate::physics::PhysicsWorld::load_from_gltf).
+    pub fn from_gltf(
+        gltf: &Gltf,
+        skin_name: &str,
+        parent_transform: Option<Transform3<f32>>,
+    ) -> Result<Self, SkeletonCreationError> {
+        let gltf_bytes = match &gltf.blob {
+            Some(bytes) => bytes,
+            None => return Err(SkeletonCreationError::NoBinaryBlob),
+        };
+        let skin = match gltf.skins().find(|skin| skin.name() == Some(skin_name)) {
+            Some(skin) => skin,
+            None => return Err(SkeletonCreationError::NameNotFound),
+        };
+
+        let mut node_parents: HashMap<usize, usize> = HashMap::new();
+        for node in gltf.nodes() {
+            for child_node in node.children() {
+                node_parents.insert(child_node.index(), node.index());
+            }
+        }
+        let nodes: Vec<Node> = gltf.nodes().collect();
+        let root_transform = parent_transform.unwrap_or(Transform3::identity());
+        let global_rest_transform = |node_index: usize| -> Transform3<f32> {
+            let mut ancestry = vec![node_index];
+            while let Some(parent_index) = node_parents.get(ancestry.last().unwrap()) {
+                ancestry.push(*parent_index);
+            }
+            ancestry
+                .into_iter()
+                .rev()
+                .fold(root_transform, |global_transform, ancestor_index| {
+                    global_transform * node_local_transform(&nodes[ancestor_index])
+                })
+        };
+
+        let joint_nodes: Vec<Node> = skin.joints().collect();
+        let inverse_bind_matrices: Vec<Matrix4<f32>> = match skin.inverse_bind_matrices() {
+            Some(accessor) => {
+                let matrix_bytes = match access_gltf_bytes(gltf_bytes, &accessor) {
+                    Ok(bytes) => bytes,
+                    Err(_) => return Err(SkeletonCreationError::CouldntAccessBytes),
+                };
+                let matrix_floats: Vec<f32> = matrix_bytes
+                    .chunks_exact(4)
+                    .map(|f32_bytes| {
+                        let f32_byte_array: [u8; 4] = f32_bytes[0..4]
+                            .try_into()
+                            .expect("Could not convert f32 byte slice into f32 byte array");
+                        f32::from_le_bytes(f32_byte_array)
+                    })
+                    .collect();
+                // glTF matrices are stored in column-major order
+                matrix_floats
+                    .chunks_exact(16)
+                    .take(joint_nodes.len())
+                    .map(Matrix4::from_column_slice)
+                    .collect()
+            }
+            None => vec![Matrix4::identity(); joint_nodes.len()],
+        };
+        if inverse_bind_matrices.len() != joint_nodes.len() {
+            return Err(SkeletonCreationError::MismatchedInverseBindMatrices);
+        }
+
+        // Order joints by depth so parents are always evaluated before their children
+        let node_depth = |node_index: usize| -> usize {
+            let mut depth = 0;
+            let mut current_index = node_index;
+            while let Some(parent_index) = node_parents.get(&current_index) {
+                depth += 1;
+                current_index = *parent_index;
+            }
+            depth
+        };
+        let mut joint_order: Vec<usize> = (0..joint_nodes.len()).collect();
+        joint_order.sort_by_key(|joint_index| node_depth(joint_nodes[*joint_index].index()));
+
+        let mut joints: Vec<Joint> = Vec::with_capacity(joint_nodes.len());
+        let mut joint_indices: HashMap<String, usize> = HashMap::new();
+        let mut node_joint_indices: HashMap<usize, usize> = HashMap::new();
+        let mut root_parent_transforms: HashMap<usize, Transform3<f32>> = HashMap::new();
+        for original_index in joint_order {
+            let joint_node = &joint_nodes[original_index];
+            let joint_name = match joint_node.name() {
+                Some(name) => String::from(name),
+                None => return Err(SkeletonCreationError::UnnamedJoint),
+            };
+            let parent_node_index = node_parents.get(&joint_node.index()).copied();
+            let parent = parent_node_index
+                .and_then(|parent_index| node_joint_indices.get(&parent_index).copied());
+            let joint_index = joints.len();
+            if parent.is_none() {
+                root_parent_transforms.insert(
+                    joint_index,
+                    parent_node_index
+                        .map(global_rest_transform)
+                        .unwrap_or(root_transform),
+                );
+            }
+
+            node_joint_indices.insert(joint_node.index(), joint_index);
+            joint_indices.insert(joint_name.clone(), joint_index);
+            joints.push(Joint {
+                name: joint_name,
+                parent,
+                rest_transform: node_local_transform(joint_node),
+                inverse_bind_matrix: inverse_bind_matrices[original_index],
+            });
+        }
+
+        // Skinned vertices are defined relative to the mesh node using the skin, so its
+        // global transform is undone when computing skinning matrices
+        let mesh_transform = gltf
+            .nodes()
+            .find(|node| node.skin().map(|node_skin| node_skin.index()) == Some(skin.index()))
+            .map(|mesh_node| global_rest_transform(mesh_node.index()))
+            .unwrap_or(root_transform);
+        let inverse_mesh_matrix = transform_matrix(&mesh_transform)
+            .try_inverse()
+            .unwrap_or(Matrix4::identity());
+
+        Ok(Self {
+            name: String::from(skin_name),
+            joints,
+            joint_indices,
+            root_parent_transforms,
+            inverse_mesh_matrix,
+        })
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    /// The skeleton's joints, ordered so that every joint comes after its parent.
+    pub fn joints(&self) -> &Vec<Joint> {
+        &self.joints
+    }
+
+    pub fn joint_index(&self, joint_name: &str) -> Option<usize> {
+        self.joint_indices.get(joint_name).copied()
+    }
+
+    fn evaluate_global_transforms(
+        &self,
+        local_transform: impl Fn(&Joint) -> Transform3<f32>,
+    ) -> Vec<Transform3<f32>> {
+        let mut global_transforms: Vec<Transform3<f32>> = Vec::with_capacity(self.joints.len());
+        for (joint_index, joint) in self.joints.iter().enumerate() {
+            let parent_transform = match joint.parent {
+                Some(parent_index) => global_transforms[parent_index],
+                None => self
+                    .root_parent_transforms
+                    .get(&joint_index)
+                    .copied()
+                    .unwrap_or(Transform3::identity()),
+            };
+            global_transforms.push(parent_transform * local_transform(joint));
+        }
+        global_transforms
+    }
+
+    /// The world-space transform of every joint (in the order of [joints](Self::joints))
+    /// posed by the provided animation at its current timeline position.
+    pub fn global_joint_transforms(&self, animation: &Animation) -> Vec<Transform3<f32>> {
+        self.evaluate_global_transforms(|joint| {
+            animation.current_transform(&joint.name, &joint.rest_transform)
+        })
+    }
+
+    /// The world-space transform of every joint (in the order of [joints](Self::joints))
+    /// posed by blending all of the manager's active animations.
+    pub fn blended_global_joint_transforms(
+        &self,
+        animations: &AnimationManager,
+    ) -> Vec<Transform3<f32>> {
+        self.evaluate_global_transforms(|joint| {
+            animations
+                .sampled_transform_from_rest(&joint.name, &joint.rest_transform)
+                .unwrap_or(joint.rest_transform)
+        })
+    }
+
+    /// The world-space transform of the named joint posed by the provided animation.
+    pub fn global_joint_transform(
+        &self,
+        joint_name: &str,
+        animation: &Animation,
+    ) -> Option<Transform3<f32>> {
+        let joint_index = self.joint_index(joint_name)?;
+        Some(self.global_joint_transforms(animation)[joint_index])
+    }
+
+    /// The world-space position of the named joint posed by the provided animation.
+    pub fn joint_position(&self, joint_name: &str, animation: &Animation) -> Option<Vector3<f32>> {
+        self.global_joint_transform(joint_name, animation)
+            .map(|transform| transform.isometry().translation.vector)
+    }
+
+    /// The joint matrices used to deform a skinned mesh. As the glTF spec describes, each is
+    /// the inverse of the skinned mesh node's global transform, multiplied by the joint's
+    /// global transform and its inverse bind matrix. The matrices therefore deform vertices
+    /// in the mesh node's local space, and are identity when the skeleton is at its bind pose.
+    pub fn skinning_matrices(&self, animation: &Animation) -> Vec<Matrix4<f32>> {
+        self.global_joint_transforms(animation)
+            .iter()
+            .zip(self.joints.iter())
+            .map(|(global_transform, joint)| {
+                self.inverse_mesh_matrix
+                    * transform_matrix(global_transform)
+                    * joint.inverse_bind_matrix
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rapier3d::na::{Isometry3, Translation3, UnitQuaternion};
+    use std::f32::consts::PI;
+
+    fn isometry_matrix(translation: Vector3<f32>, rotation: UnitQuaternion<f32>) -> Matrix4<f32> {
+        Isometry3::from_parts(Translation3::from(translation), rotation).to_homogeneous()
+    }
+
+    /// A GLB with an armature holding a two-joint skin, plus a skinned mesh node beside it.
+    fn skinned_glb() -> Vec<u8> {
+        let hips_rotation = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 0.5 * PI);
+        let armature = isometry_matrix(Vector3::new(1.0, 0.0, 0.0), UnitQuaternion::identity());
+        let hips = armature * isometry_matrix(Vector3::new(0.0, 1.0, 0.0), hips_rotation);
+        let spine = hips * isometry_matrix(Vector3::new(0.0, 1.0, 0.0), UnitQuaternion::identity());
+        let body =
+            armature * isometry_matrix(Vector3::new(0.0, 0.0, 2.0), UnitQuaternion::identity());
+
+        // Inverse bind matrices are relative to the skinned mesh node and follow the
+        // skin's joint order, which deliberately lists the child joint first
+        let mut binary_chunk: Vec<u8> = vec![];
+        for joint in [spine, hips] {
+            let inverse_bind_matrix = joint.try_inverse().unwrap() * body;
+            for value in inverse_bind_matrix.as_slice() {
+                binary_chunk.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        let json = serde_json::json!({
+            "asset": { "version": "2.0" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [
+                { "name": "Armature", "translation": [1.0, 0.0, 0.0], "children": [1, 3] },
+                {
+                    "name": "Hips",
+                    "translation": [0.0, 1.0, 0.0],
+                    "rotation": [
+                        hips_rotation.i,
+                        hips_rotation.j,
+                        hips_rotation.k,
+                        hips_rotation.w
+                    ],
+                    "children": [2]
+                },
+                { "name": "Spine", "translation": [0.0, 1.0, 0.0] },
+                { "name": "Body", "translation": [0.0, 0.0, 2.0], "skin": 0 }
+            ],
+            "skins": [{ "name": "Rig", "joints": [2, 1], "inverseBindMatrices": 0 }],
+            "accessors": [{
+                "bufferView": 0,
+                "componentType": 5126,
+                "count": 2,
+                "type": "MAT4"
+            }],
+            "bufferViews": [{ "buffer": 0, "byteLength": binary_chunk.len() }],
+            "buffers": [{ "byteLength": binary_chunk.len() }]
+        });
+        let mut json_chunk = serde_json::to_vec(&json).unwrap();
+        // GLB chunks are aligned to 4 bytes, and JSON chunks are padded with spaces
+        json_chunk.resize(json_chunk.len().div_ceil(4) * 4, b' ');
+
+        let total_length = 12 + 8 + json_chunk.len() + 8 + binary_chunk.len();
+        let mut glb: Vec<u8> = vec![];
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(total_length as u32).to_le_bytes());
+        glb.extend_from_slice(&(json_chunk.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json_chunk);
+        glb.extend_from_slice(&(binary_chunk.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&binary_chunk);
+        glb
+    }
+
+    fn assert_near(actual: Vector3<f32>, expected: Vector3<f32>) {
+        assert!(
+            (actual - expected).norm() < 1e-5,
+            "expected {expected:?}, got {actual:?}"
+        );
+    }
+
+    #[test]
+    fn skin_joints_are_posed_in_world_space() {
+        let gltf = Gltf::from_slice(&skinned_glb()).unwrap();
+        let rest = Animation::new("Rest", HashMap::new());
+
+        let skeleton = Skeleton::from_gltf(&gltf, "Rig", None).unwrap();
+        // Parents are ordered before their children regardless of the skin's joint order
+        assert_eq!(skeleton.joint_index("Hips"), Some(0));
+        assert_eq!(skeleton.joint_index("Spine"), Some(1));
+        assert_eq!(skeleton.joints()[1].parent(), Some(0));
+        assert_near(
+            skeleton.joint_position("Hips", &rest).unwrap(),
+            Vector3::new(1.0, 1.0, 0.0),
+        );
+        // The hips are turned a quarter around Z, so the spine points along -X
+        assert_near(
+            skeleton.joint_position("Spine", &rest).unwrap(),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+
+        let placement = Transform3::from(Isometry3::translation(0.0, 0.0, 5.0));
+        let placed_skeleton = Skeleton::from_gltf(&gltf, "Rig", Some(placement)).unwrap();
+        assert_near(
+            placed_skeleton.joint_position("Spine", &rest).unwrap(),
+            Vector3::new(0.0, 1.0, 5.0),
+        );
+        assert!(Skeleton::from_gltf(&gltf, "Missing", None).is_err());
+    }
+
+    #[test]
+    fn skinning_matrices_are_identity_at_bind_pose() {
+        let gltf = Gltf::from_slice(&skinned_glb()).unwrap();
+        let rest = Animation::new("Rest", HashMap::new());
+        let placement = Transform3::from(Isometry3::translation(3.0, -2.0, 5.0));
+
+        for parent_transform in [None, Some(placement)] {
+            let skeleton = Skeleton::from_gltf(&gltf, "Rig", parent_transform).unwrap();
+            let skinning_matrices = skeleton.skinning_matrices(&rest);
+            assert_eq!(skinning_matrices.len(), 2);
+            for skinning_matrix in skinning_matrices {
+                assert!((skinning_matrix - Matrix4::identity()).norm() < 1e-5);
+            }
+        }
+    }
+}
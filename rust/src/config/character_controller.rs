@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+/// Tuning parameters for a [CharacterController](crate::physics::CharacterController).
+/// Lengths are in meters, angles in degrees and speeds in meters per second.
+/// Any parameters left out of a TOML file take their default values.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+#[serde(default)]
+pub struct CharacterControllerConfig {
+    capsule_radius: f32,
+    /// Half the height of the capsule's cylindrical part.
+    capsule_half_height: f32,
+    skin_width: f32,
+    max_slope_climb_degrees: f32,
+    min_slope_slide_degrees: f32,
+    max_step_height: f32,
+    min_step_width: f32,
+    ground_snap_distance: f32,
+    max_speed: f32,
+    ground_acceleration: f32,
+    air_acceleration: f32,
+    jump_speed: f32,
+}
+
+impl Default for CharacterControllerConfig {
+    fn default() -> Self {
+        Self {
+            capsule_radius: 0.4,
+            capsule_half_height: 0.5,
+            skin_width: 0.02,
+            max_slope_climb_degrees: 45.0,
+            min_slope_slide_degrees: 30.0,
+            max_step_height: 0.3,
+            min_step_width: 0.2,
+            ground_snap_distance: 0.2,
+            max_speed: 5.0,
+            ground_acceleration: 40.0,
+            air_acceleration: 10.0,
+            jump_speed: 5.0,
+        }
+    }
+}
+
+impl CharacterControllerConfig {
+    pub fn capsule_radius(&self) -> f32 {
+        self.capsule_radius
+    }
+
+    pub fn capsule_half_height(&self) -> f32 {
+        self.capsule_half_height
+    }
+
+    /// The gap kept between the capsule and its surroundings.
+    pub fn skin_width(&self) -> f32 {
+        self.skin_width
+    }
+
+    /// The steepest slope the character can walk up.
+    pub fn max_slope_climb_degrees(&self) -> f32 {
+        self.max_slope_climb_degrees
+    }
+
+    /// Slopes steeper than this make the character slide down.
+    pub fn min_slope_slide_degrees(&self) -> f32 {
+        self.min_slope_slide_degrees
+    }
+
+    /// The tallest obstacle the character steps onto. Stepping is off when this is 0.
+    pub fn max_step_height(&self) -> f32 {
+        self.max_step_height
+    }
+
+    /// How much free space an obstacle must have on top to be stepped onto.
+    pub fn min_step_width(&self) -> f32 {
+        self.min_step_width
+    }
+
+    /// How far below its feet the character looks for ground to stick to when
+    /// walking down slopes and steps. Snapping is off when this is 0.
+    pub fn ground_snap_distance(&self) -> f32 {
+        self.ground_snap_distance
+    }
+
+    pub fn max_speed(&self) -> f32 {
+        self.max_speed
+    }
+
+    /// How quickly the character reaches its target speed while grounded, in meters per second squared.
+    pub fn ground_acceleration(&self) -> f32 {
+        self.ground_acceleration
+    }
+
+    /// How quickly the character reaches its target speed while airborne, in meters per second squared.
+    pub fn air_acceleration(&self) -> f32 {
+        self.air_acceleration
+    }
+
+    pub fn jump_speed(&self) -> f32 {
+        self.jump_speed
+    }
+}
@@ -1,3 +1,5 @@
+mod character_controller;
 mod physics;
 
+pub use character_controller::CharacterControllerConfig;
 pub use physics::PhysicsConfig;
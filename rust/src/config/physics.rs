@@ -1,11 +1,63 @@
+use crate::physics::{CollisionLayerError, CollisionLayers};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+fn default_fixed_timestep() -> f32 {
+    1.0 / 60.0
+}
+
+fn default_max_substeps() -> u32 {
+    8
+}
+
+/// The fields of a [PhysicsConfig] as they're written, before they're validated.
+#[derive(Deserialize)]
+struct PhysicsConfigFields {
+    gravity: [f32; 3],
+    event_queue_capacity: usize,
+    #[serde(default = "default_fixed_timestep")]
+    fixed_timestep: f32,
+    #[serde(default = "default_max_substeps")]
+    max_substeps: u32,
+    #[serde(default)]
+    collision_layers: Vec<String>,
+    #[serde(default)]
+    collision_layer_interactions: HashMap<String, Vec<String>>,
+}
 
 /// Configuration parameters for the [PhysicsWorld](crate::physics::PhysicsWorld).
 /// These should not be editable at runtime.
-#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+///
+/// Deserializing a config fails if its collision layers are invalid, e.g. if
+/// an interaction names a layer that isn't defined.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(try_from = "PhysicsConfigFields")]
 pub struct PhysicsConfig {
     gravity: [f32; 3],
     event_queue_capacity: usize,
+    fixed_timestep: f32,
+    max_substeps: u32,
+    collision_layers: Vec<String>,
+    collision_layer_interactions: HashMap<String, Vec<String>>,
+}
+
+impl TryFrom<PhysicsConfigFields> for PhysicsConfig {
+    type Error = CollisionLayerError;
+
+    fn try_from(fields: PhysicsConfigFields) -> Result<Self, Self::Error> {
+        CollisionLayers::new(
+            &fields.collision_layers,
+            &fields.collision_layer_interactions,
+        )?;
+        Ok(Self {
+            gravity: fields.gravity,
+            event_queue_capacity: fields.event_queue_capacity,
+            fixed_timestep: fields.fixed_timestep,
+            max_substeps: fields.max_substeps,
+            collision_layers: fields.collision_layers,
+            collision_layer_interactions: fields.collision_layer_interactions,
+        })
+    }
 }
 
 impl Default for PhysicsConfig {
@@ -13,6 +65,10 @@ impl Default for PhysicsConfig {
         Self {
             gravity: [0.0, -9.81, 0.0],
             event_queue_capacity: 5,
+            fixed_timestep: default_fixed_timestep(),
+            max_substeps: default_max_substeps(),
+            collision_layers: vec![],
+            collision_layer_interactions: HashMap::new(),
         }
     }
 }
@@ -25,4 +81,25 @@ impl PhysicsConfig {
     pub fn event_queue_capacity(&self) -> usize {
         self.event_queue_capacity
     }
+
+    /// The duration of a single [FixedStepper](crate::physics::FixedStepper) tick in seconds.
+    pub fn fixed_timestep(&self) -> f32 {
+        self.fixed_timestep
+    }
+
+    /// The most ticks a [FixedStepper](crate::physics::FixedStepper) takes in one frame.
+    pub fn max_substeps(&self) -> u32 {
+        self.max_substeps
+    }
+
+    /// The names of the [CollisionLayers](crate::physics::CollisionLayers), at most 32.
+    pub fn collision_layers(&self) -> &[String] {
+        &self.collision_layers
+    }
+
+    /// The layers each collision layer interacts with. Layers that aren't
+    /// listed interact with every layer.
+    pub fn collision_layer_interactions(&self) -> &HashMap<String, Vec<String>> {
+        &self.collision_layer_interactions
+    }
 }
@@ -5,7 +5,7 @@ use std::ops::Deref;
 use std::rc::Rc;
 
 /// A bidirectional HashMap.
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BiMap<A, B>
 where
     A: Eq + Hash + ?Sized,
@@ -15,6 +15,17 @@ where
     right_to_left: HashMap<Rc<B>, Rc<A>>,
 }
 
+// Not derived since that would require the keys to implement Default
+impl<A, B> Default for BiMap<A, B>
+where
+    A: Eq + Hash,
+    B: Eq + Hash,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<A, B> BiMap<A, B>
 where
     A: Eq + Hash,
@@ -58,6 +69,7 @@ where
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
@@ -38,10 +38,7 @@ impl<T> EventChannel<T> {
 
     pub fn eviscerate(&self) -> Result<(), TryRecvError> {
         while !self.receiver.is_empty() {
-            match self.get_message() {
-                Err(e) => return Err(e),
-                _ => {}
-            }
+            self.get_message()?;
         }
         Ok(())
     }
@@ -1,4 +1,7 @@
+use macros::ffi_export;
+
 #[no_mangle]
+#[ffi_export]
 pub extern "C" fn alloc_bytes(num_bytes: usize) -> *mut u8 {
     let reserved_bytes: Vec<u8> = vec![7; num_bytes];
     let mut boxed_byte_slice = reserved_bytes.into_boxed_slice();
@@ -10,6 +13,42 @@ pub extern "C" fn alloc_bytes(num_bytes: usize) -> *mut u8 {
 }
 
 #[no_mangle]
+#[ffi_export]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn free_bytes(bytes_ptr: *mut u8, slice_len: usize) {
-    unsafe { Vec::from_raw_parts(bytes_ptr, slice_len, slice_len) };
+    drop(unsafe { Vec::from_raw_parts(bytes_ptr, slice_len, slice_len) });
+}
+
+/// A pointer and length written into a return slot by `#[ffi]` methods that
+/// return strings or bytes. The host reads `len` bytes starting at `ptr`, then
+/// releases them with [free_bytes]. Strings are written as UTF-8 without a
+/// nul terminator.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FfiByteBuffer {
+    pub ptr: *mut u8,
+    pub len: usize,
+}
+
+impl Default for FfiByteBuffer {
+    fn default() -> Self {
+        Self {
+            ptr: std::ptr::null_mut(),
+            len: 0,
+        }
+    }
+}
+
+#[no_mangle]
+#[ffi_export]
+pub extern "C" fn allocate_ffi_byte_buffer_space() -> *mut FfiByteBuffer {
+    Box::into_raw(Box::default())
+}
+
+/// Free a return slot. This doesn't free the bytes the slot points to.
+#[no_mangle]
+#[ffi_export]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn free_ffi_byte_buffer_space(buffer_ptr: *mut FfiByteBuffer) {
+    drop(unsafe { Box::from_raw(buffer_ptr) });
 }
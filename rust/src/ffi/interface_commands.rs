@@ -1,9 +1,15 @@
 //! Functions that communicate directly with the
 //! interface when compiled to WebAssembly.
-#[cfg(not(feature = "ffi"))]
+//!
+//! Unit tests use the logging stand-ins even with the `ffi` feature,
+//! since the interface's hooks aren't linked into test binaries. The hooks
+//! are declared in every build so that their manifest can be generated, but
+//! they're only called with the `ffi` feature.
+#[cfg(any(not(feature = "ffi"), test))]
 use log::debug;
+use macros::ffi_host_hooks;
 
-#[cfg(feature = "ffi")]
+#[ffi_host_hooks]
 extern "C" {
     fn play_audio_hook(
         scene_obj_name_ptr: *const u8,
@@ -52,7 +58,7 @@ extern "C" {
         announcement_msg_name_len: usize,
     );
 }
-#[cfg(feature = "ffi")]
+#[cfg(all(feature = "ffi", not(test)))]
 pub fn play_audio(scene_object_name: &str, audio_name: &str, playback_rate: f32, volume: f32) {
     unsafe {
         play_audio_hook(
@@ -67,7 +73,7 @@ pub fn play_audio(scene_object_name: &str, audio_name: &str, playback_rate: f32,
 }
 
 /// Play the named audio track once from the perspective of the active camera.
-#[cfg(not(feature = "ffi"))]
+#[cfg(any(not(feature = "ffi"), test))]
 pub fn play_audio(scene_object_name: &str, audio_name: &str, playback_rate: f32, volume: f32) {
     debug!(
         "Play Audio: (Scene Object: {}, Audio Name: {}, Playback Rate: {}, Volume: {})",
@@ -75,7 +81,7 @@ pub fn play_audio(scene_object_name: &str, audio_name: &str, playback_rate: f32,
     );
 }
 
-#[cfg(feature = "ffi")]
+#[cfg(all(feature = "ffi", not(test)))]
 pub fn loop_audio(scene_object_name: &str, audio_name: &str, playback_rate: f32, volume: f32) {
     unsafe {
         loop_audio_hook(
@@ -91,7 +97,7 @@ pub fn loop_audio(scene_object_name: &str, audio_name: &str, playback_rate: f32,
 
 /// Repeatedly play the named audio track from the perspective of the active camera until
 /// told to stop.
-#[cfg(not(feature = "ffi"))]
+#[cfg(any(not(feature = "ffi"), test))]
 pub fn loop_audio(scene_object_name: &str, audio_name: &str, playback_rate: f32, volume: f32) {
     debug!(
         "Loop Audio: (Scene Object: {}, Audio Name: {}, Playback Rate: {}, Volume: {})",
@@ -99,7 +105,7 @@ pub fn loop_audio(scene_object_name: &str, audio_name: &str, playback_rate: f32,
     );
 }
 
-#[cfg(feature = "ffi")]
+#[cfg(all(feature = "ffi", not(test)))]
 pub fn stop_audio(scene_object_name: &str, audio_name: &str) {
     unsafe {
         stop_audio_hook(
@@ -112,7 +118,7 @@ pub fn stop_audio(scene_object_name: &str, audio_name: &str) {
 }
 
 /// Stop playing the named audio track from the perspective of the active camera.
-#[cfg(not(feature = "ffi"))]
+#[cfg(any(not(feature = "ffi"), test))]
 pub fn stop_audio(scene_object_name: &str, audio_name: &str) {
     debug!(
         "Stop Audio: (Scene Object: {}, Audio Name: {})",
@@ -120,7 +126,7 @@ pub fn stop_audio(scene_object_name: &str, audio_name: &str) {
     );
 }
 
-#[cfg(feature = "ffi")]
+#[cfg(all(feature = "ffi", not(test)))]
 pub fn play_animation(scene_object_name: &str, anim_name: &str, time_scale: f32) {
     unsafe {
         play_animation_hook(
@@ -134,7 +140,7 @@ pub fn play_animation(scene_object_name: &str, anim_name: &str, time_scale: f32)
 }
 
 /// Repeatedly play the named animation on the named scene object.
-#[cfg(not(feature = "ffi"))]
+#[cfg(any(not(feature = "ffi"), test))]
 pub fn play_animation(scene_object_name: &str, anim_name: &str, time_scale: f32) {
     debug!(
         "Play Animation: (Scene Object: {}, Animation Name: {}, Time Scale: {})",
@@ -142,7 +148,7 @@ pub fn play_animation(scene_object_name: &str, anim_name: &str, time_scale: f32)
     );
 }
 
-#[cfg(feature = "ffi")]
+#[cfg(all(feature = "ffi", not(test)))]
 pub fn loop_animation(scene_object_name: &str, anim_name: &str, time_scale: f32) {
     unsafe {
         loop_animation_hook(
@@ -156,7 +162,7 @@ pub fn loop_animation(scene_object_name: &str, anim_name: &str, time_scale: f32)
 }
 
 /// Repeatedly play the named animation on the named scene object.
-#[cfg(not(feature = "ffi"))]
+#[cfg(any(not(feature = "ffi"), test))]
 pub fn loop_animation(scene_object_name: &str, anim_name: &str, time_scale: f32) {
     debug!(
         "Loop Animation: (Scene Object: {}, Animation Name: {}, Time Scale: {})",
@@ -164,7 +170,7 @@ pub fn loop_animation(scene_object_name: &str, anim_name: &str, time_scale: f32)
     );
 }
 
-#[cfg(feature = "ffi")]
+#[cfg(all(feature = "ffi", not(test)))]
 pub fn stop_animation(scene_object_name: &str, anim_name: &str) {
     unsafe {
         stop_animation_hook(
@@ -177,7 +183,7 @@ pub fn stop_animation(scene_object_name: &str, anim_name: &str) {
 }
 
 /// Stop playing the named animation on the named scene object.
-#[cfg(not(feature = "ffi"))]
+#[cfg(any(not(feature = "ffi"), test))]
 pub fn stop_animation(scene_object_name: &str, anim_name: &str) {
     debug!(
         "Stop Animation: (Scene Object: {}, Animation Name: {})",
@@ -185,7 +191,7 @@ pub fn stop_animation(scene_object_name: &str, anim_name: &str) {
     );
 }
 
-#[cfg(feature = "ffi")]
+#[cfg(all(feature = "ffi", not(test)))]
 pub fn assistive_device_announce(announcement_msg_name: &str) {
     unsafe {
         assistive_device_announce_hook(announcement_msg_name.as_ptr(), announcement_msg_name.len());
@@ -193,7 +199,7 @@ pub fn assistive_device_announce(announcement_msg_name: &str) {
 }
 
 /// Announce the provided named message to the user through an assistive device.
-#[cfg(not(feature = "ffi"))]
+#[cfg(any(not(feature = "ffi"), test))]
 pub fn assistive_device_announce(announcement_msg_name: &str) {
     debug!(
         "Assistive Device Announcement ID: {}",
@@ -0,0 +1,507 @@
+//! A machine-readable description of the ABI exported by `#[ffi]` and
+//! `slotted_types!`, along with a generator for TypeScript host bindings.
+use crate::ffi::{bytes, interface_commands, queries, strings};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FfiArgument {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub arg_type: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FfiFunction {
+    pub name: String,
+    pub args: Vec<FfiArgument>,
+    pub returns: Option<String>,
+    /// Whether the function writes a string or bytes into an
+    /// [FfiByteBuffer](crate::ffi::bytes::FfiByteBuffer) return slot.
+    #[serde(default)]
+    pub buffer_return: Option<FfiBufferReturn>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FfiBufferReturn {
+    /// UTF-8 without a nul terminator, freed with `free_bytes`
+    #[serde(rename = "string")]
+    String,
+    /// Freed with `free_bytes`
+    #[serde(rename = "bytes")]
+    Bytes,
+}
+
+impl FfiFunction {
+    fn new(name: &str, args: &[(&str, &str)], returns: Option<&str>) -> Self {
+        Self {
+            name: String::from(name),
+            args: args
+                .iter()
+                .map(|(arg_name, arg_type)| FfiArgument {
+                    name: String::from(*arg_name),
+                    arg_type: String::from(*arg_type),
+                })
+                .collect(),
+            returns: returns.map(String::from),
+            buffer_return: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FfiSlottedType {
+    #[serde(rename = "type")]
+    pub slotted_type: String,
+    pub allocate: String,
+    pub free: String,
+}
+
+/// Every function exported to and imported from the host. Combine the
+/// manifests generated by the macros with [FfiManifest::engine] to describe
+/// the full ABI of a simulation.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct FfiManifest {
+    #[serde(default)]
+    pub functions: Vec<FfiFunction>,
+    #[serde(default)]
+    pub slotted_types: Vec<FfiSlottedType>,
+    #[serde(default)]
+    pub host_hooks: Vec<FfiFunction>,
+}
+
+impl FfiManifest {
+    /// Parse a manifest like the `FFI_MANIFEST` constant generated by `#[ffi]`.
+    pub fn from_json(manifest_json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(manifest_json)
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// The string, byte buffer and scene query functions exported by Perigee itself
+    /// and the hooks it imports from the host, as described by `#[ffi_export]`
+    /// and `#[ffi_host_hooks]` on their declarations.
+    pub fn engine() -> Self {
+        let mut engine = Self::default();
+        for manifest_json in [
+            strings::ALLOC_STRING_FFI_MANIFEST,
+            strings::FREE_STRING_FFI_MANIFEST,
+            bytes::ALLOC_BYTES_FFI_MANIFEST,
+            bytes::FREE_BYTES_FFI_MANIFEST,
+            bytes::ALLOCATE_FFI_BYTE_BUFFER_SPACE_FFI_MANIFEST,
+            bytes::FREE_FFI_BYTE_BUFFER_SPACE_FFI_MANIFEST,
+            queries::PHYSICS_WORLD_RAYCAST_FFI_MANIFEST,
+            queries::PHYSICS_WORLD_SPHERE_CAST_FFI_MANIFEST,
+            queries::PHYSICS_WORLD_OVERLAP_SPHERE_FFI_MANIFEST,
+            queries::PHYSICS_WORLD_OVERLAP_BOX_FFI_MANIFEST,
+            interface_commands::HOST_HOOKS_FFI_MANIFEST,
+        ] {
+            engine.merge(
+                Self::from_json(manifest_json)
+                    .expect("Manifests generated by the macros are valid"),
+            );
+        }
+        engine
+    }
+
+    /// The engine's manifest merged with manifests generated by the macros,
+    /// e.g. `[Sim::FFI_MANIFEST, SLOTTED_TYPES_FFI_MANIFEST]`.
+    pub fn with_engine(manifest_jsons: &[&str]) -> Result<Self, serde_json::Error> {
+        let mut manifest = Self::engine();
+        for manifest_json in manifest_jsons {
+            manifest.merge(Self::from_json(manifest_json)?);
+        }
+        Ok(manifest)
+    }
+
+    /// Write the manifest as JSON and its TypeScript bindings to the given paths,
+    /// e.g. from a small bin run by a web host's build.
+    pub fn write_files(
+        &self,
+        json_path: impl AsRef<Path>,
+        typescript_path: impl AsRef<Path>,
+    ) -> io::Result<()> {
+        fs::write(json_path, self.to_json()?)?;
+        fs::write(typescript_path, self.to_typescript())
+    }
+
+    /// Add another manifest's entries to this one, skipping any already present.
+    pub fn merge(&mut self, other: FfiManifest) {
+        for function in other.functions {
+            if !self.functions.iter().any(|f| f.name == function.name) {
+                self.functions.push(function);
+            }
+        }
+        for slotted_type in other.slotted_types {
+            if !self.slotted_types.contains(&slotted_type) {
+                self.slotted_types.push(slotted_type);
+            }
+        }
+        for hook in other.host_hooks {
+            if !self.host_hooks.iter().any(|h| h.name == hook.name) {
+                self.host_hooks.push(hook);
+            }
+        }
+    }
+
+    /// Generate TypeScript interfaces for the WebAssembly exports and
+    /// the host hooks a web host must import.
+    pub fn to_typescript(&self) -> String {
+        let mut exports: Vec<FfiFunction> = self.functions.clone();
+        for slotted_type in &self.slotted_types {
+            let pointer_type = format!("*mut {}", slotted_type.slotted_type);
+            exports.push(FfiFunction::new(
+                &slotted_type.allocate,
+                &[],
+                Some(&pointer_type),
+            ));
+            exports.push(FfiFunction::new(
+                &slotted_type.free,
+                &[("slot_ptr", &pointer_type)],
+                None,
+            ));
+        }
+
+        let mut typescript =
+            String::from("// Generated from a Perigee FFI manifest. Do not edit by hand.\n\n");
+        typescript.push_str("/** Functions exported by the simulation's WebAssembly module. */\n");
+        typescript.push_str("export interface PerigeeExports {\n");
+        typescript.push_str("  memory: WebAssembly.Memory;\n");
+        for function in &exports {
+            typescript.push_str(&typescript_method(function));
+        }
+        typescript.push_str("}\n\n");
+
+        typescript.push_str("/** Functions the host must provide to the simulation. */\n");
+        typescript.push_str("export interface PerigeeHostHooks {\n");
+        for hook in &self.host_hooks {
+            typescript.push_str(&typescript_method(hook));
+        }
+        typescript.push_str("}\n\n");
+
+        typescript.push_str(
+            "export function perigeeImports(hooks: PerigeeHostHooks): WebAssembly.Imports {\n",
+        );
+        typescript
+            .push_str("  return { env: { ...hooks } as unknown as WebAssembly.ModuleImports };\n");
+        typescript.push_str("}\n");
+
+        typescript
+    }
+}
+
+fn typescript_method(function: &FfiFunction) -> String {
+    let doc_comment = match function.buffer_return {
+        Some(FfiBufferReturn::String) => "  /** Writes a UTF-8 string's pointer and length (two u32s) into `return_slot`. Free the string with `free_bytes`. */\n",
+        Some(FfiBufferReturn::Bytes) => "  /** Writes a byte buffer's pointer and length (two u32s) into `return_slot`. Free the bytes with `free_bytes`. */\n",
+        None => "",
+    };
+    let args: Vec<String> = function
+        .args
+        .iter()
+        .map(|arg| format!("{}: {}", arg.name, typescript_type(&arg.arg_type)))
+        .collect();
+    let returns = match &function.returns {
+        Some(return_type) => typescript_type(return_type),
+        None => "void",
+    };
+    format!(
+        "{}  {}({}): {};\n",
+        doc_comment,
+        function.name,
+        args.join(", "),
+        returns
+    )
+}
+
+/// The TypeScript type a WebAssembly value of the Rust type is seen as.
+fn typescript_type(rust_type: &str) -> &'static str {
+    if rust_type.starts_with('*') || rust_type.starts_with('&') {
+        // Pointers are offsets into linear memory
+        return "number";
+    }
+    match rust_type {
+        "u64" | "i64" | "u128" | "i128" => "bigint",
+        "u8" | "i8" | "u16" | "i16" | "u32" | "i32" | "usize" | "isize" | "f32" | "f64"
+        | "bool" | "char" => "number",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffi::bytes::{free_bytes, FfiByteBuffer};
+    use crate::ffi::free_string;
+    use macros::{ffi, slotted_types};
+    use std::ffi::CString;
+
+    #[derive(Default)]
+    struct CounterSim {
+        count: u32,
+        name: String,
+    }
+
+    #[ffi]
+    #[allow(clippy::needless_lifetimes)]
+    impl CounterSim
+    where
+        Self: Sized,
+    {
+        pub fn counter_sim_increment(&mut self, amount: u32) {
+            self.count += amount;
+        }
+
+        pub fn counter_sim_count(&self) -> u32 {
+            self.count
+        }
+
+        pub fn counter_sim_rename(&mut self, name: &str) {
+            self.name = String::from(name);
+        }
+
+        pub fn counter_sim_name(&self) -> String {
+            self.name.clone()
+        }
+
+        pub fn counter_sim_name_bytes<'a>(&'a self) -> &'a [u8] {
+            self.name.as_bytes()
+        }
+
+        #[slot_return]
+        pub fn counter_sim_count_ref(&self) -> &u32 {
+            &self.count
+        }
+
+        #[ffi_only]
+        pub fn counter_sim_reset(&mut self) {
+            self.count = 0;
+        }
+
+        // Not exportable, so it would fail to compile without the marker
+        #[ffi_never]
+        pub fn counter_sim_try_count(&self) -> Option<u32> {
+            Some(self.count)
+        }
+
+        fn counter_sim_private_count(&self) -> u32 {
+            self.count
+        }
+    }
+
+    #[ffi(manifest = HALF_COUNT_FFI_MANIFEST)]
+    impl CounterSim {
+        #[slot_return]
+        pub fn counter_sim_half_count(&self) -> f64 {
+            f64::from(self.count) / 2.0
+        }
+    }
+
+    slotted_types!(u16);
+
+    fn function<'a>(manifest: &'a FfiManifest, name: &str) -> &'a FfiFunction {
+        manifest
+            .functions
+            .iter()
+            .find(|function| function.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn ffi_macros_generate_callable_functions_and_manifests() {
+        let mut sim = CounterSim::default();
+        counter_sim_increment(&mut sim, 3);
+        assert_eq!(counter_sim_count(&sim), 3);
+        assert_eq!(sim.counter_sim_try_count(), Some(3));
+        assert_eq!(sim.counter_sim_private_count(), 3);
+
+        let name_ptr = CString::new("Ada").unwrap().into_raw() as *const CString;
+        counter_sim_rename(&mut sim, name_ptr);
+        free_string(name_ptr as *mut CString);
+        assert_eq!(sim.name, "Ada");
+
+        let mut buffer = FfiByteBuffer::default();
+        counter_sim_name(&sim, &mut buffer);
+        let name = unsafe { std::slice::from_raw_parts(buffer.ptr, buffer.len) };
+        assert_eq!(name, b"Ada");
+        free_bytes(buffer.ptr, buffer.len);
+        // Returned strings aren't nul-terminated, so they can contain nul
+        sim.name = String::from("Ada\0Lovelace");
+        counter_sim_name(&sim, &mut buffer);
+        let name = unsafe { std::slice::from_raw_parts(buffer.ptr, buffer.len) };
+        assert_eq!(name, b"Ada\0Lovelace");
+        free_bytes(buffer.ptr, buffer.len);
+        counter_sim_name_bytes(&sim, &mut buffer);
+        let name_bytes = unsafe { std::slice::from_raw_parts(buffer.ptr, buffer.len) };
+        assert_eq!(name_bytes, b"Ada\0Lovelace");
+        free_bytes(buffer.ptr, buffer.len);
+
+        let count_slot = allocate_u32_space();
+        counter_sim_count_ref(&sim, count_slot);
+        assert_eq!(unsafe { *count_slot }, 3);
+        free_u32_space(count_slot);
+        let half_count_slot = allocate_f64_space();
+        counter_sim_half_count(&sim, half_count_slot);
+        assert_eq!(unsafe { *half_count_slot }, 1.5);
+        free_f64_space(half_count_slot);
+        counter_sim_reset(&mut sim);
+        assert_eq!(sim.count, 0);
+
+        let manifest = FfiManifest::from_json(CounterSim::FFI_MANIFEST).unwrap();
+        let function_names: Vec<&str> = manifest
+            .functions
+            .iter()
+            .map(|function| function.name.as_str())
+            .collect();
+        assert_eq!(
+            function_names,
+            [
+                "counter_sim_increment",
+                "counter_sim_count",
+                "counter_sim_rename",
+                "counter_sim_name",
+                "counter_sim_name_bytes",
+                "counter_sim_count_ref",
+                "counter_sim_reset"
+            ]
+        );
+        assert_eq!(
+            function(&manifest, "counter_sim_increment").args,
+            [
+                FfiArgument {
+                    name: String::from("sim_ptr"),
+                    arg_type: String::from("*mut CounterSim"),
+                },
+                FfiArgument {
+                    name: String::from("amount"),
+                    arg_type: String::from("u32"),
+                },
+            ]
+        );
+        assert_eq!(
+            function(&manifest, "counter_sim_count").returns.as_deref(),
+            Some("u32")
+        );
+        assert_eq!(
+            function(&manifest, "counter_sim_name").buffer_return,
+            Some(FfiBufferReturn::String)
+        );
+        assert_eq!(
+            function(&manifest, "counter_sim_name_bytes").buffer_return,
+            Some(FfiBufferReturn::Bytes)
+        );
+        assert_eq!(
+            function(&manifest, "counter_sim_count_ref").args[1].arg_type,
+            "*mut u32"
+        );
+        assert_eq!(
+            manifest.slotted_types,
+            [FfiSlottedType {
+                slotted_type: String::from("u32"),
+                allocate: String::from("allocate_u32_space"),
+                free: String::from("free_u32_space"),
+            }]
+        );
+
+        // Buffer return slots are described just like the engine's own
+        let engine = FfiManifest::engine();
+        assert_eq!(
+            function(&manifest, "counter_sim_name").args.last(),
+            function(&engine, "physics_world_raycast").args.last()
+        );
+
+        let mut full_manifest = engine.clone();
+        full_manifest.merge(manifest.clone());
+        full_manifest.merge(FfiManifest::from_json(CounterSim::HALF_COUNT_FFI_MANIFEST).unwrap());
+        full_manifest.merge(FfiManifest::from_json(SLOTTED_TYPES_FFI_MANIFEST).unwrap());
+        assert_eq!(
+            full_manifest.functions.len(),
+            engine.functions.len() + manifest.functions.len() + 1
+        );
+        assert_eq!(full_manifest.host_hooks, engine.host_hooks);
+        let typescript = full_manifest.to_typescript();
+        assert!(typescript
+            .contains("  counter_sim_half_count(sim_ptr: number, return_slot: number): void;\n"));
+        assert!(typescript.contains("  allocate_u16_space(): number;\n"));
+    }
+
+    #[test]
+    fn engine_manifest_is_generated_from_declarations() {
+        let engine = FfiManifest::engine();
+        let raycast = function(&engine, "physics_world_raycast");
+        assert_eq!(raycast.args.len(), 10);
+        assert_eq!(raycast.args[0].arg_type, "*const PhysicsWorld");
+        assert_eq!(raycast.buffer_return, Some(FfiBufferReturn::String));
+        assert_eq!(
+            function(&engine, "alloc_bytes").returns.as_deref(),
+            Some("*mut u8")
+        );
+        assert_eq!(function(&engine, "free_bytes").buffer_return, None);
+
+        let hook_names: Vec<&str> = engine
+            .host_hooks
+            .iter()
+            .map(|hook| hook.name.as_str())
+            .collect();
+        assert_eq!(
+            hook_names,
+            [
+                "play_audio_hook",
+                "loop_audio_hook",
+                "stop_audio_hook",
+                "play_animation_hook",
+                "loop_animation_hook",
+                "stop_animation_hook",
+                "assistive_device_announce_hook"
+            ]
+        );
+        assert_eq!(
+            engine.host_hooks[2].args[3],
+            FfiArgument {
+                name: String::from("audio_name_len"),
+                arg_type: String::from("usize"),
+            }
+        );
+    }
+
+    #[test]
+    fn manifests_are_written_as_json_and_typescript() {
+        let manifest =
+            FfiManifest::with_engine(&[CounterSim::FFI_MANIFEST, SLOTTED_TYPES_FFI_MANIFEST])
+                .unwrap();
+        let out_dir = std::env::temp_dir().join(format!("perigee_ffi_{}", std::process::id()));
+        std::fs::create_dir_all(&out_dir).unwrap();
+        let json_path = out_dir.join("perigee_ffi.json");
+        let typescript_path = out_dir.join("perigee_ffi.ts");
+        manifest.write_files(&json_path, &typescript_path).unwrap();
+
+        let json = std::fs::read_to_string(&json_path).unwrap();
+        assert_eq!(FfiManifest::from_json(&json).unwrap(), manifest);
+        let typescript = std::fs::read_to_string(&typescript_path).unwrap();
+        assert_eq!(typescript, manifest.to_typescript());
+        assert!(typescript.contains("  counter_sim_count(sim_ptr: number): number;\n"));
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[test]
+    fn macro_manifests_generate_typescript() {
+        let macro_manifest = r#"{"functions":[{"name":"step","args":[{"name":"sim_ptr","type":"*mut Sim"},{"name":"delta_seconds","type":"f32"}],"returns":null,"buffer_return":null},{"name":"current_objective","args":[{"name":"sim_ptr","type":"*const Sim"},{"name":"return_slot","type":"*mut perigee::ffi::bytes::FfiByteBuffer"}],"returns":null,"buffer_return":"string"}],"slotted_types":[{"type":"u64","allocate":"allocate_u64_space","free":"free_u64_space"}]}"#;
+        let mut manifest = FfiManifest::engine();
+        manifest.merge(FfiManifest::from_json(macro_manifest).unwrap());
+
+        let typescript = manifest.to_typescript();
+        assert!(typescript.contains("  step(sim_ptr: number, delta_seconds: number): void;\n"));
+        assert!(typescript.contains("  allocate_u64_space(): number;\n"));
+        assert!(typescript.contains("  free_u64_space(slot_ptr: number): void;\n"));
+        assert!(typescript.contains("  alloc_string(num_chars: number): number;\n"));
+        assert!(typescript.contains("  stop_audio_hook("));
+        assert!(typescript.contains(
+            "Free the string with `free_bytes`. */\n  current_objective(sim_ptr: number, return_slot: number): void;\n"
+        ));
+
+        let round_tripped = FfiManifest::from_json(&manifest.to_json().unwrap()).unwrap();
+        assert_eq!(round_tripped, manifest);
+    }
+}
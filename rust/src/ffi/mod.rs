@@ -3,14 +3,16 @@ pub use crate::ffi::strings::*;
 
 pub mod bytes;
 pub mod interface_commands;
+pub mod manifest;
+pub mod queries;
 pub mod strings;
 
 /// Dereference a pointer to the item it points
 /// to in memory and borrow it mutably.
 ///
-/// Safety assumptions:
+/// # Safety
 /// - `ptr` absolutely *cannot* be null, otherwise the program will panic
-///    or see undefined behavior.
+///   or see undefined behavior.
 pub unsafe fn from_mut_ptr<'a, T>(ptr: *mut T) -> &'a mut T {
     &mut *ptr
 }
@@ -18,9 +20,9 @@ pub unsafe fn from_mut_ptr<'a, T>(ptr: *mut T) -> &'a mut T {
 /// Dereference a pointer to the item it points
 /// to in memory and borrow it.
 ///
-/// Safety assumptions:
+/// # Safety
 /// - `ptr` absolutely *cannot* be null, otherwise the program will panic
-///    or see undefined behavior.
+///   or see undefined behavior.
 pub unsafe fn from_ptr<'a, T>(ptr: *const T) -> &'a T {
     &*ptr
 }
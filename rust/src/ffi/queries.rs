@@ -0,0 +1,132 @@
+//! Scene queries exported over FFI. Each function takes a pointer to a
+//! [PhysicsWorld] and writes its results as a JSON string into an
+//! [FfiByteBuffer] return slot, which the host frees with
+//! [free_bytes](crate::ffi::bytes::free_bytes). Simulations can hand their
+//! world's address to the host with an `#[ffi]` method like
+//! `fn physics_world_ptr(&self) -> usize`.
+use crate::ffi::bytes::FfiByteBuffer;
+use crate::physics::PhysicsWorld;
+use macros::ffi_export;
+use rapier3d::prelude::*;
+use serde::Serialize;
+
+/// Write a value's JSON into a return slot.
+///
+/// # Safety
+/// - `return_slot` absolutely *cannot* be null.
+unsafe fn write_json_to_slot<T: Serialize>(value: &T, return_slot: *mut FfiByteBuffer) {
+    let json = serde_json::to_string(value).expect("Query results are always serializable");
+    let json = json.into_bytes().into_boxed_slice();
+    let len = json.len();
+    let ptr = Box::into_raw(json) as *mut u8;
+    *return_slot = FfiByteBuffer { ptr, len };
+}
+
+fn query_filter(include_sensors: bool) -> QueryFilter<'static> {
+    if include_sensors {
+        QueryFilter::default()
+    } else {
+        QueryFilter::default().exclude_sensors()
+    }
+}
+
+/// Writes the closest hit as a JSON object, or `null` if nothing was hit.
+#[no_mangle]
+#[ffi_export(buffer_return = string)]
+#[allow(clippy::not_unsafe_ptr_arg_deref, clippy::too_many_arguments)]
+pub extern "C" fn physics_world_raycast(
+    world_ptr: *const PhysicsWorld,
+    origin_x: f32,
+    origin_y: f32,
+    origin_z: f32,
+    direction_x: f32,
+    direction_y: f32,
+    direction_z: f32,
+    max_distance: f32,
+    include_sensors: bool,
+    return_slot: *mut FfiByteBuffer,
+) {
+    let world = unsafe { crate::ffi::from_ptr(world_ptr) };
+    let hit = world.raycast(
+        point![origin_x, origin_y, origin_z],
+        vector![direction_x, direction_y, direction_z],
+        max_distance,
+        true,
+        query_filter(include_sensors),
+    );
+    unsafe { write_json_to_slot(&hit, return_slot) };
+}
+
+/// Sweeps a sphere and writes the first hit as a JSON object, or `null` if nothing was hit.
+#[no_mangle]
+#[ffi_export(buffer_return = string)]
+#[allow(clippy::not_unsafe_ptr_arg_deref, clippy::too_many_arguments)]
+pub extern "C" fn physics_world_sphere_cast(
+    world_ptr: *const PhysicsWorld,
+    origin_x: f32,
+    origin_y: f32,
+    origin_z: f32,
+    radius: f32,
+    direction_x: f32,
+    direction_y: f32,
+    direction_z: f32,
+    max_distance: f32,
+    include_sensors: bool,
+    return_slot: *mut FfiByteBuffer,
+) {
+    let world = unsafe { crate::ffi::from_ptr(world_ptr) };
+    let hit = world.shape_cast(
+        &Ball::new(radius),
+        &Isometry::translation(origin_x, origin_y, origin_z),
+        vector![direction_x, direction_y, direction_z],
+        max_distance,
+        query_filter(include_sensors),
+    );
+    unsafe { write_json_to_slot(&hit, return_slot) };
+}
+
+/// Writes the overlapping colliders as a JSON array.
+#[no_mangle]
+#[ffi_export(buffer_return = string)]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn physics_world_overlap_sphere(
+    world_ptr: *const PhysicsWorld,
+    center_x: f32,
+    center_y: f32,
+    center_z: f32,
+    radius: f32,
+    include_sensors: bool,
+    return_slot: *mut FfiByteBuffer,
+) {
+    let world = unsafe { crate::ffi::from_ptr(world_ptr) };
+    let overlaps = world.overlap_sphere(
+        point![center_x, center_y, center_z],
+        radius,
+        query_filter(include_sensors),
+    );
+    unsafe { write_json_to_slot(&overlaps, return_slot) };
+}
+
+/// Writes the colliders overlapping an axis-aligned box as a JSON array.
+#[no_mangle]
+#[ffi_export(buffer_return = string)]
+#[allow(clippy::not_unsafe_ptr_arg_deref, clippy::too_many_arguments)]
+pub extern "C" fn physics_world_overlap_box(
+    world_ptr: *const PhysicsWorld,
+    center_x: f32,
+    center_y: f32,
+    center_z: f32,
+    half_extent_x: f32,
+    half_extent_y: f32,
+    half_extent_z: f32,
+    include_sensors: bool,
+    return_slot: *mut FfiByteBuffer,
+) {
+    let world = unsafe { crate::ffi::from_ptr(world_ptr) };
+    let overlaps = world.overlap_box(
+        &Isometry::translation(center_x, center_y, center_z),
+        vector![half_extent_x, half_extent_y, half_extent_z],
+        query_filter(include_sensors),
+    );
+    unsafe { write_json_to_slot(&overlaps, return_slot) };
+}
@@ -1,6 +1,8 @@
+use macros::ffi_export;
 use std::ffi::{CStr, CString};
 
 #[no_mangle]
+#[ffi_export]
 pub extern "C" fn alloc_string(num_chars: usize) -> *mut CString {
     // Init the space with 7s. They'll get updated with the
     // actual string later
@@ -8,11 +10,16 @@ pub extern "C" fn alloc_string(num_chars: usize) -> *mut CString {
     unsafe { CString::from_vec_unchecked(reserved_bytes).into_raw() as *mut CString }
 }
 
+/// Borrow the C string pointed to by `cstr_ptr`.
+///
+/// # Safety
+/// - `cstr_ptr` absolutely *cannot* be null and must point to a nul-terminated string.
 pub unsafe fn from_cstring_ptr<'a>(cstr_ptr: *const CString) -> &'a CStr {
     CStr::from_ptr(cstr_ptr as *mut i8)
 }
 
 #[no_mangle]
+#[ffi_export]
 pub extern "C" fn free_string(string_ptr: *mut CString) {
-    unsafe { CString::from_raw(string_ptr as *mut i8) };
+    drop(unsafe { CString::from_raw(string_ptr as *mut i8) });
 }
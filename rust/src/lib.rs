@@ -10,6 +10,8 @@ pub mod math;
 pub mod perigee_gltf;
 pub mod physics;
 pub mod pointers;
+pub mod replay;
+pub mod rollback;
 pub mod time;
 pub mod traits;
 pub mod types;
@@ -25,6 +27,8 @@ pub mod prelude {
     pub use crate::perigee_gltf::poi::*;
     pub use crate::physics::*;
     pub use crate::pointers::*;
+    pub use crate::replay::*;
+    pub use crate::rollback::*;
     pub use crate::time::*;
     pub use crate::traits::*;
     pub use crate::types::*;
@@ -10,9 +10,12 @@ pub fn init_perigee_logger() {
 
     // If the below fails even when a logger has never been set
     // then we're SoL
-    if let Ok(_) = set_logger(&PerigeeLogger).map(|_| set_max_level(max_level)) {
+    if set_logger(&PerigeeLogger)
+        .map(|_| set_max_level(max_level))
+        .is_ok()
+    {
         std::panic::set_hook(Box::new(|panic_info| {
-            error!("{}", panic_info.to_string());
+            error!("{}", panic_info);
         }));
     } else {
         warn!("Perigee logger already set!");
@@ -46,11 +46,11 @@ pub fn move_towards(
 #[inline]
 pub fn project_on_plane(vector: &Vector3<f32>, plane_normal: &Vector3<f32>) -> Vector3<f32> {
     let squared_magnitude = plane_normal.dot(plane_normal);
-    if squared_magnitude < std::f32::EPSILON {
-        return *vector;
+    if squared_magnitude < f32::EPSILON {
+        *vector
     } else {
         let dot = vector.dot(plane_normal);
-        return vector - plane_normal * dot / squared_magnitude;
+        vector - plane_normal * dot / squared_magnitude
     }
 }
 
@@ -67,7 +67,7 @@ where
     (num - start_min) * (end_max - end_min) / (start_max - start_min) + end_min
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy)]
 pub struct Transform3<T>
 where
     T: Scalar + SimdValue + RealField + Copy,
@@ -235,7 +235,7 @@ where
 
     fn mul(self, rhs: Vector3<T>) -> Self::Output {
         // T * R * S: Scale first, rotate second, translate third
-        (*self).isometry * rhs
+        self.isometry * rhs
     }
 }
 
@@ -247,6 +247,6 @@ where
 
     fn mul(self, rhs: &Vector3<T>) -> Self::Output {
         // T * R * S: Scale first, rotate second, translate third
-        (*self).isometry * (*rhs)
+        self.isometry * (*rhs)
     }
 }
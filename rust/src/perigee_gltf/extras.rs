@@ -1,4 +1,5 @@
 use rapier3d::na::Vector3;
+use rapier3d::prelude::{CoefficientCombineRule, ColliderBuilder, Group, InteractionGroups};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
@@ -23,9 +24,88 @@ pub enum GltfOptimizedShape {
     Cuboid,
     #[serde(rename = "SPHERE")]
     Sphere,
+    #[serde(rename = "CAPSULE")]
+    Capsule,
+    #[serde(rename = "CYLINDER")]
+    Cylinder,
+    #[serde(rename = "CONE")]
+    Cone,
+    /// Built from the shapes of the physics-enabled meshes below the node,
+    /// which aren't loaded as bodies of their own.
+    #[serde(rename = "COMPOUND")]
+    Compound,
+    /// Convex parts approximating a concave mesh.
+    #[serde(rename = "CONVEX_DECOMPOSITION")]
+    ConvexDecomposition,
 }
 
+impl GltfOptimizedShape {
+    /// Whether building this shape requires reading the mesh's triangles.
+    pub fn needs_mesh_geometry(&self) -> bool {
+        matches!(
+            self,
+            Self::None | Self::ConvexMesh | Self::ConvexDecomposition
+        )
+    }
+}
+
+/// How the friction or restitution coefficients of two touching colliders are combined.
+/// When the colliders' rules differ, the rule listed last wins.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub enum GltfCombineRule {
+    #[default]
+    #[serde(rename = "AVERAGE")]
+    Average,
+    #[serde(rename = "MIN")]
+    Min,
+    #[serde(rename = "MULTIPLY")]
+    Multiply,
+    #[serde(rename = "MAX")]
+    Max,
+}
+
+impl From<GltfCombineRule> for CoefficientCombineRule {
+    fn from(rule: GltfCombineRule) -> Self {
+        match rule {
+            GltfCombineRule::Average => CoefficientCombineRule::Average,
+            GltfCombineRule::Min => CoefficientCombineRule::Min,
+            GltfCombineRule::Multiply => CoefficientCombineRule::Multiply,
+            GltfCombineRule::Max => CoefficientCombineRule::Max,
+        }
+    }
+}
+
+/// Bit masks of the groups a collider belongs to and the groups it interacts with.
+/// Two colliders interact if each one's memberships intersect the other's filter.
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct GltfInteractionGroups {
+    pub memberships: u32,
+    pub filter: u32,
+}
+
+impl Default for GltfInteractionGroups {
+    fn default() -> Self {
+        Self {
+            memberships: u32::MAX,
+            filter: u32::MAX,
+        }
+    }
+}
+
+impl From<GltfInteractionGroups> for InteractionGroups {
+    fn from(groups: GltfInteractionGroups) -> Self {
+        InteractionGroups::new(
+            Group::from_bits_truncate(groups.memberships),
+            Group::from_bits_truncate(groups.filter),
+        )
+    }
+}
+
+fn default_friction() -> f32 {
+    ColliderBuilder::default_friction()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GltfPhysicsSettings {
     pub enabled: bool,
     #[serde(rename = "isAnonymous")]
@@ -38,17 +118,127 @@ pub struct GltfPhysicsSettings {
     #[serde(rename = "baseScale")]
     #[serde(default)]
     pub base_scale: Vector3<f32>,
+    #[serde(default = "default_friction")]
+    pub friction: f32,
+    #[serde(default)]
+    pub restitution: f32,
+    #[serde(rename = "frictionCombineRule")]
+    #[serde(default)]
+    pub friction_combine_rule: GltfCombineRule,
+    #[serde(rename = "restitutionCombineRule")]
+    #[serde(default)]
+    pub restitution_combine_rule: GltfCombineRule,
+    /// Overrides `mass` when set, so the body's mass follows its collider's volume.
+    #[serde(default)]
+    pub density: Option<f32>,
+    /// Which colliders this collider generates contacts and intersection events with.
+    #[serde(rename = "collisionGroups")]
+    #[serde(default)]
+    pub collision_groups: GltfInteractionGroups,
+    /// The name of the [collision layer](crate::physics::CollisionLayers) whose
+    /// groups replace `collisionGroups`.
+    #[serde(rename = "collisionLayer")]
+    #[serde(default)]
+    pub collision_layer: Option<String>,
+    /// Which colliders this collider's contacts push apart.
+    #[serde(rename = "solverGroups")]
+    #[serde(default)]
+    pub solver_groups: GltfInteractionGroups,
+    /// Keep fast dynamic bodies from tunneling through thin colliders.
+    #[serde(rename = "ccdEnabled")]
+    #[serde(default)]
+    pub ccd_enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum GltfJointType {
+    #[serde(rename = "FIXED")]
+    Fixed,
+    #[serde(rename = "REVOLUTE")]
+    Revolute,
+    #[serde(rename = "PRISMATIC")]
+    Prismatic,
+    #[serde(rename = "SPHERICAL")]
+    Spherical,
 }
 
+/// Limits of a joint's free axis, in radians for rotations and meters for translations.
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct GltfJointLimits {
+    pub min: f32,
+    pub max: f32,
+}
+
+/// A motor driving a joint's free axis towards a target position and velocity.
+/// A stiffness of 0 makes it a pure velocity motor.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct GltfJointMotor {
+    #[serde(rename = "targetPosition")]
+    #[serde(default)]
+    pub target_position: f32,
+    #[serde(rename = "targetVelocity")]
+    #[serde(default)]
+    pub target_velocity: f32,
+    #[serde(default)]
+    pub stiffness: f32,
+    #[serde(default)]
+    pub damping: f32,
+    #[serde(rename = "maxForce")]
+    #[serde(default)]
+    pub max_force: Option<f32>,
+}
+
+fn default_joint_axis() -> Vector3<f32> {
+    Vector3::x()
+}
+
+/// A joint between two named rigid bodies, anchored at the joint node.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GltfJointSettings {
+    pub enabled: bool,
+    #[serde(rename = "jointType")]
+    pub joint_type: GltfJointType,
+    #[serde(rename = "firstBody")]
+    pub first_body: String,
+    #[serde(rename = "secondBody")]
+    pub second_body: String,
+    /// The free axis of revolute and prismatic joints in the joint node's space.
+    #[serde(default = "default_joint_axis")]
+    pub axis: Vector3<f32>,
+    /// Applied to the free axis of revolute and prismatic joints and
+    /// to every rotation axis of spherical joints.
+    #[serde(default)]
+    pub limits: Option<GltfJointLimits>,
+    /// Applied to the same axes as the limits.
+    #[serde(default)]
+    pub motor: Option<GltfJointMotor>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GltfSimSettings {
     pub physics: GltfPhysicsSettings,
     #[serde(rename = "isPointOfInterest")]
     pub is_point_of_interest: bool,
+    #[serde(default)]
+    pub joint: Option<GltfJointSettings>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GltfExtras {
     #[serde(rename = "simSettings")]
     pub sim_settings: GltfSimSettings,
 }
+
+/// A named point on an animation's timeline, like a Blender pose marker.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GltfAnimationMarker {
+    pub name: String,
+    /// The marker's position on the timeline in seconds.
+    pub time: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GltfAnimationExtras {
+    #[serde(default)]
+    pub markers: Vec<GltfAnimationMarker>,
+}
@@ -1,4 +1,5 @@
 pub mod extras;
 pub mod poi;
 pub mod prefabs;
+pub mod scenes;
 pub mod util;
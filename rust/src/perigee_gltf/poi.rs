@@ -1,4 +1,5 @@
 use crate::perigee_gltf::extras::GltfExtras;
+use crate::perigee_gltf::scenes::{GltfSceneSelectionError, GltfSceneSelector, LoadedGltfScene};
 use gltf::{Gltf, Node};
 use rapier3d::na::{Isometry3, Quaternion, UnitQuaternion, Vector3};
 use serde::{Deserialize, Serialize};
@@ -14,6 +15,8 @@ pub enum PointsOfInterestInitError {
     InvalidPerigeeExtrasData,
     #[error("glTF node must have a name")]
     UnnamedNode,
+    #[error("could not select glTF scenes: {0}")]
+    SceneSelection(#[from] GltfSceneSelectionError),
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -27,6 +30,7 @@ impl PointsOfInterest {
         node: &Node,
         parent_isometry: &Isometry3<f32>,
         visited_nodes: &mut HashMap<usize, ()>,
+        loaded_node_names: &mut Vec<String>,
     ) -> Result<(), PointsOfInterestInitError> {
         if visited_nodes.contains_key(&node.index()) {
             return Ok(());
@@ -55,7 +59,12 @@ impl PointsOfInterest {
             );
 
         for child_node in node.children() {
-            self.visit_gltf_node(&child_node, &global_isometry, visited_nodes)?;
+            self.visit_gltf_node(
+                &child_node,
+                &global_isometry,
+                visited_nodes,
+                loaded_node_names,
+            )?;
         }
 
         if node_extras.sim_settings.is_point_of_interest {
@@ -65,22 +74,43 @@ impl PointsOfInterest {
             };
 
             self.map.insert(String::from(node_name), global_isometry);
+            loaded_node_names.push(String::from(node_name));
         }
 
         visited_nodes.insert(node.index(), ());
         Ok(())
     }
 
+    /// Note: Only loads the first scene. Use
+    /// [load_scenes_from_gltf](Self::load_scenes_from_gltf) to choose scenes.
     pub fn load_from_gltf(&mut self, gltf: &Gltf) -> Result<(), PointsOfInterestInitError> {
+        self.load_scenes_from_gltf(gltf, &GltfSceneSelector::First)?;
+        Ok(())
+    }
+
+    /// Load the points of interest in the selected scenes of a glTF.
+    /// Returns the names of the points loaded from each scene.
+    pub fn load_scenes_from_gltf(
+        &mut self,
+        gltf: &Gltf,
+        scenes: &GltfSceneSelector,
+    ) -> Result<Vec<LoadedGltfScene>, PointsOfInterestInitError> {
         let mut visited_nodes: HashMap<usize, ()> = HashMap::new();
-        // Only loads the first scene
-        if let Some(scene) = gltf.scenes().next() {
+        let mut loaded_scenes = vec![];
+        for scene in scenes.select(gltf)? {
+            let mut loaded_scene = LoadedGltfScene::new(&scene);
             for node in scene.nodes() {
-                self.visit_gltf_node(&node, &Isometry3::identity(), &mut visited_nodes)?;
+                self.visit_gltf_node(
+                    &node,
+                    &Isometry3::identity(),
+                    &mut visited_nodes,
+                    &mut loaded_scene.node_names,
+                )?;
             }
+            loaded_scenes.push(loaded_scene);
         }
 
-        Ok(())
+        Ok(loaded_scenes)
     }
 
     pub fn point_with_name(&self, name: &str) -> Option<&Isometry3<f32>> {
@@ -1,16 +1,560 @@
+use crate::animation::{Animation, AnimationChannel, AnimationCreationError, ChannelType};
 use crate::math::Transform3;
+use crate::perigee_gltf::extras::{GltfExtras, GltfOptimizedShape, GltfPhysicsSettings};
+use crate::perigee_gltf::util::node_local_transform;
+use crate::physics::{GltfCompoundPart, GltfMeshGeometry, PhysicsWorldInitError};
+use gltf::{Gltf, Node};
+use rapier3d::{na::Isometry3, prelude::*};
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PrefabCreationError {
+    #[error("could not find prefab root node name in glTF document")]
+    NameNotFound,
+    #[error("can't access the provided glTF's binary payload")]
+    CantAccessBlob,
+    #[error("glTF must have Perigee extras to load prefab")]
+    PerigeeExtrasUndetected,
+    #[error("invalid JSON stored in glTF node extras")]
+    InvalidPerigeeExtrasData,
+    #[error("glTF node must have a name")]
+    UnnamedNode,
+    #[error("could not load prefab physics object: {0}")]
+    Physics(#[from] PhysicsWorldInitError),
+    #[error("could not load prefab animation: {0}")]
+    Animation(#[from] AnimationCreationError),
+}
 
 #[derive(Clone)]
-enum PrefabItem {}
+pub(crate) enum PrefabItem {
+    Empty,
+    RigidBody {
+        physics_settings: GltfPhysicsSettings,
+        geometry: Option<GltfMeshGeometry>,
+        compound_parts: Vec<GltfCompoundPart>,
+    },
+    Sensor {
+        physics_settings: GltfPhysicsSettings,
+    },
+}
 
 #[derive(Clone)]
-struct SceneTreeNode {
-    transform: Transform3<f32>,
-    item: PrefabItem,
-    children: Vec<SceneTreeNode>,
+pub(crate) struct SceneTreeNode {
+    pub(crate) name: Option<String>,
+    /// The node's transform relative to its parent.
+    pub(crate) transform: Transform3<f32>,
+    pub(crate) item: PrefabItem,
+    pub(crate) is_point_of_interest: bool,
+    pub(crate) children: Vec<SceneTreeNode>,
+}
+
+impl SceneTreeNode {
+    fn from_gltf_node(
+        node: &Node,
+        gltf_bytes: &[u8],
+        transform: Transform3<f32>,
+        compound_part_nodes: &mut HashSet<usize>,
+    ) -> Result<Self, PrefabCreationError> {
+        let node_extra_data = match node.extras().as_ref() {
+            Some(extra_data) => extra_data,
+            None => return Err(PrefabCreationError::PerigeeExtrasUndetected),
+        };
+        let node_extras: GltfExtras = match serde_json::from_str(node_extra_data.get()) {
+            Ok(extras) => extras,
+            Err(_) => return Err(PrefabCreationError::InvalidPerigeeExtrasData),
+        };
+        let physics_settings = node_extras.sim_settings.physics;
+
+        let name = node.name().map(String::from);
+        if name.is_none()
+            && (physics_settings.enabled || node_extras.sim_settings.is_point_of_interest)
+        {
+            return Err(PrefabCreationError::UnnamedNode);
+        }
+
+        // Meshes that are part of an ancestor's compound shape don't get bodies of their own
+        let item = if !physics_settings.enabled || compound_part_nodes.contains(&node.index()) {
+            PrefabItem::Empty
+        } else if let Some(mesh) = node.mesh() {
+            let geometry = if physics_settings.optimized_shape.needs_mesh_geometry() {
+                Some(GltfMeshGeometry::from_gltf_mesh(&mesh, gltf_bytes)?)
+            } else {
+                None
+            };
+            let mut compound_parts = vec![];
+            if matches!(
+                physics_settings.optimized_shape,
+                GltfOptimizedShape::Compound
+            ) {
+                let mut part_node_indices = vec![];
+                GltfCompoundPart::collect_from_gltf_node(
+                    node,
+                    gltf_bytes,
+                    &Transform3::identity(),
+                    &mut compound_parts,
+                    &mut part_node_indices,
+                )?;
+                compound_part_nodes.extend(part_node_indices);
+            }
+            PrefabItem::RigidBody {
+                physics_settings,
+                geometry,
+                compound_parts,
+            }
+        } else {
+            PrefabItem::Sensor { physics_settings }
+        };
+
+        let mut children = vec![];
+        for child_node in node.children() {
+            children.push(Self::from_gltf_node(
+                &child_node,
+                gltf_bytes,
+                node_local_transform(&child_node),
+                compound_part_nodes,
+            )?);
+        }
+
+        Ok(Self {
+            name,
+            transform,
+            item,
+            is_point_of_interest: node_extras.sim_settings.is_point_of_interest,
+            children,
+        })
+    }
+
+    fn collect_names(&self, names: &mut Vec<String>) {
+        if let Some(name) = &self.name {
+            names.push(name.clone());
+        }
+        for child in &self.children {
+            child.collect_names(names);
+        }
+    }
+
+    fn walk<S: Copy, E>(
+        &self,
+        parent_transform: &Transform3<f32>,
+        parent_state: S,
+        visitor: &mut impl FnMut(&SceneTreeNode, &Transform3<f32>, S) -> Result<S, E>,
+    ) -> Result<(), E> {
+        let global_transform = parent_transform * self.transform;
+        let state = visitor(self, &global_transform, parent_state)?;
+        for child in &self.children {
+            child.walk(&global_transform, state, visitor)?;
+        }
+        Ok(())
+    }
 }
 
+#[derive(Clone)]
+struct PrefabAnimation {
+    name: String,
+    target_channels: HashMap<String, HashMap<ChannelType, AnimationChannel>>,
+    markers: HashMap<String, f32>,
+}
+
+/// A reusable copy of a glTF node sub-tree, capturing its rigid bodies, sensors,
+/// points of interest and the animations that target it. Spawn copies of it with
+/// [instantiate_prefab](crate::physics::PhysicsWorld::instantiate_prefab).
 #[derive(Clone)]
 pub struct Prefab {
+    name: String,
     root: SceneTreeNode,
+    animations: Vec<PrefabAnimation>,
+}
+
+impl Prefab {
+    /// Capture the sub-tree rooted at the named glTF node. The root node's own
+    /// transform is discarded so that instances are placed by their instance transform.
+    pub fn from_gltf(gltf: &Gltf, root_node_name: &str) -> Result<Self, PrefabCreationError> {
+        let gltf_bytes = match &gltf.blob {
+            Some(bytes) => bytes,
+            None => return Err(PrefabCreationError::CantAccessBlob),
+        };
+        let root_node = match gltf
+            .nodes()
+            .find(|node| node.name() == Some(root_node_name))
+        {
+            Some(node) => node,
+            None => return Err(PrefabCreationError::NameNotFound),
+        };
+        let root = SceneTreeNode::from_gltf_node(
+            &root_node,
+            gltf_bytes,
+            Transform3::identity(),
+            &mut HashSet::new(),
+        )?;
+
+        let mut node_names = vec![];
+        root.collect_names(&mut node_names);
+        let node_names: HashSet<String> = node_names.into_iter().collect();
+
+        let mut animations = vec![];
+        for anim in gltf.animations() {
+            let anim_name = match anim.name() {
+                Some(name) => name,
+                None => continue,
+            };
+            // Only load animations that target the sub-tree, so that problems with
+            // the rest of the document don't keep the prefab from loading
+            let targets_sub_tree = anim.channels().any(|channel| {
+                channel
+                    .target()
+                    .node()
+                    .name()
+                    .is_some_and(|target_name| node_names.contains(target_name))
+            });
+            if !targets_sub_tree {
+                continue;
+            }
+            let animation = Animation::from_gltf(gltf, anim_name)?;
+            let target_channels: HashMap<String, HashMap<ChannelType, AnimationChannel>> =
+                animation
+                    .target_channels()
+                    .iter()
+                    .filter(|(target_name, _)| node_names.contains(*target_name))
+                    .map(|(target_name, channels)| (target_name.clone(), channels.clone()))
+                    .collect();
+            if target_channels.is_empty() {
+                continue;
+            }
+            animations.push(PrefabAnimation {
+                name: String::from(anim_name),
+                target_channels,
+                markers: animation.markers().clone(),
+            });
+        }
+
+        Ok(Self {
+            name: String::from(root_node_name),
+            root,
+            animations,
+        })
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    /// The names of every node in the prefab, root first.
+    pub fn node_names(&self) -> Vec<String> {
+        let mut names = vec![];
+        self.root.collect_names(&mut names);
+        names
+    }
+
+    /// Fresh copies of the prefab's animations whose names and targets
+    /// are prefixed with `name_prefix`.
+    pub fn instantiate_animations(&self, name_prefix: &str) -> Vec<Animation> {
+        self.animations
+            .iter()
+            .map(|prefab_animation| {
+                let target_channels = prefab_animation
+                    .target_channels
+                    .iter()
+                    .map(|(target_name, channels)| {
+                        (format!("{}{}", name_prefix, target_name), channels.clone())
+                    })
+                    .collect();
+                let mut animation = Animation::new(
+                    &format!("{}{}", name_prefix, prefab_animation.name),
+                    target_channels,
+                );
+                for (marker_name, timestamp) in &prefab_animation.markers {
+                    animation.add_marker(marker_name.clone(), *timestamp);
+                }
+                animation
+            })
+            .collect()
+    }
+
+    /// Visit every node in the prefab along with its global transform when
+    /// the prefab root is placed at `root_transform`. The state returned by the
+    /// visitor for a node is passed to the visits of its children, starting
+    /// with `root_state` for the root.
+    pub(crate) fn walk<S: Copy, E>(
+        &self,
+        root_transform: &Transform3<f32>,
+        root_state: S,
+        mut visitor: impl FnMut(&SceneTreeNode, &Transform3<f32>, S) -> Result<S, E>,
+    ) -> Result<(), E> {
+        self.root.walk(root_transform, root_state, &mut visitor)
+    }
+}
+
+/// Everything created by instantiating a [Prefab], keyed by the prefixed names
+/// given to the instance's objects.
+#[derive(Default)]
+pub struct PrefabInstance {
+    pub rigid_bodies: HashMap<String, RigidBodyHandle>,
+    pub sensors: HashMap<String, ColliderHandle>,
+    pub points_of_interest: HashMap<String, Isometry3<f32>>,
+    pub animations: Vec<Animation>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perigee_gltf::extras::{GltfBodyType, GltfOptimizedShape};
+    use crate::perigee_gltf::util::glb;
+    use crate::physics::{PhysicsWorld, PrefabInstantiationError};
+    use rapier3d::na::{Translation3, UnitQuaternion, Vector3};
+
+    fn physics_settings(body_type: GltfBodyType) -> GltfPhysicsSettings {
+        GltfPhysicsSettings {
+            enabled: true,
+            is_anonymous: false,
+            body_type,
+            mass: 1.0,
+            optimized_shape: GltfOptimizedShape::Cuboid,
+            base_scale: Vector3::new(1.0, 1.0, 1.0),
+            friction: 0.5,
+            restitution: 0.0,
+            friction_combine_rule: Default::default(),
+            restitution_combine_rule: Default::default(),
+            density: None,
+            collision_groups: Default::default(),
+            collision_layer: None,
+            solver_groups: Default::default(),
+            ccd_enabled: false,
+        }
+    }
+
+    fn pickup_prefab() -> Prefab {
+        Prefab {
+            name: String::from("Pickup"),
+            root: SceneTreeNode {
+                name: Some(String::from("Pickup")),
+                transform: Transform3::identity(),
+                item: PrefabItem::RigidBody {
+                    physics_settings: physics_settings(GltfBodyType::Dynamic),
+                    geometry: None,
+                    compound_parts: vec![],
+                },
+                is_point_of_interest: false,
+                children: vec![SceneTreeNode {
+                    name: Some(String::from("Trigger")),
+                    transform: Transform3::from_parts(
+                        Isometry3::from_parts(
+                            Translation3::new(0.0, 1.0, 0.0),
+                            UnitQuaternion::identity(),
+                        ),
+                        Vector3::new(1.0, 1.0, 1.0),
+                    ),
+                    item: PrefabItem::Sensor {
+                        physics_settings: physics_settings(GltfBodyType::Sensor),
+                    },
+                    is_point_of_interest: true,
+                    children: vec![],
+                }],
+            },
+            animations: vec![],
+        }
+    }
+
+    #[test]
+    fn instances_get_unique_names_and_handles() {
+        let prefab = pickup_prefab();
+        let mut world = PhysicsWorld::default();
+        let placement = |x: f32| {
+            Transform3::from_parts(
+                Isometry3::translation(x, 0.0, 0.0),
+                Vector3::new(1.0, 1.0, 1.0),
+            )
+        };
+
+        let first = world
+            .instantiate_prefab(&prefab, placement(0.0), "A_")
+            .unwrap();
+        let second = world
+            .instantiate_prefab(&prefab, placement(5.0), "B_")
+            .unwrap();
+
+        assert_ne!(
+            first.rigid_bodies["A_Pickup"],
+            second.rigid_bodies["B_Pickup"]
+        );
+        assert_eq!(
+            world.named_sensors.handle_with_name("B_Trigger"),
+            Some(&second.sensors["B_Trigger"])
+        );
+        let trigger_position = second.points_of_interest["B_Trigger"].translation.vector;
+        assert!((trigger_position - Vector3::new(5.0, 1.0, 0.0)).norm() < 1e-5);
+
+        // The trigger rides along with its pickup without adding to its mass
+        let pickup_handle = second.rigid_bodies["B_Pickup"];
+        let trigger = &world.collider_set[second.sensors["B_Trigger"]];
+        assert_eq!(trigger.parent(), Some(pickup_handle));
+        assert!((world.rigid_body_set[pickup_handle].mass() - 1.0).abs() < 1e-5);
+        world.rigid_body_set[pickup_handle].set_translation(Vector3::new(5.0, 3.0, 0.0), true);
+        world.step(1.0 / 60.0);
+        let pickup_position = world.rigid_body_set[pickup_handle].translation();
+        let trigger_position = world.collider_set[second.sensors["B_Trigger"]].translation();
+        assert!((trigger_position - pickup_position - Vector3::new(0.0, 1.0, 0.0)).norm() < 1e-5);
+
+        assert!(matches!(
+            world.instantiate_prefab(&prefab, placement(0.0), "A_"),
+            Err(PrefabInstantiationError::NameTaken(_))
+        ));
+        assert_eq!(world.rigid_body_set.len(), 2);
+    }
+
+    /// A GLB holding a pickup prefab (a dynamic body with a sensor child that's also
+    /// a point of interest), an animation spinning it and a malformed animation that
+    /// targets another node.
+    fn pickup_glb() -> Vec<u8> {
+        let mut binary_chunk: Vec<u8> = vec![];
+        // Keyframe timestamps, translation keyframes, then the cube's corner positions
+        let values: [f32; 17] = [
+            0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 2.0, 0.0, -0.5, -0.5, -0.5, 0.5, -0.5, -0.5, 0.5, 0.5,
+            0.5,
+        ];
+        for value in values {
+            binary_chunk.extend_from_slice(&value.to_le_bytes());
+        }
+        let extras = |body_type: &str, is_point_of_interest: bool| {
+            serde_json::json!({
+                "simSettings": {
+                    "physics": {
+                        "enabled": true,
+                        "isAnonymous": false,
+                        "bodyType": body_type,
+                        "mass": 1.0,
+                        "optimizedShape": "CUBOID",
+                        "baseScale": [1.0, 1.0, 1.0]
+                    },
+                    "isPointOfInterest": is_point_of_interest
+                }
+            })
+        };
+        let json = serde_json::json!({
+            "asset": { "version": "2.0" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0, 3] }],
+            "nodes": [
+                { "name": "Shelf", "children": [1] },
+                {
+                    "name": "Pickup",
+                    "translation": [9.0, 9.0, 9.0],
+                    "mesh": 0,
+                    "children": [2],
+                    "extras": extras("DYNAMIC", false)
+                },
+                {
+                    "name": "Trigger",
+                    "translation": [0.0, 1.0, 0.0],
+                    "extras": extras("SENSOR", true)
+                },
+                { "name": "Door" }
+            ],
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 2 } }] }],
+            "animations": [
+                {
+                    "name": "Bob",
+                    "channels": [{ "sampler": 0, "target": { "node": 1, "path": "translation" } }],
+                    "samplers": [{ "input": 0, "output": 1 }]
+                },
+                {
+                    "name": "Swing",
+                    "channels": [{ "sampler": 0, "target": { "node": 3, "path": "translation" } }],
+                    "samplers": [{ "input": 0, "output": 1 }],
+                    "extras": { "markers": "not a list of markers" }
+                }
+            ],
+            "accessors": [
+                {
+                    "bufferView": 0,
+                    "componentType": 5126,
+                    "count": 2,
+                    "type": "SCALAR",
+                    "min": [0.0],
+                    "max": [1.0]
+                },
+                { "bufferView": 1, "componentType": 5126, "count": 2, "type": "VEC3" },
+                {
+                    "bufferView": 2,
+                    "componentType": 5126,
+                    "count": 3,
+                    "type": "VEC3",
+                    "min": [-0.5, -0.5, -0.5],
+                    "max": [0.5, 0.5, 0.5]
+                }
+            ],
+            "bufferViews": [
+                { "buffer": 0, "byteOffset": 0, "byteLength": 8 },
+                { "buffer": 0, "byteOffset": 8, "byteLength": 24 },
+                { "buffer": 0, "byteOffset": 32, "byteLength": 36 }
+            ],
+            "buffers": [{ "byteLength": binary_chunk.len() }]
+        });
+        glb(&json, &binary_chunk)
+    }
+
+    #[test]
+    fn prefabs_load_from_gltf_sub_trees() {
+        let gltf = Gltf::from_slice(&pickup_glb()).unwrap();
+        // The malformed animation only fails to load on its own
+        assert!(matches!(
+            Animation::from_gltf(&gltf, "Swing"),
+            Err(AnimationCreationError::InvalidPerigeeExtrasData)
+        ));
+        assert!(matches!(
+            Prefab::from_gltf(&gltf, "Crate"),
+            Err(PrefabCreationError::NameNotFound)
+        ));
+
+        let prefab = Prefab::from_gltf(&gltf, "Pickup").unwrap();
+        assert_eq!(prefab.name(), "Pickup");
+        assert_eq!(prefab.node_names(), ["Pickup", "Trigger"]);
+
+        let mut world = PhysicsWorld::default();
+        let placement = |x: f32| {
+            Transform3::from_parts(
+                Isometry3::translation(x, 0.0, 0.0),
+                Vector3::new(1.0, 1.0, 1.0),
+            )
+        };
+        let first = world
+            .instantiate_prefab(&prefab, placement(0.0), "A_")
+            .unwrap();
+        let second = world
+            .instantiate_prefab(&prefab, placement(5.0), "B_")
+            .unwrap();
+
+        assert_eq!(first.rigid_bodies.keys().collect::<Vec<_>>(), ["A_Pickup"]);
+        assert_eq!(second.rigid_bodies.keys().collect::<Vec<_>>(), ["B_Pickup"]);
+        assert_ne!(
+            first.rigid_bodies["A_Pickup"],
+            second.rigid_bodies["B_Pickup"]
+        );
+        assert_eq!(first.sensors.keys().collect::<Vec<_>>(), ["A_Trigger"]);
+        assert_eq!(second.sensors.keys().collect::<Vec<_>>(), ["B_Trigger"]);
+        assert_eq!(
+            world.named_sensors.handle_with_name("A_Trigger"),
+            Some(&first.sensors["A_Trigger"])
+        );
+
+        // The root node's own transform is replaced by the instance's placement
+        let pickup_position = world.rigid_body_set[second.rigid_bodies["B_Pickup"]].translation();
+        assert!((pickup_position - Vector3::new(5.0, 0.0, 0.0)).norm() < 1e-5);
+        let trigger_position = second.points_of_interest["B_Trigger"].translation.vector;
+        assert!((trigger_position - Vector3::new(5.0, 1.0, 0.0)).norm() < 1e-5);
+        assert_eq!(first.points_of_interest.len(), 1);
+        assert!(first.points_of_interest.contains_key("A_Trigger"));
+
+        // Only the animation targeting the sub-tree is captured
+        let animation_names: Vec<&String> = first
+            .animations
+            .iter()
+            .chain(&second.animations)
+            .map(|animation| animation.name())
+            .collect();
+        assert_eq!(animation_names, ["A_Bob", "B_Bob"]);
+        assert!(second.animations[0]
+            .target_channels()
+            .contains_key("B_Pickup"));
+    }
 }
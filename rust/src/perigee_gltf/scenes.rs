@@ -0,0 +1,298 @@
+use gltf::{Gltf, Scene};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum GltfSceneSelectionError {
+    #[error("no glTF scene has the provided index")]
+    IndexNotFound,
+    #[error("no glTF scene has the provided name")]
+    NameNotFound,
+}
+
+/// Which scenes of a glTF document to load.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum GltfSceneSelector {
+    /// The first scene in the document.
+    #[default]
+    First,
+    /// The scene at the provided index.
+    Index(usize),
+    /// The first scene with the provided name.
+    Name(String),
+    /// Every scene in the document, in order.
+    All,
+}
+
+impl GltfSceneSelector {
+    /// The selected scenes of a glTF document. Selecting the first scene of
+    /// a document without scenes selects nothing.
+    pub fn select<'a>(&self, gltf: &'a Gltf) -> Result<Vec<Scene<'a>>, GltfSceneSelectionError> {
+        match self {
+            Self::First => Ok(gltf.scenes().take(1).collect()),
+            Self::Index(index) => match gltf.scenes().nth(*index) {
+                Some(scene) => Ok(vec![scene]),
+                None => Err(GltfSceneSelectionError::IndexNotFound),
+            },
+            Self::Name(name) => match gltf.scenes().find(|scene| scene.name() == Some(name)) {
+                Some(scene) => Ok(vec![scene]),
+                None => Err(GltfSceneSelectionError::NameNotFound),
+            },
+            Self::All => Ok(gltf.scenes().collect()),
+        }
+    }
+}
+
+/// The names of the nodes loaded from a single glTF scene.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedGltfScene {
+    pub index: usize,
+    pub name: Option<String>,
+    pub node_names: Vec<String>,
+}
+
+impl LoadedGltfScene {
+    pub(crate) fn new(scene: &Scene) -> Self {
+        Self {
+            index: scene.index(),
+            name: scene.name().map(String::from),
+            node_names: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::AnimationManager;
+    use crate::perigee_gltf::poi::PointsOfInterest;
+    use crate::perigee_gltf::util::glb;
+    use crate::physics::{KinematicAnimationBindings, PhysicsWorld};
+    use rapier3d::na::Vector3;
+
+    fn two_scene_gltf() -> Gltf {
+        let json = serde_json::json!({
+            "asset": { "version": "2.0" },
+            "scenes": [
+                { "name": "Level", "nodes": [0] },
+                { "name": "Props", "nodes": [1] }
+            ],
+            "nodes": [
+                { "name": "Floor", "translation": [0.0, -1.0, 0.0] },
+                { "name": "Crate", "translation": [2.0, 0.0, 0.0] }
+            ]
+        });
+        Gltf::from_slice(&serde_json::to_vec(&json).unwrap()).unwrap()
+    }
+
+    fn selected_indices(gltf: &Gltf, selector: GltfSceneSelector) -> Vec<usize> {
+        selector
+            .select(gltf)
+            .unwrap()
+            .iter()
+            .map(|scene| scene.index())
+            .collect()
+    }
+
+    #[test]
+    fn selectors_choose_scenes() {
+        let gltf = two_scene_gltf();
+        assert_eq!(selected_indices(&gltf, GltfSceneSelector::First), vec![0]);
+        assert_eq!(
+            selected_indices(&gltf, GltfSceneSelector::Index(1)),
+            vec![1]
+        );
+        assert_eq!(
+            selected_indices(&gltf, GltfSceneSelector::Name(String::from("Props"))),
+            vec![1]
+        );
+        assert_eq!(selected_indices(&gltf, GltfSceneSelector::All), vec![0, 1]);
+    }
+
+    #[test]
+    fn selecting_a_missing_scene_fails() {
+        let gltf = two_scene_gltf();
+        assert!(matches!(
+            GltfSceneSelector::Index(2).select(&gltf),
+            Err(GltfSceneSelectionError::IndexNotFound)
+        ));
+        assert!(matches!(
+            GltfSceneSelector::Name(String::from("Cutscene")).select(&gltf),
+            Err(GltfSceneSelectionError::NameNotFound)
+        ));
+        assert!(KinematicAnimationBindings::from_gltf_scenes(
+            &gltf,
+            &GltfSceneSelector::Index(2),
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn kinematic_bindings_record_selected_scenes() {
+        let gltf = two_scene_gltf();
+        let animations = AnimationManager::new();
+        let crate_position = |bindings: &KinematicAnimationBindings| {
+            bindings
+                .global_transform("Crate", &animations)
+                .isometry()
+                .translation
+                .vector
+        };
+
+        // The first scene doesn't contain the crate, so it isn't placed
+        let first_scene = KinematicAnimationBindings::from_gltf(&gltf, None);
+        assert_eq!(crate_position(&first_scene), Vector3::zeros());
+
+        for selector in [
+            GltfSceneSelector::Index(1),
+            GltfSceneSelector::Name(String::from("Props")),
+            GltfSceneSelector::All,
+        ] {
+            let bindings =
+                KinematicAnimationBindings::from_gltf_scenes(&gltf, &selector, None).unwrap();
+            assert_eq!(crate_position(&bindings), Vector3::new(2.0, 0.0, 0.0));
+        }
+    }
+
+    /// A GLB with a level scene holding a floor and a checkpoint sensor, and a props
+    /// scene holding a crate with a spawn point above it. Both scenes share the checkpoint.
+    fn two_scene_level_glb() -> Vec<u8> {
+        let mut binary_chunk: Vec<u8> = vec![];
+        for value in [-0.5f32, -0.5, -0.5, 0.5, -0.5, -0.5, 0.5, 0.5, 0.5] {
+            binary_chunk.extend_from_slice(&value.to_le_bytes());
+        }
+        let extras = |enabled: bool, body_type: &str, is_point_of_interest: bool| {
+            serde_json::json!({
+                "simSettings": {
+                    "physics": {
+                        "enabled": enabled,
+                        "isAnonymous": false,
+                        "bodyType": body_type,
+                        "mass": 1.0,
+                        "optimizedShape": "CUBOID",
+                        "baseScale": [1.0, 1.0, 1.0]
+                    },
+                    "isPointOfInterest": is_point_of_interest
+                }
+            })
+        };
+        let json = serde_json::json!({
+            "asset": { "version": "2.0" },
+            "scenes": [
+                { "name": "Level", "nodes": [0, 1] },
+                { "name": "Props", "nodes": [2, 1] }
+            ],
+            "nodes": [
+                {
+                    "name": "Floor",
+                    "translation": [0.0, -1.0, 0.0],
+                    "mesh": 0,
+                    "extras": extras(true, "STATIC", false)
+                },
+                {
+                    "name": "Checkpoint",
+                    "translation": [0.0, 0.0, 4.0],
+                    "extras": extras(true, "SENSOR", true)
+                },
+                {
+                    "name": "Crate",
+                    "translation": [2.0, 0.0, 0.0],
+                    "mesh": 0,
+                    "children": [3],
+                    "extras": extras(true, "DYNAMIC", false)
+                },
+                {
+                    "name": "Spawn",
+                    "translation": [0.0, 1.0, 0.0],
+                    "extras": extras(false, "STATIC", true)
+                }
+            ],
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 } }] }],
+            "accessors": [{
+                "bufferView": 0,
+                "componentType": 5126,
+                "count": 3,
+                "type": "VEC3",
+                "min": [-0.5, -0.5, -0.5],
+                "max": [0.5, 0.5, 0.5]
+            }],
+            "bufferViews": [{ "buffer": 0, "byteLength": binary_chunk.len() }],
+            "buffers": [{ "byteLength": binary_chunk.len() }]
+        });
+        glb(&json, &binary_chunk)
+    }
+
+    fn loaded_scene(index: usize, name: &str, node_names: &[&str]) -> LoadedGltfScene {
+        LoadedGltfScene {
+            index,
+            name: Some(String::from(name)),
+            node_names: node_names.iter().map(|name| String::from(*name)).collect(),
+        }
+    }
+
+    #[test]
+    fn physics_worlds_report_the_nodes_loaded_from_each_scene() {
+        let gltf = Gltf::from_slice(&two_scene_level_glb()).unwrap();
+
+        let mut world = PhysicsWorld::default();
+        let loaded = world
+            .load_scenes_from_gltf(&gltf, &GltfSceneSelector::Name(String::from("Props")), None)
+            .unwrap();
+        assert_eq!(loaded, [loaded_scene(1, "Props", &["Crate", "Checkpoint"])]);
+        assert!(world.named_rigid_bodies.handle_with_name("Floor").is_none());
+
+        let mut world = PhysicsWorld::default();
+        let loaded = world
+            .load_scenes_from_gltf(&gltf, &GltfSceneSelector::Index(0), None)
+            .unwrap();
+        assert_eq!(loaded, [loaded_scene(0, "Level", &["Floor", "Checkpoint"])]);
+        assert!(world.named_rigid_bodies.handle_with_name("Crate").is_none());
+
+        // Nodes shared between scenes are only loaded with the first scene that has them
+        let mut world = PhysicsWorld::default();
+        let loaded = world
+            .load_scenes_from_gltf(&gltf, &GltfSceneSelector::All, None)
+            .unwrap();
+        assert_eq!(
+            loaded,
+            [
+                loaded_scene(0, "Level", &["Floor", "Checkpoint"]),
+                loaded_scene(1, "Props", &["Crate"])
+            ]
+        );
+        assert_eq!(world.rigid_body_set.len(), 2);
+        assert_eq!(world.collider_set.len(), 3);
+    }
+
+    #[test]
+    fn points_of_interest_report_the_nodes_loaded_from_each_scene() {
+        let gltf = Gltf::from_slice(&two_scene_level_glb()).unwrap();
+
+        let mut points = PointsOfInterest::default();
+        let loaded = points
+            .load_scenes_from_gltf(&gltf, &GltfSceneSelector::Name(String::from("Props")))
+            .unwrap();
+        assert_eq!(loaded, [loaded_scene(1, "Props", &["Spawn", "Checkpoint"])]);
+        let spawn_position = points["Spawn"].translation.vector;
+        assert!((spawn_position - Vector3::new(2.0, 1.0, 0.0)).norm() < 1e-5);
+
+        let mut points = PointsOfInterest::default();
+        let loaded = points
+            .load_scenes_from_gltf(&gltf, &GltfSceneSelector::Index(0))
+            .unwrap();
+        assert_eq!(loaded, [loaded_scene(0, "Level", &["Checkpoint"])]);
+        assert!(points.point_with_name("Spawn").is_none());
+
+        let mut points = PointsOfInterest::default();
+        let loaded = points
+            .load_scenes_from_gltf(&gltf, &GltfSceneSelector::All)
+            .unwrap();
+        assert_eq!(
+            loaded,
+            [
+                loaded_scene(0, "Level", &["Checkpoint"]),
+                loaded_scene(1, "Props", &["Spawn"])
+            ]
+        );
+    }
+}
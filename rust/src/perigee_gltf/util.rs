@@ -1,4 +1,6 @@
-use gltf::accessor::Accessor;
+use crate::math::Transform3;
+use gltf::{accessor::Accessor, Node};
+use rapier3d::na::{Isometry3, Quaternion, Translation3, UnitQuaternion, Vector3};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -8,7 +10,7 @@ pub enum GltfAccessorViewError {
 }
 
 pub fn access_gltf_bytes<'a>(
-    gltf_bytes: &'a Vec<u8>,
+    gltf_bytes: &'a [u8],
     accessor: &'a Accessor,
 ) -> Result<&'a [u8], GltfAccessorViewError> {
     if let Some(buffer_view) = accessor.view() {
@@ -18,3 +20,43 @@ pub fn access_gltf_bytes<'a>(
         Err(GltfAccessorViewError::NoAccessorFound)
     }
 }
+
+/// The transform of a glTF node relative to its parent.
+pub fn node_local_transform(node: &Node) -> Transform3<f32> {
+    let (translation, quaternion, scale) = node.transform().decomposed();
+    Transform3::from_parts(
+        Isometry3::from_parts(
+            Translation3::new(translation[0], translation[1], translation[2]),
+            UnitQuaternion::from_quaternion(Quaternion::new(
+                quaternion[3],
+                quaternion[0],
+                quaternion[1],
+                quaternion[2],
+            )),
+        ),
+        Vector3::new(scale[0], scale[1], scale[2]),
+    )
+}
+
+/// Pack a glTF document and its binary buffer into a GLB.
+#[cfg(test)]
+pub(crate) fn glb(json: &serde_json::Value, binary_chunk: &[u8]) -> Vec<u8> {
+    let mut json_chunk = serde_json::to_vec(json).unwrap();
+    // GLB chunks are aligned to 4 bytes, and JSON chunks are padded with spaces
+    json_chunk.resize(json_chunk.len().div_ceil(4) * 4, b' ');
+    let mut binary_chunk = binary_chunk.to_vec();
+    binary_chunk.resize(binary_chunk.len().div_ceil(4) * 4, 0);
+
+    let total_length = 12 + 8 + json_chunk.len() + 8 + binary_chunk.len();
+    let mut glb: Vec<u8> = vec![];
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(total_length as u32).to_le_bytes());
+    glb.extend_from_slice(&(json_chunk.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(&json_chunk);
+    glb.extend_from_slice(&(binary_chunk.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"BIN\0");
+    glb.extend_from_slice(&binary_chunk);
+    glb
+}
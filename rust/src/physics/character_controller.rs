@@ -0,0 +1,431 @@
+use crate::config::CharacterControllerConfig;
+use crate::math::{move_towards, project_on_plane};
+use crate::physics::PhysicsWorld;
+use crate::traits::FromConfig;
+use rapier3d::control::{CharacterAutostep, CharacterLength, KinematicCharacterController};
+use rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CharacterGroundState {
+    Grounded,
+    Airborne,
+}
+
+/// Moves a kinematic capsule through a [PhysicsWorld]. The capsule walks
+/// up slopes and steps within its limits, sticks to the ground when walking
+/// down them, slides along walls and falls with the world's gravity.
+///
+/// Call [update](Self::update) once per frame before stepping the world.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterController {
+    config: CharacterControllerConfig,
+    body_handle: Option<RigidBodyHandle>,
+    velocity: Vector<Real>,
+    ground_state: CharacterGroundState,
+}
+
+impl FromConfig for CharacterController {
+    type Config<'a> = &'a CharacterControllerConfig;
+
+    fn from_config<'a>(config: Self::Config<'a>) -> Self {
+        Self {
+            config: *config,
+            body_handle: None,
+            velocity: Vector::zeros(),
+            ground_state: CharacterGroundState::Airborne,
+        }
+    }
+
+    fn set_config<'a>(&mut self, config: Self::Config<'a>) {
+        self.config = *config;
+    }
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self::from_config(&CharacterControllerConfig::default())
+    }
+}
+
+impl CharacterController {
+    fn rapier_controller(&self) -> KinematicCharacterController {
+        let config = &self.config;
+        KinematicCharacterController {
+            up: Vector::y_axis(),
+            offset: CharacterLength::Absolute(config.skin_width()),
+            slide: true,
+            autostep: if config.max_step_height() > 0.0 {
+                Some(CharacterAutostep {
+                    max_height: CharacterLength::Absolute(config.max_step_height()),
+                    min_width: CharacterLength::Absolute(config.min_step_width()),
+                    include_dynamic_bodies: false,
+                })
+            } else {
+                None
+            },
+            max_slope_climb_angle: config.max_slope_climb_degrees().to_radians(),
+            min_slope_slide_angle: config.min_slope_slide_degrees().to_radians(),
+            snap_to_ground: if config.ground_snap_distance() > 0.0 {
+                Some(CharacterLength::Absolute(config.ground_snap_distance()))
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Insert the character's kinematic body and capsule collider into the world,
+    /// with the capsule centered at `position`. The body is named `name`.
+    /// A body spawned earlier is [despawned](Self::despawn) first.
+    pub fn spawn(
+        &mut self,
+        world: &mut PhysicsWorld,
+        name: impl Into<String>,
+        position: Vector<Real>,
+    ) -> RigidBodyHandle {
+        self.despawn(world);
+        let body_handle = world.rigid_body_set.insert(
+            RigidBodyBuilder::kinematic_position_based()
+                .translation(position)
+                .build(),
+        );
+        world.collider_set.insert_with_parent(
+            ColliderBuilder::capsule_y(
+                self.config.capsule_half_height(),
+                self.config.capsule_radius(),
+            )
+            .build(),
+            body_handle,
+            &mut world.rigid_body_set,
+        );
+        world.named_rigid_bodies.insert(name, body_handle);
+        self.body_handle = Some(body_handle);
+        self.velocity = Vector::zeros();
+        self.ground_state = CharacterGroundState::Airborne;
+        body_handle
+    }
+
+    /// Remove the character's body, its collider and its name from the world.
+    /// Returns false if the character wasn't spawned.
+    pub fn despawn(&mut self, world: &mut PhysicsWorld) -> bool {
+        let body_handle = match self.body_handle.take() {
+            Some(handle) => handle,
+            None => return false,
+        };
+        world.named_rigid_bodies.remove_by_handle(&body_handle);
+        self.velocity = Vector::zeros();
+        self.ground_state = CharacterGroundState::Airborne;
+        world.remove_body(body_handle).is_some()
+    }
+
+    pub fn body_handle(&self) -> Option<RigidBodyHandle> {
+        self.body_handle
+    }
+
+    pub fn velocity(&self) -> &Vector<Real> {
+        &self.velocity
+    }
+
+    pub fn ground_state(&self) -> CharacterGroundState {
+        self.ground_state
+    }
+
+    pub fn is_grounded(&self) -> bool {
+        self.ground_state == CharacterGroundState::Grounded
+    }
+
+    /// Move the character towards `move_direction`, whose horizontal part is scaled
+    /// by the max speed and clamped to a length of 1. Jumping only works while grounded.
+    /// The character's new position takes effect on the world's next step.
+    pub fn update(
+        &mut self,
+        world: &mut PhysicsWorld,
+        move_direction: &Vector<Real>,
+        jump: bool,
+        delta_seconds: f32,
+    ) {
+        let body_handle = match self.body_handle {
+            Some(handle) if delta_seconds > 0.0 => handle,
+            _ => return,
+        };
+        let (character_position, character_shape) = match world.rigid_body_set.get(body_handle) {
+            Some(body) => match body
+                .colliders()
+                .first()
+                .and_then(|collider_handle| world.collider_set.get(*collider_handle))
+            {
+                Some(collider) => (*collider.position(), collider.shared_shape().clone()),
+                None => return,
+            },
+            None => return,
+        };
+        let rapier_controller = self.rapier_controller();
+        let up = rapier_controller.up.into_inner();
+        let mut move_direction = project_on_plane(move_direction, &up);
+        if move_direction.norm_squared() > 1.0 {
+            move_direction.normalize_mut();
+        }
+        let horizontal_velocity = project_on_plane(&self.velocity, &up);
+        let mut vertical_speed = self.velocity.dot(&up);
+        let acceleration = if self.is_grounded() {
+            self.config.ground_acceleration()
+        } else {
+            self.config.air_acceleration()
+        };
+        let horizontal_velocity = move_towards(
+            &horizontal_velocity,
+            &(move_direction * self.config.max_speed()),
+            acceleration * delta_seconds,
+        );
+
+        if self.is_grounded() && jump {
+            vertical_speed = self.config.jump_speed();
+        } else {
+            vertical_speed += world.gravity.dot(&up) * delta_seconds;
+        }
+        let desired_translation = (horizontal_velocity + up * vertical_speed) * delta_seconds;
+
+        let mut hit_walkable_ground = false;
+        let mut hit_steep_ground = false;
+        let movement = rapier_controller.move_shape(
+            delta_seconds,
+            &world.rigid_body_set,
+            &world.collider_set,
+            &world.query_pipeline,
+            &*character_shape,
+            &character_position,
+            desired_translation,
+            QueryFilter::default()
+                .exclude_rigid_body(body_handle)
+                .exclude_sensors(),
+            |collision| {
+                let ground_normal = collision.toi.normal1;
+                if ground_normal.dot(&up) > 0.0 {
+                    if up.angle(&ground_normal) <= rapier_controller.max_slope_climb_angle {
+                        hit_walkable_ground = true;
+                    } else {
+                        hit_steep_ground = true;
+                    }
+                }
+            },
+        );
+        // Slopes too steep to climb can't be stood on, so the character keeps
+        // falling and slides down them
+        let on_ground = movement.grounded && (hit_walkable_ground || !hit_steep_ground);
+
+        // Keep only the velocity that wasn't absorbed by walls and the ground
+        let applied_velocity = movement.translation / delta_seconds;
+        let horizontal_velocity = project_on_plane(&applied_velocity, &up);
+        if on_ground && vertical_speed <= 0.0 {
+            self.ground_state = CharacterGroundState::Grounded;
+            self.velocity = horizontal_velocity;
+        } else {
+            self.ground_state = CharacterGroundState::Airborne;
+            if vertical_speed > 0.0 && applied_velocity.dot(&up) <= 0.0 {
+                // Bumped into a ceiling
+                vertical_speed = 0.0;
+            }
+            self.velocity = horizontal_velocity + up * vertical_speed;
+        }
+
+        let body = &mut world.rigid_body_set[body_handle];
+        let next_translation = body.translation() + movement.translation;
+        body.set_next_kinematic_translation(next_translation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world_with_floor_and_wall() -> PhysicsWorld {
+        let mut world = PhysicsWorld::default();
+        world.collider_set.insert(
+            ColliderBuilder::cuboid(50.0, 0.5, 50.0)
+                .translation(vector![0.0, -0.5, 0.0])
+                .build(),
+        );
+        world.collider_set.insert(
+            ColliderBuilder::cuboid(0.5, 5.0, 50.0)
+                .translation(vector![5.5, 5.0, 0.0])
+                .build(),
+        );
+        world.step(1.0 / 60.0);
+        world
+    }
+
+    /// A ramp rising towards +X whose surface passes through the origin.
+    fn world_with_ramp(degrees: f32) -> PhysicsWorld {
+        let mut world = PhysicsWorld::default();
+        let angle = degrees.to_radians();
+        let surface_normal = vector![-angle.sin(), angle.cos(), 0.0];
+        world.collider_set.insert(
+            ColliderBuilder::cuboid(20.0, 0.5, 20.0)
+                .position(Isometry::new(
+                    -0.5 * surface_normal,
+                    vector![0.0, 0.0, angle],
+                ))
+                .build(),
+        );
+        world.step(1.0 / 60.0);
+        world
+    }
+
+    fn run(
+        character: &mut CharacterController,
+        world: &mut PhysicsWorld,
+        move_direction: Vector<Real>,
+        frames: usize,
+    ) {
+        for _ in 0..frames {
+            character.update(world, &move_direction, false, 1.0 / 60.0);
+            world.step(1.0 / 60.0);
+        }
+    }
+
+    #[test]
+    fn character_slides_down_steep_slopes() {
+        let config = CharacterControllerConfig::default();
+        let mut resting_x = vec![];
+        for degrees in [15.0, 55.0] {
+            let mut world = world_with_ramp(degrees);
+            let mut character = CharacterController::from_config(&config);
+            let body_handle = character.spawn(&mut world, "Player", vector![0.0, 1.5, 0.0]);
+            run(&mut character, &mut world, Vector::zeros(), 30);
+            let landed_x = world.rigid_body_set[body_handle].translation().x;
+            run(&mut character, &mut world, Vector::zeros(), 60);
+            assert_eq!(
+                character.is_grounded(),
+                degrees < config.max_slope_climb_degrees()
+            );
+            resting_x.push(world.rigid_body_set[body_handle].translation().x - landed_x);
+        }
+        // Gentle slopes hold the character in place, steep ones slide it downhill
+        assert!(resting_x[0].abs() < 0.05);
+        assert!(resting_x[1] < -0.5);
+    }
+
+    #[test]
+    fn character_steps_up_low_ledges() {
+        let config = CharacterControllerConfig::default();
+        for (step_height, climbs) in [(0.2, true), (0.6, false)] {
+            let mut world = world_with_floor_and_wall();
+            world.collider_set.insert(
+                ColliderBuilder::cuboid(1.0, 0.5 * step_height, 50.0)
+                    .translation(vector![3.0, 0.5 * step_height, 0.0])
+                    .build(),
+            );
+            world.step(1.0 / 60.0);
+            let mut character = CharacterController::from_config(&config);
+            let body_handle = character.spawn(&mut world, "Player", vector![0.0, 1.0, 0.0]);
+            run(&mut character, &mut world, Vector::zeros(), 30);
+            run(&mut character, &mut world, vector![1.0, 0.0, 0.0], 60);
+
+            let position = world.rigid_body_set[body_handle].translation();
+            assert_eq!(position.x > 2.5, climbs, "step of height {step_height}");
+            if climbs {
+                assert!((position.y - (0.9 + step_height)).abs() < 0.05);
+            }
+        }
+    }
+
+    #[test]
+    fn character_snaps_to_the_ground_below_small_drops() {
+        let mut airborne_frames = vec![];
+        for ground_snap_distance in [0.0, 0.2] {
+            let config: CharacterControllerConfig =
+                toml::from_str(&format!("ground_snap_distance = {ground_snap_distance}")).unwrap();
+            let mut world = world_with_floor_and_wall();
+            // A raised platform that drops 0.15 back to the floor at x = 1
+            world.collider_set.insert(
+                ColliderBuilder::cuboid(5.0, 0.075, 50.0)
+                    .translation(vector![-4.0, 0.075, 0.0])
+                    .build(),
+            );
+            world.step(1.0 / 60.0);
+            let mut character = CharacterController::from_config(&config);
+            let body_handle = character.spawn(&mut world, "Player", vector![0.0, 1.1, 0.0]);
+            run(&mut character, &mut world, Vector::zeros(), 30);
+            assert!(character.is_grounded());
+
+            let mut frames = 0;
+            for _ in 0..60 {
+                character.update(&mut world, &vector![1.0, 0.0, 0.0], false, 1.0 / 60.0);
+                world.step(1.0 / 60.0);
+                if !character.is_grounded() {
+                    frames += 1;
+                }
+            }
+            assert!(world.rigid_body_set[body_handle].translation().x > 2.0);
+            airborne_frames.push(frames);
+        }
+        assert!(airborne_frames[0] > 0);
+        assert_eq!(airborne_frames[1], 0);
+    }
+
+    #[test]
+    fn respawning_replaces_the_previous_body() {
+        let mut world = world_with_floor_and_wall();
+        let mut character = CharacterController::default();
+        let first_handle = character.spawn(&mut world, "Player", vector![0.0, 1.0, 0.0]);
+        let second_handle = character.spawn(&mut world, "Player", vector![2.0, 1.0, 0.0]);
+
+        assert!(world.rigid_body_set.get(first_handle).is_none());
+        assert_eq!(world.rigid_body_set.len(), 1);
+        assert_eq!(world.collider_set.len(), 3);
+        assert_eq!(
+            world.named_rigid_bodies.handle_with_name("Player"),
+            Some(&second_handle)
+        );
+
+        assert!(character.despawn(&mut world));
+        assert!(!character.despawn(&mut world));
+        assert!(world.rigid_body_set.is_empty());
+        assert!(world
+            .named_rigid_bodies
+            .handle_with_name("Player")
+            .is_none());
+    }
+
+    #[test]
+    fn character_lands_and_stops_at_walls() {
+        let config: CharacterControllerConfig = toml::from_str(
+            r#"
+            capsule_radius = 0.5
+            capsule_half_height = 0.5
+            max_speed = 4.0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.jump_speed(),
+            CharacterControllerConfig::default().jump_speed()
+        );
+
+        let mut world = world_with_floor_and_wall();
+        let mut character = CharacterController::from_config(&config);
+        let body_handle = character.spawn(&mut world, "Player", vector![0.0, 3.0, 0.0]);
+
+        for _ in 0..120 {
+            character.update(&mut world, &Vector::zeros(), false, 1.0 / 60.0);
+            world.step(1.0 / 60.0);
+        }
+        assert!(character.is_grounded());
+        let resting_height = world.rigid_body_set[body_handle].translation().y;
+        assert!((resting_height - 1.0).abs() < 0.05);
+
+        for _ in 0..240 {
+            character.update(&mut world, &vector![1.0, 0.0, 0.0], false, 1.0 / 60.0);
+            world.step(1.0 / 60.0);
+        }
+        let position = world.rigid_body_set[body_handle].translation();
+        assert!(position.x > 4.4 && position.x < 4.5);
+        assert!(character.velocity().x.abs() < 0.1);
+        assert!(character.is_grounded());
+
+        character.update(&mut world, &Vector::zeros(), true, 1.0 / 60.0);
+        world.step(1.0 / 60.0);
+        assert_eq!(character.ground_state(), CharacterGroundState::Airborne);
+        assert!(world.rigid_body_set[body_handle].translation().y > resting_height);
+    }
+}
@@ -0,0 +1,261 @@
+use crate::config::PhysicsConfig;
+use crate::traits::physics::ColliderEventListener;
+use rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The most collision layers a world can have, one per interaction group bit.
+pub const MAX_COLLISION_LAYERS: usize = 32;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CollisionLayerError {
+    #[error("at most {MAX_COLLISION_LAYERS} collision layers can be defined")]
+    TooManyLayers,
+    #[error("collision layer {0} is defined more than once")]
+    DuplicateLayer(String),
+    #[error("unknown collision layer {0}")]
+    UnknownLayer(String),
+}
+
+/// Named collision layers, each backed by one of rapier's interaction group bits.
+///
+/// A collider on a layer is a member of the layer's group and interacts
+/// with the layers the layer interacts with. Two colliders only interact
+/// if each one's layer interacts with the other's, so a layer can be made to
+/// ignore another without configuring both. Colliders that aren't on a
+/// layer are members of every layer's group, so they interact with every
+/// layer and pass every layer filter.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CollisionLayers {
+    names: Vec<String>,
+    /// The interaction group bits each layer interacts with, indexed like `names`.
+    filters: Vec<u32>,
+}
+
+impl CollisionLayers {
+    /// Create layers from their names and the names of the layers each interacts with.
+    /// Layers without interactions interact with every layer.
+    pub fn new<'a>(
+        names: &[String],
+        interactions: impl IntoIterator<Item = (&'a String, &'a Vec<String>)>,
+    ) -> Result<Self, CollisionLayerError> {
+        if names.len() > MAX_COLLISION_LAYERS {
+            return Err(CollisionLayerError::TooManyLayers);
+        }
+        let mut layers = Self {
+            names: Vec::with_capacity(names.len()),
+            filters: vec![u32::MAX; names.len()],
+        };
+        for name in names {
+            if layers.names.contains(name) {
+                return Err(CollisionLayerError::DuplicateLayer(name.clone()));
+            }
+            layers.names.push(name.clone());
+        }
+        for (layer_name, interacting_layer_names) in interactions {
+            let layer_index = layers.index(layer_name)?;
+            layers.filters[layer_index] = layers.mask(interacting_layer_names)?.bits();
+        }
+        Ok(layers)
+    }
+
+    pub fn from_config(config: &PhysicsConfig) -> Result<Self, CollisionLayerError> {
+        Self::new(
+            config.collision_layers(),
+            config.collision_layer_interactions(),
+        )
+    }
+
+    fn index(&self, layer_name: &str) -> Result<usize, CollisionLayerError> {
+        self.names
+            .iter()
+            .position(|name| name == layer_name)
+            .ok_or_else(|| CollisionLayerError::UnknownLayer(String::from(layer_name)))
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// The interaction group a layer's colliders are members of.
+    pub fn group(&self, layer_name: &str) -> Result<Group, CollisionLayerError> {
+        Ok(Group::from_bits_truncate(1 << self.index(layer_name)?))
+    }
+
+    /// The union of the groups of several layers.
+    pub fn mask<S: AsRef<str>>(&self, layer_names: &[S]) -> Result<Group, CollisionLayerError> {
+        layer_names
+            .iter()
+            .try_fold(Group::NONE, |mask, layer_name| {
+                Ok(mask | self.group(layer_name.as_ref())?)
+            })
+    }
+
+    /// The collision groups of a collider on a layer.
+    pub fn interaction_groups(
+        &self,
+        layer_name: &str,
+    ) -> Result<InteractionGroups, CollisionLayerError> {
+        let layer_index = self.index(layer_name)?;
+        Ok(InteractionGroups::new(
+            Group::from_bits_truncate(1 << layer_index),
+            Group::from_bits_truncate(self.filters[layer_index]),
+        ))
+    }
+
+    /// The name of the layer a collider's collision groups put it on, if any.
+    pub fn layer_of(&self, collision_groups: InteractionGroups) -> Option<&str> {
+        let memberships = collision_groups.memberships.bits();
+        if memberships.count_ones() != 1 {
+            return None;
+        }
+        self.names
+            .get(memberships.trailing_zeros() as usize)
+            .map(String::as_str)
+    }
+}
+
+/// Forwards the events of another listener's collider that involve colliders on the
+/// given layers. See [listen_to_collider_on_layers](crate::physics::PhysicsWorld::listen_to_collider_on_layers).
+pub(crate) struct LayerFilteredListener<L> {
+    pub(crate) listener: L,
+    pub(crate) layers: Group,
+}
+
+impl<L: ColliderEventListener> ColliderEventListener for LayerFilteredListener<L> {
+    fn on_collision_start(&mut self, other: &ColliderHandle) {
+        self.listener.on_collision_start(other);
+    }
+
+    fn on_collision_end(&mut self, other: &ColliderHandle) {
+        self.listener.on_collision_end(other);
+    }
+
+    fn on_intersection_start(&mut self, other: &ColliderHandle) {
+        self.listener.on_intersection_start(other);
+    }
+
+    fn on_intersection_end(&mut self, other: &ColliderHandle) {
+        self.listener.on_intersection_end(other);
+    }
+
+    fn on_contact_force_event(&mut self, other: &ColliderHandle, details: ContactForceEvent) {
+        self.listener.on_contact_force_event(other, details);
+    }
+
+    fn listened_layers(&self) -> Group {
+        self.layers & self.listener.listened_layers()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{ColliderEvent, ColliderEventRelayer, PhysicsWorld};
+    use crate::traits::FromConfig;
+
+    #[test]
+    fn layers_filter_contacts_queries_and_listeners() {
+        let config: PhysicsConfig = toml::from_str(
+            r#"
+            gravity = [0.0, 0.0, 0.0]
+            event_queue_capacity = 16
+            collision_layers = ["player", "enemy", "player_projectile", "pickup"]
+
+            [collision_layer_interactions]
+            player_projectile = ["enemy"]
+            pickup = ["player"]
+            "#,
+        )
+        .unwrap();
+        let layers = CollisionLayers::from_config(&config).unwrap();
+        let groups = |layer_name| layers.interaction_groups(layer_name).unwrap();
+        assert!(!groups("player_projectile").test(groups("player")));
+        assert!(groups("player_projectile").test(groups("enemy")));
+        assert!(groups("player").test(groups("enemy")));
+        assert_eq!(layers.layer_of(groups("pickup")), Some("pickup"));
+        assert_eq!(
+            layers.group("wall"),
+            Err(CollisionLayerError::UnknownLayer(String::from("wall")))
+        );
+
+        let mut world = PhysicsWorld::from_config(&config);
+        let pickup_handle = world.collider_set.insert(
+            ColliderBuilder::ball(1.0)
+                .sensor(true)
+                .active_events(ActiveEvents::COLLISION_EVENTS)
+                .build(),
+        );
+        world.set_collider_layer(pickup_handle, "pickup").unwrap();
+        let insert_ball = |world: &mut PhysicsWorld, layer_name| {
+            let body_handle = world
+                .rigid_body_set
+                .insert(RigidBodyBuilder::dynamic().build());
+            let collider_handle = world.collider_set.insert_with_parent(
+                ColliderBuilder::ball(0.5).build(),
+                body_handle,
+                &mut world.rigid_body_set,
+            );
+            world
+                .set_collider_layer(collider_handle, layer_name)
+                .unwrap();
+            collider_handle
+        };
+        let player_handle = insert_ball(&mut world, "player");
+        let enemy_handle = insert_ball(&mut world, "enemy");
+
+        let (event_sender, event_receiver) = crossbeam::channel::unbounded();
+        world
+            .listen_to_collider_on_layers(
+                pickup_handle,
+                ColliderEventRelayer::from(event_sender),
+                &["player", "enemy"],
+            )
+            .unwrap();
+        world.step(1.0 / 60.0);
+
+        // The pickup only interacts with the player even though it listens to enemies
+        let events: Vec<ColliderEvent> = event_receiver.try_iter().collect();
+        assert_eq!(events.len(), 1);
+        assert!(
+            matches!(events[0], ColliderEvent::IntersectionStart(handle) if handle == player_handle)
+        );
+
+        let enemy_filter = world.layer_query_filter(&["enemy"]).unwrap();
+        let overlaps = world.overlap_sphere(Point::origin(), 0.1, enemy_filter);
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!(overlaps[0].collider, enemy_handle);
+    }
+
+    #[test]
+    fn invalid_layer_configs_dont_deserialize() {
+        let config_with_layers = |layers: &str| {
+            toml::from_str::<PhysicsConfig>(&format!(
+                "gravity = [0.0, -9.81, 0.0]\nevent_queue_capacity = 5\n{layers}"
+            ))
+        };
+        assert!(config_with_layers(r#"collision_layers = ["player", "enemy"]"#).is_ok());
+
+        let error = config_with_layers(
+            r#"
+            collision_layers = ["player"]
+            [collision_layer_interactions]
+            player = ["wall"]
+            "#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("unknown collision layer wall"));
+        let error = config_with_layers(r#"collision_layers = ["player", "player"]"#).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("collision layer player is defined more than once"));
+        let too_many_layers: Vec<String> = (0..=MAX_COLLISION_LAYERS)
+            .map(|layer| format!("\"layer{layer}\""))
+            .collect();
+        assert!(config_with_layers(&format!(
+            "collision_layers = [{}]",
+            too_many_layers.join(", ")
+        ))
+        .is_err());
+    }
+}
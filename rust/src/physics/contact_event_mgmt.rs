@@ -45,16 +45,10 @@ impl ContactEventManager {
 
     pub fn eviscerate_channels(&self) -> Result<(), TryRecvError> {
         while !self.contact_force_event_receiver.is_empty() {
-            match self.get_contact_force_event() {
-                Err(e) => return Err(e),
-                _ => {}
-            }
+            self.get_contact_force_event()?;
         }
         while !self.collision_event_receiver.is_empty() {
-            match self.get_collider_event() {
-                Err(e) => return Err(e),
-                _ => {}
-            }
+            self.get_collider_event()?;
         }
         Ok(())
     }
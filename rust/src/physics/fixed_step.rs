@@ -0,0 +1,251 @@
+use crate::config::PhysicsConfig;
+use crate::physics::PhysicsWorld;
+use crate::traits::FromConfig;
+use log::warn;
+use rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How close to a full tick the accumulator must be to take it. This keeps
+/// rounding in the host's frame times from skipping ticks.
+const TICK_TOLERANCE: f64 = 1e-5;
+
+/// Steps a [PhysicsWorld] in fixed increments regardless of the host's frame
+/// rate. Frame time is banked in an accumulator and spent one tick at a time,
+/// so the same sequence of ticks produces the same simulation on every host.
+///
+/// Rendering usually happens between ticks, so the stepper remembers where
+/// every moving body was before the latest tick and can blend between that
+/// and its current position.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixedStepper {
+    timestep: f32,
+    max_substeps: u32,
+    accumulator: f64,
+    previous_isometries: HashMap<RigidBodyHandle, Isometry<Real>>,
+}
+
+impl FromConfig for FixedStepper {
+    type Config<'a> = &'a PhysicsConfig;
+    fn from_config<'a>(config: Self::Config<'a>) -> Self {
+        Self::new(config.fixed_timestep(), config.max_substeps())
+    }
+
+    fn set_config<'a>(&mut self, config: Self::Config<'a>) {
+        self.timestep = valid_timestep(config.fixed_timestep());
+        self.max_substeps = config.max_substeps();
+    }
+}
+
+impl Default for FixedStepper {
+    fn default() -> Self {
+        Self::from_config(&PhysicsConfig::default())
+    }
+}
+
+/// Ticks must move time forward, so timesteps that are zero, negative, NaN or
+/// infinite are replaced with the default timestep.
+fn valid_timestep(timestep: f32) -> f32 {
+    if timestep.is_finite() && timestep > 0.0 {
+        return timestep;
+    }
+    let default_timestep = PhysicsConfig::default().fixed_timestep();
+    warn!(
+        "FixedStepper timestep must be positive and finite, using {} instead of {}",
+        default_timestep, timestep
+    );
+    default_timestep
+}
+
+impl FixedStepper {
+    /// Create a stepper that ticks every `timestep` seconds. A timestep that isn't
+    /// positive and finite is replaced with the default timestep.
+    pub fn new(timestep: f32, max_substeps: u32) -> Self {
+        Self {
+            timestep: valid_timestep(timestep),
+            max_substeps,
+            accumulator: 0.0,
+            previous_isometries: HashMap::new(),
+        }
+    }
+
+    pub fn timestep(&self) -> f32 {
+        self.timestep
+    }
+
+    pub fn max_substeps(&self) -> u32 {
+        self.max_substeps
+    }
+
+    /// How far the simulation is between the last tick and the next one,
+    /// from 0.0 (just ticked) to 1.0 (about to tick).
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / f64::from(self.timestep)).clamp(0.0, 1.0) as f32
+    }
+
+    /// Spend the host's frame time on as many fixed ticks as it covers.
+    /// Returns the number of ticks taken.
+    pub fn advance(&mut self, world: &mut PhysicsWorld, frame_delta_seconds: f32) -> u32 {
+        self.advance_with(world, frame_delta_seconds, |_, _| {})
+    }
+
+    /// Spend the host's frame time on as many fixed ticks as it covers, calling
+    /// `on_tick` before each one so game logic runs at the same fixed rate.
+    /// Returns the number of ticks taken.
+    ///
+    /// Time that would need more than [max_substeps](Self::max_substeps) ticks
+    /// is dropped so that slow frames don't snowball into even slower ones.
+    pub fn advance_with(
+        &mut self,
+        world: &mut PhysicsWorld,
+        frame_delta_seconds: f32,
+        mut on_tick: impl FnMut(&mut PhysicsWorld, f32),
+    ) -> u32 {
+        let timestep = f64::from(self.timestep);
+        let tick_threshold = timestep * (1.0 - TICK_TOLERANCE);
+        self.accumulator += f64::from(frame_delta_seconds.max(0.0));
+
+        let mut ticks = 0;
+        while self.accumulator >= tick_threshold && ticks < self.max_substeps {
+            on_tick(world, self.timestep);
+            self.record_isometries(world);
+            world.step(self.timestep);
+            self.accumulator = (self.accumulator - timestep).max(0.0);
+            ticks += 1;
+        }
+
+        if self.accumulator >= tick_threshold {
+            warn!(
+                "FixedStepper dropped {} seconds after reaching {} substeps",
+                self.accumulator - self.accumulator % timestep,
+                self.max_substeps
+            );
+            self.accumulator %= timestep;
+        }
+        ticks
+    }
+
+    fn record_isometries(&mut self, world: &PhysicsWorld) {
+        self.previous_isometries.clear();
+        for (handle, body) in world.rigid_body_set.iter() {
+            if !body.is_fixed() {
+                self.previous_isometries.insert(handle, *body.position());
+            }
+        }
+    }
+
+    /// The body's isometry blended between the last two ticks by [alpha](Self::alpha).
+    pub fn interpolated_isometry(
+        &self,
+        world: &PhysicsWorld,
+        handle: RigidBodyHandle,
+    ) -> Option<Isometry<Real>> {
+        let current_isometry = world.rigid_body_set.get(handle)?.position();
+        Some(match self.previous_isometries.get(&handle) {
+            Some(previous_isometry) => previous_isometry.lerp_slerp(current_isometry, self.alpha()),
+            None => *current_isometry,
+        })
+    }
+
+    /// The named body's isometry blended between the last two ticks by [alpha](Self::alpha).
+    pub fn interpolated_isometry_by_name(
+        &self,
+        world: &PhysicsWorld,
+        body_name: &str,
+    ) -> Option<Isometry<Real>> {
+        let handle = world.named_rigid_bodies.handle_with_name(body_name)?;
+        self.interpolated_isometry(world, *handle)
+    }
+
+    /// The blended isometry of every non-fixed body.
+    pub fn interpolated_isometries(
+        &self,
+        world: &PhysicsWorld,
+    ) -> HashMap<RigidBodyHandle, Isometry<Real>> {
+        world
+            .rigid_body_set
+            .iter()
+            .filter(|(_, body)| !body.is_fixed())
+            .filter_map(|(handle, _)| {
+                self.interpolated_isometry(world, handle)
+                    .map(|isometry| (handle, isometry))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_rate_doesnt_change_the_simulation() {
+        let falling_world = || {
+            let mut world = PhysicsWorld::default();
+            let handle = world
+                .rigid_body_set
+                .insert(RigidBodyBuilder::dynamic().build());
+            world.collider_set.insert_with_parent(
+                ColliderBuilder::ball(0.5).build(),
+                handle,
+                &mut world.rigid_body_set,
+            );
+            (world, handle)
+        };
+        let (mut fast_world, handle) = falling_world();
+        let (mut slow_world, _) = falling_world();
+        let mut fast_stepper = FixedStepper::new(1.0 / 60.0, 8);
+        let mut slow_stepper = FixedStepper::new(1.0 / 60.0, 8);
+
+        let mut fast_ticks = 0;
+        for _ in 0..120 {
+            fast_ticks += fast_stepper.advance(&mut fast_world, 1.0 / 120.0);
+        }
+        let mut slow_ticks = 0;
+        for _ in 0..40 {
+            slow_ticks += slow_stepper.advance(&mut slow_world, 1.0 / 40.0);
+        }
+
+        assert_eq!(fast_ticks, slow_ticks);
+        assert_eq!(
+            fast_world.rigid_body_set[handle].translation(),
+            slow_world.rigid_body_set[handle].translation()
+        );
+
+        // Halfway between ticks, rendering sits halfway between positions
+        slow_stepper.advance(&mut slow_world, 1.0 / 120.0);
+        let previous_y = slow_stepper.previous_isometries[&handle].translation.y;
+        let current_y = slow_world.rigid_body_set[handle].translation().y;
+        let interpolated_y = slow_stepper
+            .interpolated_isometry(&slow_world, handle)
+            .unwrap()
+            .translation
+            .y;
+        assert!((interpolated_y - (previous_y + current_y) / 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn invalid_timesteps_use_the_default() {
+        let default_timestep = PhysicsConfig::default().fixed_timestep();
+        let mut world = PhysicsWorld::default();
+        for timestep in [0.0, -1.0 / 60.0, f32::NAN, f32::INFINITY] {
+            let mut stepper = FixedStepper::new(timestep, 8);
+            assert_eq!(stepper.timestep(), default_timestep);
+            assert_eq!(stepper.alpha(), 0.0);
+            assert_eq!(stepper.advance(&mut world, default_timestep), 1);
+            assert!(stepper.alpha().is_finite());
+        }
+
+        let mut stepper = FixedStepper::default();
+        let config: PhysicsConfig = toml::from_str(
+            r#"
+            gravity = [0.0, -9.81, 0.0]
+            event_queue_capacity = 5
+            fixed_timestep = 0.0
+            "#,
+        )
+        .unwrap();
+        stepper.set_config(&config);
+        assert_eq!(stepper.timestep(), default_timestep);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::perigee_gltf::util::access_gltf_bytes;
+use crate::physics::PhysicsWorldInitError;
+use gltf::{accessor::DataType as GltfDataType, Mesh, Semantic as PrimitiveSemantic};
+use rapier3d::{
+    na::{Point3, Vector3},
+    prelude::*,
+};
+
+/// The triangles of a glTF mesh, kept around so collider shapes
+/// can be built from them at any scale.
+#[derive(Debug, Clone)]
+pub(crate) struct GltfMeshGeometry {
+    vertices: Vec<Point3<f32>>,
+    indices: Vec<[u32; 3]>,
+}
+
+impl GltfMeshGeometry {
+    pub(crate) fn from_gltf_mesh(
+        mesh: &Mesh,
+        gltf_bytes: &[u8],
+    ) -> Result<Self, PhysicsWorldInitError> {
+        let mut maybe_indices: Option<Vec<[u32; 3]>> = None;
+        let mut maybe_vertices: Option<Vec<Point3<f32>>> = None;
+        for primitive in mesh.primitives() {
+            let indices_accesor = match primitive.indices() {
+                Some(accessor) => accessor,
+                None => return Err(PhysicsWorldInitError::NoPrimitiveAccessorForTrimesh),
+            };
+
+            let indices_bytes =
+                if let Ok(indices_bytes) = access_gltf_bytes(gltf_bytes, &indices_accesor) {
+                    indices_bytes
+                } else {
+                    return Err(PhysicsWorldInitError::CouldntAccessBytes);
+                };
+            let mut indices: Vec<[u32; 3]> = Vec::with_capacity(indices_accesor.count() / 3);
+
+            match indices_accesor.data_type() {
+                GltfDataType::U16 => {
+                    let flattened_indices: Vec<u16> = indices_bytes
+                        .chunks_exact(2)
+                        .map(|uint_bytes| {
+                            let uint_byte_array: [u8; 2] = uint_bytes[0..2]
+                                .try_into()
+                                .expect("Could not convert u16 byte slice into u16 byte array");
+                            u16::from_le_bytes(uint_byte_array)
+                        })
+                        .collect();
+                    let chunked_indices: Vec<&[u16]> = flattened_indices.chunks(3).collect();
+                    for face_u16 in chunked_indices {
+                        indices.push([
+                            u32::from(face_u16[0]),
+                            u32::from(face_u16[1]),
+                            u32::from(face_u16[2]),
+                        ]);
+                    }
+                    maybe_indices = Some(indices);
+                }
+                GltfDataType::U32 => {
+                    let flattened_indices: Vec<u32> = indices_bytes
+                        .chunks_exact(4)
+                        .map(|uint_bytes| {
+                            let uint_byte_array: [u8; 4] = uint_bytes[0..4]
+                                .try_into()
+                                .expect("Could not convert u32 byte slice into u32 byte array");
+                            u32::from_le_bytes(uint_byte_array)
+                        })
+                        .collect();
+                    let chunked_indices: Vec<&[u32]> = flattened_indices.chunks(3).collect();
+                    for face_u32 in chunked_indices {
+                        indices.push([face_u32[0], face_u32[1], face_u32[2]]);
+                    }
+                    maybe_indices = Some(indices);
+                }
+                _ => {
+                    return Err(PhysicsWorldInitError::InvalidIndicesDataType);
+                }
+            };
+            match primitive.get(&PrimitiveSemantic::Positions) {
+                None => {
+                    return Err(PhysicsWorldInitError::NoVertexPositionsAccessor);
+                }
+                Some(vertex_positions_accessor) => {
+                    let positions_bytes = if let Ok(positions_bytes) =
+                        access_gltf_bytes(gltf_bytes, &vertex_positions_accessor)
+                    {
+                        positions_bytes
+                    } else {
+                        return Err(PhysicsWorldInitError::CouldntAccessBytes);
+                    };
+
+                    let mut floats: Vec<f32> = Vec::with_capacity(positions_bytes.len() / 4);
+                    for float_bytes in positions_bytes.chunks_exact(4) {
+                        let float_byte_array: [u8; 4] = float_bytes[0..4]
+                            .try_into()
+                            .expect("Could not convert float byte slice into float byte array");
+                        floats.push(f32::from_le_bytes(float_byte_array));
+                    }
+                    let mut vertices: Vec<Point3<f32>> = Vec::with_capacity(floats.len() / 3);
+                    for float_chunk in floats.chunks(3) {
+                        vertices.push(Point3::new(float_chunk[0], float_chunk[1], float_chunk[2]));
+                    }
+                    maybe_vertices = Some(vertices);
+                }
+            };
+        }
+
+        let indices = match maybe_indices {
+            Some(indices) => indices,
+            None => return Err(PhysicsWorldInitError::NoIndicesFound),
+        };
+        let vertices = match maybe_vertices {
+            Some(vertices) => vertices,
+            None => return Err(PhysicsWorldInitError::NoVerticesFound),
+        };
+        Ok(Self { vertices, indices })
+    }
+
+    pub(crate) fn scaled_trimesh(&self, scale: &Vector3<f32>) -> TriMesh {
+        TriMesh::new(self.vertices.clone(), self.indices.clone()).scaled(scale)
+    }
+}
@@ -0,0 +1,152 @@
+use crate::perigee_gltf::extras::{GltfJointSettings, GltfJointType};
+use crate::physics::{PhysicsWorld, PhysicsWorldInitError};
+use rapier3d::na::{Isometry3, UnitQuaternion, Vector3};
+use rapier3d::prelude::*;
+use std::f32::consts::PI;
+
+/// A joint node found while visiting a glTF scene. Joints are created
+/// once every scene is loaded since they can reference bodies anywhere.
+pub(crate) struct GltfJointNode {
+    pub name: String,
+    pub settings: GltfJointSettings,
+    pub global_isometry: Isometry3<f32>,
+}
+
+impl PhysicsWorld {
+    /// Create a joint between the two named rigid bodies of a glTF joint node.
+    /// The joint's frame is the node's global isometry, rotated so its
+    /// X axis points along the configured axis.
+    pub(crate) fn insert_gltf_joint(
+        &mut self,
+        joint_settings: &GltfJointSettings,
+        global_isometry: &Isometry3<f32>,
+    ) -> Result<ImpulseJointHandle, PhysicsWorldInitError> {
+        let body_handle = |body_name: &String| {
+            self.named_rigid_bodies
+                .handle_with_name(body_name)
+                .copied()
+                .ok_or_else(|| PhysicsWorldInitError::UnknownJointBody(body_name.clone()))
+        };
+        let first_body_handle = body_handle(&joint_settings.first_body)?;
+        let second_body_handle = body_handle(&joint_settings.second_body)?;
+
+        let axis = joint_settings
+            .axis
+            .try_normalize(f32::EPSILON)
+            .ok_or(PhysicsWorldInitError::InvalidJointAxis)?;
+        let axis_rotation = UnitQuaternion::rotation_between(&Vector3::x(), &axis)
+            .unwrap_or_else(|| UnitQuaternion::from_axis_angle(&Vector3::y_axis(), PI));
+        let joint_frame =
+            global_isometry * Isometry3::from_parts(Default::default(), axis_rotation);
+        let local_frame = |body_handle: RigidBodyHandle| {
+            self.rigid_body_set[body_handle].position().inverse() * joint_frame
+        };
+
+        let (locked_axes, free_axes): (JointAxesMask, &[JointAxis]) = match joint_settings
+            .joint_type
+        {
+            GltfJointType::Fixed => (JointAxesMask::LOCKED_FIXED_AXES, &[]),
+            GltfJointType::Revolute => (JointAxesMask::LOCKED_REVOLUTE_AXES, &[JointAxis::AngX]),
+            GltfJointType::Prismatic => (JointAxesMask::LOCKED_PRISMATIC_AXES, &[JointAxis::X]),
+            GltfJointType::Spherical => (
+                JointAxesMask::LOCKED_SPHERICAL_AXES,
+                &[JointAxis::AngX, JointAxis::AngY, JointAxis::AngZ],
+            ),
+        };
+        let mut joint_builder = GenericJointBuilder::new(locked_axes)
+            .local_frame1(local_frame(first_body_handle))
+            .local_frame2(local_frame(second_body_handle));
+        for free_axis in free_axes {
+            if let Some(limits) = joint_settings.limits {
+                joint_builder = joint_builder.limits(*free_axis, [limits.min, limits.max]);
+            }
+            if let Some(motor) = joint_settings.motor {
+                joint_builder = joint_builder.set_motor(
+                    *free_axis,
+                    motor.target_position,
+                    motor.target_velocity,
+                    motor.stiffness,
+                    motor.damping,
+                );
+                if let Some(max_force) = motor.max_force {
+                    joint_builder = joint_builder.motor_max_force(*free_axis, max_force);
+                }
+            }
+        }
+
+        Ok(self.impulse_joint_set.insert(
+            first_body_handle,
+            second_body_handle,
+            joint_builder.build(),
+            true,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perigee_gltf::extras::{GltfExtras, GltfJointLimits};
+
+    #[test]
+    fn revolute_joints_hinge_named_bodies() {
+        let mut world = PhysicsWorld::default();
+        let frame_handle = world
+            .rigid_body_set
+            .insert(RigidBodyBuilder::fixed().build());
+        world.named_rigid_bodies.insert("DoorFrame", frame_handle);
+        let door_handle = world.rigid_body_set.insert(
+            RigidBodyBuilder::dynamic()
+                .translation(vector![0.5, 1.0, 0.0])
+                .build(),
+        );
+        world.collider_set.insert_with_parent(
+            ColliderBuilder::cuboid(0.5, 1.0, 0.05).build(),
+            door_handle,
+            &mut world.rigid_body_set,
+        );
+        world.named_rigid_bodies.insert("Door", door_handle);
+
+        let extras: GltfExtras = serde_json::from_str(
+            r#"{"simSettings": {
+                "physics": {"enabled": false, "isAnonymous": false, "bodyType": "SENSOR", "mass": 1, "optimizedShape": "NONE"},
+                "isPointOfInterest": false,
+                "joint": {
+                    "enabled": true,
+                    "jointType": "REVOLUTE",
+                    "firstBody": "DoorFrame",
+                    "secondBody": "Door",
+                    "axis": [0, 1, 0],
+                    "limits": {"min": -1.0, "max": 1.0}
+                }
+            }}"#,
+        )
+        .unwrap();
+        let mut joint_settings = extras.sim_settings.joint.unwrap();
+        assert!(matches!(
+            joint_settings.limits,
+            Some(GltfJointLimits { min, max }) if min == -1.0 && max == 1.0
+        ));
+        let hinge_position = Isometry3::translation(0.0, 1.0, 0.0);
+        world
+            .insert_gltf_joint(&joint_settings, &hinge_position)
+            .unwrap();
+
+        // Swing the door about the hinge
+        world.rigid_body_set[door_handle].apply_impulse(vector![0.0, 0.0, 2.0], true);
+        for _ in 0..60 {
+            world.step(1.0 / 60.0);
+        }
+        let door = &world.rigid_body_set[door_handle];
+        let hinge_to_door = door.translation() - hinge_position.translation.vector;
+        assert!((hinge_to_door.norm() - 0.5).abs() < 0.02);
+        assert!(hinge_to_door.y.abs() < 0.02);
+        assert!(door.translation().z > 0.05);
+
+        joint_settings.second_body = String::from("Window");
+        assert!(matches!(
+            world.insert_gltf_joint(&joint_settings, &hinge_position),
+            Err(PhysicsWorldInitError::UnknownJointBody(name)) if name == "Window"
+        ));
+    }
+}
@@ -0,0 +1,281 @@
+use crate::math::Transform3;
+use crate::perigee_gltf::extras::{GltfExtras, GltfOptimizedShape, GltfPhysicsSettings};
+use crate::perigee_gltf::util::node_local_transform;
+use crate::physics::{CollisionLayers, GltfMeshGeometry, PhysicsWorldInitError};
+use gltf::Node;
+use rapier3d::na::Vector3;
+use rapier3d::prelude::*;
+
+/// A physics-enabled mesh node whose shape becomes part
+/// of an ancestor's compound collider instead of its own body.
+#[derive(Debug, Clone)]
+pub(crate) struct GltfCompoundPart {
+    pub(crate) physics_settings: GltfPhysicsSettings,
+    pub(crate) geometry: Option<GltfMeshGeometry>,
+    /// The part's transform relative to the node with the compound shape.
+    pub(crate) transform: Transform3<f32>,
+}
+
+impl GltfCompoundPart {
+    /// Collect the physics-enabled mesh nodes below `node` as compound parts.
+    /// The glTF indices of the part nodes are added to `part_node_indices` so
+    /// they aren't loaded as bodies of their own.
+    pub(crate) fn collect_from_gltf_node(
+        node: &Node,
+        gltf_bytes: &[u8],
+        relative_transform: &Transform3<f32>,
+        parts: &mut Vec<GltfCompoundPart>,
+        part_node_indices: &mut Vec<usize>,
+    ) -> Result<(), PhysicsWorldInitError> {
+        for child_node in node.children() {
+            let child_transform = relative_transform * node_local_transform(&child_node);
+            let node_extra_data = match child_node.extras().as_ref() {
+                Some(extra_data) => extra_data,
+                None => return Err(PhysicsWorldInitError::PerigeeExtrasUndetected),
+            };
+            let node_extras: GltfExtras = match serde_json::from_str(node_extra_data.get()) {
+                Ok(extras) => extras,
+                Err(_) => return Err(PhysicsWorldInitError::InvalidPerigeeExtrasData),
+            };
+            let physics_settings = node_extras.sim_settings.physics;
+
+            if let Some(mesh) = child_node.mesh() {
+                if physics_settings.enabled {
+                    let geometry = if physics_settings.optimized_shape.needs_mesh_geometry() {
+                        Some(GltfMeshGeometry::from_gltf_mesh(&mesh, gltf_bytes)?)
+                    } else {
+                        None
+                    };
+                    parts.push(GltfCompoundPart {
+                        physics_settings,
+                        geometry,
+                        transform: child_transform,
+                    });
+                    part_node_indices.push(child_node.index());
+                }
+            }
+            Self::collect_from_gltf_node(
+                &child_node,
+                gltf_bytes,
+                &child_transform,
+                parts,
+                part_node_indices,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Build the collider shape of a glTF node placed at `global_transform`.
+///
+/// Capsules, cylinders and cones are aligned with the node's Y axis. Their radius
+/// comes from the larger of the X and Z dimensions and their height from the Y dimension.
+pub(crate) fn gltf_collider_shape(
+    physics_settings: &GltfPhysicsSettings,
+    geometry: Option<&GltfMeshGeometry>,
+    compound_parts: &[GltfCompoundPart],
+    global_transform: &Transform3<f32>,
+) -> Result<SharedShape, PhysicsWorldInitError> {
+    let global_scale = global_transform.scale();
+    let dimensions: Vector3<f32> = physics_settings.base_scale.component_mul(global_scale);
+    let radius = dimensions.x.max(dimensions.z) / 2.0;
+    let half_height = dimensions.y / 2.0;
+
+    let shape = match physics_settings.optimized_shape {
+        GltfOptimizedShape::Cuboid => {
+            SharedShape::cuboid(dimensions.x / 2.0, dimensions.y / 2.0, dimensions.z / 2.0)
+        }
+        GltfOptimizedShape::Sphere => SharedShape::ball(dimensions.x / 2.0),
+        // The capsule's height includes its hemispherical caps
+        GltfOptimizedShape::Capsule => {
+            SharedShape::capsule_y((half_height - radius).max(0.0), radius)
+        }
+        GltfOptimizedShape::Cylinder => SharedShape::cylinder(half_height, radius),
+        GltfOptimizedShape::Cone => SharedShape::cone(half_height, radius),
+        GltfOptimizedShape::ConvexMesh => {
+            let geometry = geometry.ok_or(PhysicsWorldInitError::NoVerticesFound)?;
+            let scaled_trimesh = geometry.scaled_trimesh(global_scale);
+            match SharedShape::convex_hull(scaled_trimesh.vertices()) {
+                Some(shape) => shape,
+                None => return Err(PhysicsWorldInitError::MeshNotConvex),
+            }
+        }
+        GltfOptimizedShape::ConvexDecomposition => {
+            let geometry = geometry.ok_or(PhysicsWorldInitError::NoVerticesFound)?;
+            let scaled_trimesh = geometry.scaled_trimesh(global_scale);
+            SharedShape::convex_decomposition(scaled_trimesh.vertices(), scaled_trimesh.indices())
+        }
+        GltfOptimizedShape::None => {
+            let geometry = geometry.ok_or(PhysicsWorldInitError::NoVerticesFound)?;
+            let scaled_trimesh = geometry.scaled_trimesh(global_scale);
+            SharedShape::trimesh(
+                scaled_trimesh.vertices().to_vec(),
+                scaled_trimesh.indices().to_vec(),
+            )
+        }
+        GltfOptimizedShape::Compound => {
+            if compound_parts.is_empty() {
+                return Err(PhysicsWorldInitError::EmptyCompoundShape);
+            }
+            let inverse_isometry = global_transform.isometry().inverse();
+            let mut shapes = Vec::with_capacity(compound_parts.len());
+            for part in compound_parts {
+                let part_global_transform = global_transform * part.transform;
+                let part_shape = gltf_collider_shape(
+                    &part.physics_settings,
+                    part.geometry.as_ref(),
+                    &[],
+                    &part_global_transform,
+                )?;
+                shapes.push((
+                    inverse_isometry * part_global_transform.isometry(),
+                    part_shape,
+                ));
+            }
+            SharedShape::compound(shapes)
+        }
+    };
+    Ok(shape)
+}
+
+/// Start building a collider with the material and groups of a glTF node's physics settings.
+/// A node's collision layer takes precedence over its collision groups.
+pub(crate) fn gltf_collider_builder(
+    physics_settings: &GltfPhysicsSettings,
+    shape: SharedShape,
+    collision_layers: &CollisionLayers,
+) -> Result<ColliderBuilder, PhysicsWorldInitError> {
+    let collision_groups = match &physics_settings.collision_layer {
+        Some(layer_name) => collision_layers.interaction_groups(layer_name)?,
+        None => physics_settings.collision_groups.into(),
+    };
+    Ok(ColliderBuilder::new(shape)
+        .friction(physics_settings.friction)
+        .friction_combine_rule(physics_settings.friction_combine_rule.into())
+        .restitution(physics_settings.restitution)
+        .restitution_combine_rule(physics_settings.restitution_combine_rule.into())
+        .collision_groups(collision_groups)
+        .solver_groups(physics_settings.solver_groups.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rapier3d::na::Isometry3;
+
+    fn physics_settings(optimized_shape: GltfOptimizedShape) -> GltfPhysicsSettings {
+        serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "isAnonymous": false,
+            "bodyType": "DYNAMIC",
+            "mass": 1.0,
+            "optimizedShape": optimized_shape,
+            "baseScale": [1.0, 2.0, 0.5],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn primitive_and_compound_shapes_follow_node_dimensions() {
+        let transform = Transform3::from_parts(Isometry3::identity(), Vector3::new(2.0, 1.0, 2.0));
+
+        let capsule = gltf_collider_shape(
+            &physics_settings(GltfOptimizedShape::Capsule),
+            None,
+            &[],
+            &transform,
+        )
+        .unwrap();
+        let capsule = capsule.as_capsule().unwrap();
+        assert_eq!(capsule.radius, 1.0);
+        assert_eq!(capsule.half_height(), 0.0);
+
+        let cylinder = gltf_collider_shape(
+            &physics_settings(GltfOptimizedShape::Cylinder),
+            None,
+            &[],
+            &transform,
+        )
+        .unwrap();
+        let cylinder = cylinder.as_cylinder().unwrap();
+        assert_eq!((cylinder.half_height, cylinder.radius), (1.0, 1.0));
+
+        assert!(matches!(
+            gltf_collider_shape(
+                &physics_settings(GltfOptimizedShape::Compound),
+                None,
+                &[],
+                &transform,
+            ),
+            Err(PhysicsWorldInitError::EmptyCompoundShape)
+        ));
+        let parts: Vec<GltfCompoundPart> = [-1.0, 1.0]
+            .iter()
+            .map(|x| GltfCompoundPart {
+                physics_settings: physics_settings(GltfOptimizedShape::Cuboid),
+                geometry: None,
+                transform: Transform3::from_parts(
+                    Isometry3::translation(*x, 0.0, 0.0),
+                    Vector3::new(1.0, 1.0, 1.0),
+                ),
+            })
+            .collect();
+        let compound = gltf_collider_shape(
+            &physics_settings(GltfOptimizedShape::Compound),
+            None,
+            &parts,
+            &transform,
+        )
+        .unwrap();
+        let compound = compound.as_compound().unwrap();
+        assert_eq!(compound.shapes().len(), 2);
+        // The parts are placed in the scaled space of the compound's node
+        assert_eq!(compound.shapes()[1].0.translation.vector.x, 2.0);
+        assert_eq!(
+            compound.shapes()[1].1.as_cuboid().unwrap().half_extents.x,
+            1.0
+        );
+    }
+
+    #[test]
+    fn material_settings_apply_to_colliders() {
+        let physics_settings: GltfPhysicsSettings = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "isAnonymous": false,
+            "bodyType": "STATIC",
+            "mass": 1.0,
+            "optimizedShape": "CUBOID",
+            "friction": 0.02,
+            "frictionCombineRule": "MIN",
+            "restitution": 0.9,
+            "restitutionCombineRule": "MAX",
+            "collisionGroups": {"memberships": 2, "filter": 5},
+        }))
+        .unwrap();
+        assert!(physics_settings.density.is_none());
+        assert!(!physics_settings.ccd_enabled);
+
+        let collider = gltf_collider_builder(
+            &physics_settings,
+            SharedShape::ball(1.0),
+            &CollisionLayers::default(),
+        )
+        .unwrap()
+        .build();
+        assert_eq!(collider.friction(), 0.02);
+        assert_eq!(
+            collider.friction_combine_rule(),
+            CoefficientCombineRule::Min
+        );
+        assert_eq!(collider.restitution(), 0.9);
+        assert_eq!(
+            collider.restitution_combine_rule(),
+            CoefficientCombineRule::Max
+        );
+        assert_eq!(
+            collider.collision_groups(),
+            InteractionGroups::new(Group::GROUP_2, Group::GROUP_1 | Group::GROUP_3)
+        );
+        assert_eq!(collider.solver_groups(), InteractionGroups::all());
+    }
+}
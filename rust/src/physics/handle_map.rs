@@ -4,9 +4,9 @@ use serde::{Deserialize, Serialize};
 use std::{hash::Hash, ops::Index};
 
 /// A [BiMap](crate::data_structures::BiMap)
-/// for naming [Rapier](https://rapier.rs) RigidBodyHandles
-/// and ColliderHandles.
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+/// for naming [Rapier](https://rapier.rs) RigidBodyHandles,
+/// ColliderHandles and ImpulseJointHandles.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NamedHandleMap<T>
 where
     T: Eq + Hash + Copy,
@@ -14,6 +14,17 @@ where
     inner: BiMap<String, T>,
 }
 
+impl<T> Default for NamedHandleMap<T>
+where
+    T: Eq + Hash + Copy,
+{
+    fn default() -> Self {
+        Self {
+            inner: BiMap::default(),
+        }
+    }
+}
+
 impl<T> NamedHandleMap<T>
 where
     T: Eq + Hash + Copy,
@@ -50,7 +61,7 @@ where
         };
         self.remove_by_name(old_name);
         self.insert(new_name, handle);
-        return true;
+        true
     }
 
     pub fn swap_named_handle(&mut self, old_handle: &T, new_handle: T) -> bool {
@@ -60,7 +71,7 @@ where
         };
         self.remove_by_handle(old_handle);
         self.insert(name, new_handle);
-        return true;
+        true
     }
 }
 
@@ -79,11 +90,21 @@ pub type NamedColliderHandleMap = NamedHandleMap<ColliderHandle>;
 impl Index<&ColliderHandle> for NamedColliderHandleMap {
     type Output = String;
     fn index(&self, index: &ColliderHandle) -> &Self::Output {
-        self.name_of_handle(&index)
+        self.name_of_handle(index)
             .expect("Unrecognized collider handle given!")
     }
 }
 
+pub type NamedImpulseJointHandleMap = NamedHandleMap<ImpulseJointHandle>;
+
+impl Index<&ImpulseJointHandle> for NamedImpulseJointHandleMap {
+    type Output = String;
+    fn index(&self, index: &ImpulseJointHandle) -> &Self::Output {
+        self.name_of_handle(index)
+            .expect("Unrecognized joint handle given!")
+    }
+}
+
 impl<T> Index<&str> for NamedHandleMap<T>
 where
     T: Eq + Hash + Copy,
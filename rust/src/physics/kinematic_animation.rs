@@ -0,0 +1,165 @@
+use crate::animation::AnimationManager;
+use crate::math::Transform3;
+use crate::perigee_gltf::extras::{GltfBodyType, GltfExtras};
+use crate::perigee_gltf::scenes::{GltfSceneSelectionError, GltfSceneSelector};
+use crate::perigee_gltf::util::node_local_transform;
+use gltf::{Gltf, Node, Scene};
+use std::collections::HashMap;
+
+/// Where a glTF node sits in its scene's hierarchy.
+#[derive(Debug, Clone)]
+struct NodeRest {
+    parent: Option<String>,
+    rest_transform: Transform3<f32>,
+}
+
+/// Maps animation targets to named kinematic rigid bodies so that
+/// animations authored on those nodes move the bodies in the physics world.
+///
+/// Animations store transforms relative to each node's parent, so the
+/// scene hierarchy is recorded as well. A body whose parent node is animated
+/// follows its parent's animation too.
+#[derive(Debug, Clone)]
+pub struct KinematicAnimationBindings {
+    root_transform: Transform3<f32>,
+    nodes: HashMap<String, NodeRest>,
+    target_bodies: HashMap<String, String>,
+}
+
+impl Default for KinematicAnimationBindings {
+    fn default() -> Self {
+        Self {
+            root_transform: Transform3::identity(),
+            nodes: HashMap::new(),
+            target_bodies: HashMap::new(),
+        }
+    }
+}
+
+impl KinematicAnimationBindings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn visit_gltf_node(&mut self, node: &Node, parent_name: Option<&str>) {
+        let node_name = node.name();
+        if let Some(node_name) = node_name {
+            self.nodes.insert(
+                String::from(node_name),
+                NodeRest {
+                    parent: parent_name.map(String::from),
+                    rest_transform: node_local_transform(node),
+                },
+            );
+
+            let node_extras: Option<GltfExtras> = node
+                .extras()
+                .as_ref()
+                .and_then(|extra_data| serde_json::from_str(extra_data.get()).ok());
+            if let Some(node_extras) = node_extras {
+                let physics_settings = node_extras.sim_settings.physics;
+                if physics_settings.enabled
+                    && !physics_settings.is_anonymous
+                    && matches!(physics_settings.body_type, GltfBodyType::Kinematic)
+                    && node.mesh().is_some()
+                {
+                    self.bind(node_name, node_name);
+                }
+            }
+        }
+
+        for child_node in node.children() {
+            self.visit_gltf_node(&child_node, node_name);
+        }
+    }
+
+    /// Record the node hierarchy of a Perigee-enabled glTF and bind every
+    /// named kinematic body to the animation target with the same name.
+    /// `parent_transform` should match the one given to
+    /// [load_from_gltf](crate::physics::PhysicsWorld::load_from_gltf).
+    ///
+    /// Note: Only the first scene is loaded. Use
+    /// [from_gltf_scenes](Self::from_gltf_scenes) to choose scenes.
+    pub fn from_gltf(gltf: &Gltf, parent_transform: Option<Transform3<f32>>) -> Self {
+        Self::from_scenes(gltf.scenes().take(1), parent_transform)
+    }
+
+    /// Record the node hierarchy of the selected scenes of a Perigee-enabled
+    /// glTF and bind every named kinematic body in them to the animation target
+    /// with the same name. The selection should match the one given to
+    /// [load_scenes_from_gltf](crate::physics::PhysicsWorld::load_scenes_from_gltf).
+    pub fn from_gltf_scenes(
+        gltf: &Gltf,
+        scenes: &GltfSceneSelector,
+        parent_transform: Option<Transform3<f32>>,
+    ) -> Result<Self, GltfSceneSelectionError> {
+        Ok(Self::from_scenes(scenes.select(gltf)?, parent_transform))
+    }
+
+    fn from_scenes<'a>(
+        scenes: impl IntoIterator<Item = Scene<'a>>,
+        parent_transform: Option<Transform3<f32>>,
+    ) -> Self {
+        let mut bindings = Self {
+            root_transform: parent_transform.unwrap_or(Transform3::identity()),
+            ..Self::default()
+        };
+        for scene in scenes {
+            for node in scene.nodes() {
+                bindings.visit_gltf_node(&node, None);
+            }
+        }
+        bindings
+    }
+
+    /// Move the named rigid body with the named animation target.
+    pub fn bind(&mut self, target_name: impl Into<String>, body_name: impl Into<String>) {
+        self.target_bodies
+            .insert(target_name.into(), body_name.into());
+    }
+
+    /// Stop moving a body with the named animation target. Returns false if
+    /// the target wasn't bound.
+    pub fn unbind(&mut self, target_name: &str) -> bool {
+        self.target_bodies.remove(target_name).is_some()
+    }
+
+    /// The animation target names and the names of the rigid bodies they move.
+    pub fn bindings(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.target_bodies.iter()
+    }
+
+    /// The world-space transform of the named target after applying every
+    /// active animation in `animations` to it and its ancestors.
+    pub fn global_transform(
+        &self,
+        target_name: &str,
+        animations: &AnimationManager,
+    ) -> Transform3<f32> {
+        let mut ancestry: Vec<&str> = vec![target_name];
+        let mut current_node = self.nodes.get(target_name);
+        while let Some(node) = current_node {
+            match &node.parent {
+                Some(parent_name) => {
+                    ancestry.push(parent_name);
+                    current_node = self.nodes.get(parent_name);
+                }
+                None => break,
+            }
+        }
+
+        let mut global_transform = self.root_transform;
+        for node_name in ancestry.into_iter().rev() {
+            let rest_transform = self
+                .nodes
+                .get(node_name)
+                .map(|node| node.rest_transform)
+                .unwrap_or(Transform3::identity());
+            let local_transform = animations
+                .sampled_transform_from_rest(node_name, &rest_transform)
+                .unwrap_or(rest_transform);
+            global_transform = global_transform * local_transform;
+        }
+        global_transform
+    }
+}
@@ -1,29 +1,56 @@
 use std::collections::HashMap;
 
+use crate::animation::AnimationManager;
 use crate::config::PhysicsConfig;
 use crate::math::Transform3;
-use crate::perigee_gltf::extras::{GltfBodyType, GltfExtras, GltfOptimizedShape};
-use crate::perigee_gltf::util::access_gltf_bytes;
+use crate::perigee_gltf::extras::{
+    GltfBodyType, GltfExtras, GltfOptimizedShape, GltfPhysicsSettings,
+};
+use crate::perigee_gltf::prefabs::{Prefab, PrefabInstance, PrefabItem};
+use crate::perigee_gltf::scenes::{GltfSceneSelectionError, GltfSceneSelector, LoadedGltfScene};
+use crate::perigee_gltf::util::node_local_transform;
 use crate::physics::contact_event_mgmt::ContactEventManager;
-use crate::physics::handle_map::{NamedColliderHandleMap, NamedRigidBodyHandleMap};
+use crate::physics::gltf_joints::GltfJointNode;
+use crate::physics::handle_map::{
+    NamedColliderHandleMap, NamedImpulseJointHandleMap, NamedRigidBodyHandleMap,
+};
 use crate::traits::{physics::ColliderEventListener, FromConfig};
+pub use character_controller::*;
 pub use collider_event_listener::*;
-use gltf::Node;
-use gltf::{accessor::DataType as GltfDataType, Gltf, Semantic as PrimitiveSemantic};
+pub use collision_layers::*;
+pub use fixed_step::*;
+use gltf::{Gltf, Node};
+pub(crate) use gltf_geometry::GltfMeshGeometry;
+pub(crate) use gltf_shapes::GltfCompoundPart;
+use gltf_shapes::{gltf_collider_builder, gltf_collider_shape};
+pub use kinematic_animation::*;
 use log::warn;
-use rapier3d::{
-    na::{Point3, Quaternion, Translation3, UnitQuaternion, Vector3},
-    prelude::*,
-};
+pub use queries::*;
+use rapier3d::{na::Vector3, prelude::*};
 use serde::{Deserialize, Serialize};
+pub use snapshot::*;
+pub(crate) use state_hash::StateHasher;
 use thiserror::Error;
 
+mod character_controller;
 mod collider_event_listener;
+mod collision_layers;
 mod contact_event_mgmt;
+mod fixed_step;
+mod gltf_geometry;
+mod gltf_joints;
+mod gltf_shapes;
 mod handle_map;
+mod kinematic_animation;
+mod queries;
+mod snapshot;
+mod state_hash;
 
 #[derive(Error, Debug)]
 pub enum PhysicsWorldInitError {
+    /// The selected glTF scenes couldn't be found.
+    #[error("could not select glTF scenes: {0}")]
+    SceneSelection(#[from] GltfSceneSelectionError),
     /// The binary payload for the glTF couldn't be found.
     #[error("can't access the provided glTF's binary payload")]
     CantAccessBlob,
@@ -61,6 +88,28 @@ pub enum PhysicsWorldInitError {
     CouldntAccessBytes,
     #[error("mesh defined as convex is not convex")]
     MeshNotConvex,
+    /// A node with a compound shape had no physics-enabled meshes below it.
+    #[error("compound shape has no child meshes to build it from")]
+    EmptyCompoundShape,
+    /// A joint referenced a rigid body that isn't named in the physics world.
+    #[error("joint references unknown rigid body {0}")]
+    UnknownJointBody(String),
+    /// A joint's axis had a length of zero.
+    #[error("joint axis must not be zero")]
+    InvalidJointAxis,
+    /// A node's collision layer isn't one of the world's layers.
+    #[error("invalid collision layer: {0}")]
+    CollisionLayer(#[from] CollisionLayerError),
+}
+
+#[derive(Error, Debug)]
+pub enum PrefabInstantiationError {
+    /// An object in the physics world already has the prefixed name of a prefab object.
+    #[error("an object named {0} already exists in the physics world")]
+    NameTaken(String),
+    /// The physics objects of the prefab couldn't be created.
+    #[error("could not create prefab physics object: {0}")]
+    Physics(#[from] PhysicsWorldInitError),
 }
 
 /// The physics management structure. This is a
@@ -81,6 +130,12 @@ pub struct PhysicsWorld {
     pub query_pipeline: QueryPipeline,
     pub named_rigid_bodies: NamedRigidBodyHandleMap,
     pub named_sensors: NamedColliderHandleMap,
+    pub named_joints: NamedImpulseJointHandleMap,
+    collision_layers: CollisionLayers,
+    event_queue_capacity: usize,
+    /// The registry names of listeners attached with
+    /// [listen_to_collider_from_registry](Self::listen_to_collider_from_registry).
+    registered_listener_names: HashMap<ColliderHandle, Vec<String>>,
     #[serde(skip)]
     collider_event_handlers: HashMap<ColliderHandle, Vec<Box<dyn ColliderEventListener>>>,
     #[serde(skip)]
@@ -110,6 +165,11 @@ impl FromConfig for PhysicsWorld {
             ),
             named_rigid_bodies: NamedRigidBodyHandleMap::default(),
             named_sensors: NamedColliderHandleMap::default(),
+            named_joints: NamedImpulseJointHandleMap::default(),
+            collision_layers: CollisionLayers::from_config(config)
+                .expect("PhysicsConfig collision layers are validated when deserialized"),
+            event_queue_capacity: config.event_queue_capacity(),
+            registered_listener_names: HashMap::default(),
             collider_event_handlers: HashMap::default(),
         }
     }
@@ -138,12 +198,19 @@ impl PhysicsWorld {
         )
     }
 
+    /// Load a glTF node and its descendants. Sensors below a node with a
+    /// rigid body are attached to the nearest such body, `parent_body` being
+    /// the nearest body above `node`.
+    #[allow(clippy::too_many_arguments)]
     fn visit_gltf_node(
         &mut self,
         node: &Node,
         gltf_blob: Option<&Vec<u8>>,
         parent_transform: &Transform3<f32>,
+        parent_body: Option<RigidBodyHandle>,
         visited_nodes: &mut HashMap<usize, ()>,
+        loaded_node_names: &mut Vec<String>,
+        joint_nodes: &mut Vec<GltfJointNode>,
     ) -> Result<(), PhysicsWorldInitError> {
         let gltf_bytes = match gltf_blob {
             Some(bytes) => bytes,
@@ -160,388 +227,330 @@ impl PhysicsWorld {
             Ok(extras) => extras,
             Err(_) => return Err(PhysicsWorldInitError::InvalidPerigeeExtrasData),
         };
-
-        let body_type = node_extras.sim_settings.physics.body_type;
-
-        let (translation, quaternion, scale) = node.transform().decomposed();
-        let scale = Vector3::new(scale[0], scale[1], scale[2]);
-        let object_isometry = Isometry::from_parts(
-            Translation3::new(translation[0], translation[1], translation[2]),
-            UnitQuaternion::from_quaternion(Quaternion::new(
-                quaternion[3],
-                quaternion[0],
-                quaternion[1],
-                quaternion[2],
-            )),
-        );
-        let global_transform = parent_transform
-            * Transform3 {
-                isometry: object_isometry,
-                scale,
-            };
-        let global_isometry = *global_transform.isometry();
-        let global_scale = global_transform.scale();
-
-        for child_node in node.children() {
-            self.visit_gltf_node(&child_node, gltf_blob, &global_transform, visited_nodes)?;
-        }
-        if !node_extras.sim_settings.physics.enabled || visited_nodes.contains_key(&node.index()) {
-            return Ok(());
+        let physics_settings = node_extras.sim_settings.physics;
+
+        let global_transform = parent_transform * node_local_transform(node);
+
+        // Claim the meshes making up a compound shape before they're visited as bodies
+        let mut compound_parts = vec![];
+        if physics_settings.enabled
+            && node.mesh().is_some()
+            && !visited_nodes.contains_key(&node.index())
+            && matches!(
+                physics_settings.optimized_shape,
+                GltfOptimizedShape::Compound
+            )
+        {
+            let mut part_node_indices = vec![];
+            GltfCompoundPart::collect_from_gltf_node(
+                node,
+                gltf_bytes,
+                &Transform3::identity(),
+                &mut compound_parts,
+                &mut part_node_indices,
+            )?;
+            for part_node_index in part_node_indices {
+                visited_nodes.insert(part_node_index, ());
+            }
         }
 
-        // Create a rigid body
-        if let Some(mesh) = node.mesh() {
-            let mesh_name = match node.name() {
-                Some(name) => name,
-                None => return Err(PhysicsWorldInitError::UnnamedMesh),
-            };
-            let mesh_name = String::from(mesh_name);
-            let mut rigid_body_builder = match body_type {
-                GltfBodyType::Static => RigidBodyBuilder::fixed().sleeping(true),
-                GltfBodyType::Kinematic => {
-                    RigidBodyBuilder::kinematic_position_based().sleeping(true)
-                }
-                GltfBodyType::Dynamic => RigidBodyBuilder::dynamic(),
-                GltfBodyType::Sensor => return Err(PhysicsWorldInitError::MeshCantBeSensor),
-            };
-            rigid_body_builder = rigid_body_builder.position(global_isometry);
-
-            let base_scale = node_extras.sim_settings.physics.base_scale;
-            let collider_silhouette = match node_extras.sim_settings.physics.optimized_shape {
-                GltfOptimizedShape::Cuboid => {
-                    let cuboid_half_dimensions = base_scale.component_mul(&global_scale) / 2.0;
-                    SharedShape::cuboid(
-                        cuboid_half_dimensions.x,
-                        cuboid_half_dimensions.y,
-                        cuboid_half_dimensions.z,
-                    )
+        // Bodies are created before their descendants so sensors can be attached to them
+        let mut node_body = None;
+        let already_loaded = visited_nodes.contains_key(&node.index());
+        if !already_loaded {
+            if let Some(joint_settings) = node_extras.sim_settings.joint {
+                if joint_settings.enabled {
+                    let joint_name = match node.name() {
+                        Some(name) => String::from(name),
+                        None => return Err(PhysicsWorldInitError::UnnamedNode),
+                    };
+                    loaded_node_names.push(joint_name.clone());
+                    joint_nodes.push(GltfJointNode {
+                        name: joint_name,
+                        settings: joint_settings,
+                        global_isometry: *global_transform.isometry(),
+                    });
                 }
-                GltfOptimizedShape::Sphere => {
-                    let ball_dimensions = base_scale.component_mul(global_scale);
-                    SharedShape::ball(ball_dimensions.x / 2.0)
-                }
-                GltfOptimizedShape::ConvexMesh => {
-                    let mut maybe_indices: Option<Vec<[u32; 3]>> = None;
-                    let mut maybe_vertices: Option<Vec<Point3<f32>>> = None;
-                    for primitive in mesh.primitives() {
-                        let indices_accesor = match primitive.indices() {
-                            Some(accessor) => accessor,
-                            None => {
-                                return Err(PhysicsWorldInitError::NoPrimitiveAccessorForTrimesh)
-                            }
-                        };
-
-                        let indices_bytes = if let Ok(indices_bytes) =
-                            access_gltf_bytes(gltf_bytes, &indices_accesor)
-                        {
-                            indices_bytes
-                        } else {
-                            return Err(PhysicsWorldInitError::CouldntAccessBytes);
-                        };
-                        let mut indices: Vec<[u32; 3]> =
-                            Vec::with_capacity(indices_accesor.count() / 3);
-
-                        match indices_accesor.data_type() {
-                            GltfDataType::U16 => {
-                                let flattened_indices: Vec<u16> = indices_bytes
-                                    .chunks_exact(2)
-                                    .map(|uint_bytes| {
-                                        let uint_byte_array: [u8; 2] = uint_bytes[0..2]
-                                            .try_into()
-                                            .expect(
-                                            "Could not convert u16 byte slice into u16 byte array",
-                                        );
-                                        u16::from_le_bytes(uint_byte_array)
-                                    })
-                                    .collect();
-                                let chunked_indices: Vec<&[u16]> =
-                                    flattened_indices.chunks(3).collect();
-                                for face_u16 in chunked_indices {
-                                    indices.push([
-                                        u32::from(face_u16[0]),
-                                        u32::from(face_u16[1]),
-                                        u32::from(face_u16[2]),
-                                    ]);
-                                }
-                                maybe_indices = Some(indices);
-                            }
-                            GltfDataType::U32 => {
-                                let flattened_indices: Vec<u32> = indices_bytes
-                                    .chunks_exact(4)
-                                    .map(|uint_bytes| {
-                                        let uint_byte_array: [u8; 4] = uint_bytes[0..4]
-                                            .try_into()
-                                            .expect(
-                                            "Could not convert u32 byte slice into u32 byte array",
-                                        );
-                                        u32::from_le_bytes(uint_byte_array)
-                                    })
-                                    .collect();
-                                let chunked_indices: Vec<&[u32]> =
-                                    flattened_indices.chunks(3).collect();
-                                for face_u32 in chunked_indices {
-                                    indices.push([face_u32[0], face_u32[1], face_u32[2]]);
-                                }
-                                maybe_indices = Some(indices);
-                            }
-                            _ => {
-                                return Err(PhysicsWorldInitError::InvalidIndicesDataType);
-                            }
-                        };
-                        match primitive.get(&PrimitiveSemantic::Positions) {
-                            None => {
-                                return Err(PhysicsWorldInitError::NoVertexPositionsAccessor);
-                            }
-                            Some(vertex_positions_accessor) => {
-                                let positions_bytes = if let Ok(positions_bytes) =
-                                    access_gltf_bytes(gltf_bytes, &vertex_positions_accessor)
-                                {
-                                    positions_bytes
-                                } else {
-                                    return Err(PhysicsWorldInitError::CouldntAccessBytes);
-                                };
-
-                                let mut floats: Vec<f32> =
-                                    Vec::with_capacity(positions_bytes.len() / 4);
-                                for float_bytes in positions_bytes.chunks_exact(4) {
-                                    let float_byte_array: [u8; 4] = float_bytes[0..4]
-                                        .try_into()
-                                        .expect(
-                                        "Could not convert float byte slice into float byte array",
-                                    );
-                                    floats.push(f32::from_le_bytes(float_byte_array));
-                                }
-                                let mut vertices: Vec<Point3<f32>> =
-                                    Vec::with_capacity(floats.len() / 3);
-                                for float_chunk in floats.chunks(3) {
-                                    vertices.push(Point3::new(
-                                        float_chunk[0],
-                                        float_chunk[1],
-                                        float_chunk[2],
-                                    ));
-                                }
-                                maybe_vertices = Some(vertices);
-                            }
-                        };
-                    }
-                    if maybe_indices.is_none() {
-                        return Err(PhysicsWorldInitError::NoIndicesFound);
-                    }
-                    if maybe_vertices.is_none() {
-                        return Err(PhysicsWorldInitError::NoVerticesFound);
-                    }
-                    let scaled_trimesh: TriMesh = TriMesh::new(
-                        maybe_vertices
-                            .expect("Trimesh vertices were None despite asserting they weren't!"),
-                        maybe_indices
-                            .expect("Trimesh indices were None despite asserting they weren't!"),
-                    )
-                    .scaled(&global_scale);
-                    if let Some(shape) =
-                        SharedShape::convex_hull(scaled_trimesh.vertices().to_vec().as_ref())
-                    {
-                        shape
-                    } else {
-                        return Err(PhysicsWorldInitError::MeshNotConvex);
-                    }
-                }
-                GltfOptimizedShape::None => {
-                    let mut maybe_indices: Option<Vec<[u32; 3]>> = None;
-                    let mut maybe_vertices: Option<Vec<Point3<f32>>> = None;
-                    for primitive in mesh.primitives() {
-                        let indices_accesor = match primitive.indices() {
-                            Some(accessor) => accessor,
-                            None => {
-                                return Err(PhysicsWorldInitError::NoPrimitiveAccessorForTrimesh)
-                            }
-                        };
-
-                        let indices_bytes = if let Ok(indices_bytes) =
-                            access_gltf_bytes(gltf_bytes, &indices_accesor)
-                        {
-                            indices_bytes
-                        } else {
-                            return Err(PhysicsWorldInitError::CouldntAccessBytes);
-                        };
-                        let mut indices: Vec<[u32; 3]> =
-                            Vec::with_capacity(indices_accesor.count() / 3);
-
-                        match indices_accesor.data_type() {
-                            GltfDataType::U16 => {
-                                let flattened_indices: Vec<u16> = indices_bytes
-                                    .chunks_exact(2)
-                                    .map(|uint_bytes| {
-                                        let uint_byte_array: [u8; 2] = uint_bytes[0..2]
-                                            .try_into()
-                                            .expect(
-                                            "Could not convert u16 byte slice into u16 byte array",
-                                        );
-                                        u16::from_le_bytes(uint_byte_array)
-                                    })
-                                    .collect();
-                                let chunked_indices: Vec<&[u16]> =
-                                    flattened_indices.chunks(3).collect();
-                                for face_u16 in chunked_indices {
-                                    indices.push([
-                                        u32::from(face_u16[0]),
-                                        u32::from(face_u16[1]),
-                                        u32::from(face_u16[2]),
-                                    ]);
-                                }
-                                maybe_indices = Some(indices);
-                            }
-                            GltfDataType::U32 => {
-                                let flattened_indices: Vec<u32> = indices_bytes
-                                    .chunks_exact(4)
-                                    .map(|uint_bytes| {
-                                        let uint_byte_array: [u8; 4] = uint_bytes[0..4]
-                                            .try_into()
-                                            .expect(
-                                            "Could not convert u32 byte slice into u32 byte array",
-                                        );
-                                        u32::from_le_bytes(uint_byte_array)
-                                    })
-                                    .collect();
-                                let chunked_indices: Vec<&[u32]> =
-                                    flattened_indices.chunks(3).collect();
-                                for face_u32 in chunked_indices {
-                                    indices.push([face_u32[0], face_u32[1], face_u32[2]]);
-                                }
-                                maybe_indices = Some(indices);
-                            }
-                            _ => {
-                                return Err(PhysicsWorldInitError::InvalidIndicesDataType);
-                            }
-                        };
-                        match primitive.get(&PrimitiveSemantic::Positions) {
-                            None => {
-                                return Err(PhysicsWorldInitError::NoVertexPositionsAccessor);
-                            }
-                            Some(vertex_positions_accessor) => {
-                                let positions_bytes = if let Ok(positions_bytes) =
-                                    access_gltf_bytes(gltf_bytes, &vertex_positions_accessor)
-                                {
-                                    positions_bytes
-                                } else {
-                                    return Err(PhysicsWorldInitError::CouldntAccessBytes);
-                                };
-
-                                let mut floats: Vec<f32> =
-                                    Vec::with_capacity(positions_bytes.len() / 4);
-                                for float_bytes in positions_bytes.chunks_exact(4) {
-                                    let float_byte_array: [u8; 4] = float_bytes[0..4]
-                                        .try_into()
-                                        .expect(
-                                        "Could not convert float byte slice into float byte array",
-                                    );
-                                    floats.push(f32::from_le_bytes(float_byte_array));
-                                }
-                                let mut vertices: Vec<Point3<f32>> =
-                                    Vec::with_capacity(floats.len() / 3);
-                                for float_chunk in floats.chunks(3) {
-                                    vertices.push(Point3::new(
-                                        float_chunk[0],
-                                        float_chunk[1],
-                                        float_chunk[2],
-                                    ));
-                                }
-                                maybe_vertices = Some(vertices);
-                            }
-                        };
-                    }
-                    if maybe_indices.is_none() {
-                        return Err(PhysicsWorldInitError::NoIndicesFound);
-                    }
-                    if maybe_vertices.is_none() {
-                        return Err(PhysicsWorldInitError::NoVerticesFound);
-                    }
-                    let scaled_trimesh: TriMesh = TriMesh::new(
-                        maybe_vertices
-                            .expect("Trimesh vertices were None despite asserting they weren't!"),
-                        maybe_indices
-                            .expect("Trimesh indices were None despite asserting they weren't!"),
-                    )
-                    .scaled(&global_scale);
-                    SharedShape::trimesh(
-                        scaled_trimesh.vertices().to_vec(),
-                        scaled_trimesh.indices().to_vec(),
-                    )
-                }
-            };
-
-            let mut collider_builder = ColliderBuilder::new(collider_silhouette);
-            if matches!(body_type, GltfBodyType::Dynamic) {
-                collider_builder = collider_builder.mass(node_extras.sim_settings.physics.mass);
             }
 
-            let rb_handle = self.rigid_body_set.insert(rigid_body_builder.build());
-            let _col_handle = self.collider_set.insert_with_parent(
-                collider_builder.build(),
-                rb_handle,
-                &mut self.rigid_body_set,
-            );
-            if !node_extras.sim_settings.physics.is_anonymous {
-                self.named_rigid_bodies.insert(mesh_name.clone(), rb_handle);
-            }
-        } else {
-            // Create a sensor
-            let sensor_name = match node.name() {
-                Some(name) => name,
-                None => return Err(PhysicsWorldInitError::UnnamedNode),
-            };
-            let sensor_name = String::from(sensor_name);
-
-            let base_scale = node_extras.sim_settings.physics.base_scale;
-            let sensor_silhouette = match node_extras.sim_settings.physics.optimized_shape {
-                GltfOptimizedShape::Cuboid => {
-                    let cuboid_half_dimensions = base_scale.component_mul(global_scale) / 2.0;
-                    SharedShape::cuboid(
-                        cuboid_half_dimensions.x,
-                        cuboid_half_dimensions.y,
-                        cuboid_half_dimensions.z,
-                    )
-                }
-                GltfOptimizedShape::Sphere => {
-                    let ball_dimensions = base_scale.component_mul(global_scale);
-                    SharedShape::ball(ball_dimensions.x / 2.0)
+            if !physics_settings.enabled {
+                // Nothing to load
+            } else if let Some(mesh) = node.mesh() {
+                // Create a rigid body
+                let mesh_name = match node.name() {
+                    Some(name) => name,
+                    None => return Err(PhysicsWorldInitError::UnnamedMesh),
+                };
+                let mesh_name = String::from(mesh_name);
+                let geometry = if physics_settings.optimized_shape.needs_mesh_geometry() {
+                    Some(GltfMeshGeometry::from_gltf_mesh(&mesh, gltf_bytes)?)
+                } else {
+                    None
+                };
+
+                let rb_handle = self.insert_gltf_rigid_body(
+                    &physics_settings,
+                    geometry.as_ref(),
+                    &compound_parts,
+                    &global_transform,
+                )?;
+                if !physics_settings.is_anonymous {
+                    self.named_rigid_bodies.insert(mesh_name.clone(), rb_handle);
                 }
-                _ => return Err(PhysicsWorldInitError::CantAccessBlob),
-            };
-            let collider_builder = ColliderBuilder::new(sensor_silhouette)
-                .position(global_isometry)
-                .sensor(true);
+                loaded_node_names.push(mesh_name);
+                node_body = Some(rb_handle);
+            } else {
+                // Create a sensor
+                let sensor_name = match node.name() {
+                    Some(name) => name,
+                    None => return Err(PhysicsWorldInitError::UnnamedNode),
+                };
+                let sensor_name = String::from(sensor_name);
+
+                let sensor_handle =
+                    self.insert_gltf_sensor(&physics_settings, &global_transform, parent_body)?;
+                self.named_sensors
+                    .insert(sensor_name.clone(), sensor_handle);
+                loaded_node_names.push(sensor_name);
+            }
 
-            let sensor_handle = self.collider_set.insert(collider_builder.build());
-            self.named_sensors.insert(sensor_name, sensor_handle);
+            visited_nodes.insert(node.index(), ());
         }
 
-        visited_nodes.insert(node.index(), ());
+        for child_node in node.children() {
+            self.visit_gltf_node(
+                &child_node,
+                gltf_blob,
+                &global_transform,
+                node_body.or(parent_body),
+                visited_nodes,
+                loaded_node_names,
+                joint_nodes,
+            )?;
+        }
         Ok(())
     }
 
+    /// Create a rigid body and its collider from a glTF node's physics settings,
+    /// placing it at the node's global transform. Compound shapes are built
+    /// from `compound_parts`.
+    fn insert_gltf_rigid_body(
+        &mut self,
+        physics_settings: &GltfPhysicsSettings,
+        geometry: Option<&GltfMeshGeometry>,
+        compound_parts: &[GltfCompoundPart],
+        global_transform: &Transform3<f32>,
+    ) -> Result<RigidBodyHandle, PhysicsWorldInitError> {
+        let rigid_body_builder = match physics_settings.body_type {
+            GltfBodyType::Static => RigidBodyBuilder::fixed().sleeping(true),
+            GltfBodyType::Kinematic => RigidBodyBuilder::kinematic_position_based().sleeping(true),
+            GltfBodyType::Dynamic => RigidBodyBuilder::dynamic(),
+            GltfBodyType::Sensor => return Err(PhysicsWorldInitError::MeshCantBeSensor),
+        }
+        .position(*global_transform.isometry())
+        .ccd_enabled(physics_settings.ccd_enabled);
+
+        let collider_silhouette =
+            gltf_collider_shape(physics_settings, geometry, compound_parts, global_transform)?;
+
+        let mut collider_builder = gltf_collider_builder(
+            physics_settings,
+            collider_silhouette,
+            &self.collision_layers,
+        )?;
+        if matches!(physics_settings.body_type, GltfBodyType::Dynamic) {
+            collider_builder = match physics_settings.density {
+                Some(density) => collider_builder.density(density),
+                None => collider_builder.mass(physics_settings.mass),
+            };
+        }
+
+        let rb_handle = self.rigid_body_set.insert(rigid_body_builder.build());
+        let _col_handle = self.collider_set.insert_with_parent(
+            collider_builder.build(),
+            rb_handle,
+            &mut self.rigid_body_set,
+        );
+        Ok(rb_handle)
+    }
+
+    /// Create a sensor collider from a glTF node's physics settings,
+    /// placing it at the node's global transform. A sensor with a parent body
+    /// is attached to it, keeping its position relative to the body.
+    fn insert_gltf_sensor(
+        &mut self,
+        physics_settings: &GltfPhysicsSettings,
+        global_transform: &Transform3<f32>,
+        parent_body: Option<RigidBodyHandle>,
+    ) -> Result<ColliderHandle, PhysicsWorldInitError> {
+        let sensor_silhouette = gltf_collider_shape(physics_settings, None, &[], global_transform)?;
+        let collider_builder =
+            gltf_collider_builder(physics_settings, sensor_silhouette, &self.collision_layers)?
+                .sensor(true);
+
+        let parent_body = match parent_body {
+            Some(handle) if self.rigid_body_set.contains(handle) => handle,
+            _ => {
+                return Ok(self.collider_set.insert(
+                    collider_builder
+                        .position(*global_transform.isometry())
+                        .build(),
+                ))
+            }
+        };
+        let relative_isometry =
+            self.rigid_body_set[parent_body].position().inverse() * global_transform.isometry();
+        // Sensors shouldn't add to the mass of the body carrying them
+        let collider_builder = collider_builder.position(relative_isometry).density(0.0);
+        Ok(self.collider_set.insert_with_parent(
+            collider_builder.build(),
+            parent_body,
+            &mut self.rigid_body_set,
+        ))
+    }
+
     /// Load physics-enabled objects from a Perigee-enabled
     /// glTF into the physics world.
     ///
-    /// Note: Nodes that are children of others will be ignored.
+    /// Note: Only loads the first scene. Use
+    /// [load_scenes_from_gltf](Self::load_scenes_from_gltf) to choose scenes.
     pub fn load_from_gltf(
         &mut self,
         gltf: &Gltf,
         parent_transform: Option<Transform3<f32>>,
     ) -> Result<(), PhysicsWorldInitError> {
+        self.load_scenes_from_gltf(gltf, &GltfSceneSelector::First, parent_transform)?;
+        Ok(())
+    }
+
+    /// Load physics-enabled objects from the selected scenes of a
+    /// Perigee-enabled glTF into the physics world. Returns the names of
+    /// the nodes loaded from each scene.
+    ///
+    /// Joint nodes are connected once every selected scene is loaded, so
+    /// they can reference bodies in any of them.
+    ///
+    /// Note: A node shared by several scenes is only loaded (and reported) once.
+    pub fn load_scenes_from_gltf(
+        &mut self,
+        gltf: &Gltf,
+        scenes: &GltfSceneSelector,
+        parent_transform: Option<Transform3<f32>>,
+    ) -> Result<Vec<LoadedGltfScene>, PhysicsWorldInitError> {
         let mut visited_nodes: HashMap<usize, ()> = HashMap::new();
-        // Only loads the first scene
-        if let Some(scene) = gltf.scenes().next() {
+        let mut loaded_scenes = vec![];
+        let mut joint_nodes = vec![];
+        for scene in scenes.select(gltf)? {
+            let mut loaded_scene = LoadedGltfScene::new(&scene);
             for node in scene.nodes() {
                 self.visit_gltf_node(
                     &node,
                     gltf.blob.as_ref(),
                     &parent_transform.unwrap_or(Transform3::identity()),
+                    None,
                     &mut visited_nodes,
+                    &mut loaded_scene.node_names,
+                    &mut joint_nodes,
                 )?;
             }
+            loaded_scenes.push(loaded_scene);
+        }
+        for joint_node in joint_nodes {
+            let joint_handle =
+                self.insert_gltf_joint(&joint_node.settings, &joint_node.global_isometry)?;
+            self.named_joints.insert(joint_node.name, joint_handle);
         }
 
-        return Ok(());
+        Ok(loaded_scenes)
+    }
+
+    /// Spawn a copy of a prefab's rigid bodies and sensors with its root placed at
+    /// `transform`. Every object's name is prefixed with `name_prefix`, which must be
+    /// unique to this instance.
+    pub fn instantiate_prefab(
+        &mut self,
+        prefab: &Prefab,
+        transform: Transform3<f32>,
+        name_prefix: &str,
+    ) -> Result<PrefabInstance, PrefabInstantiationError> {
+        // Check names up front so a failed instantiation doesn't leave stray objects behind
+        prefab.walk(&transform, (), |node, _, _| {
+            let node_name = match &node.name {
+                Some(name) => name,
+                None => return Ok(()),
+            };
+            let instance_name = format!("{}{}", name_prefix, node_name);
+            let name_taken = match &node.item {
+                PrefabItem::RigidBody {
+                    physics_settings, ..
+                } if !physics_settings.is_anonymous => self
+                    .named_rigid_bodies
+                    .handle_with_name(&instance_name)
+                    .is_some(),
+                PrefabItem::Sensor { .. } => self
+                    .named_sensors
+                    .handle_with_name(&instance_name)
+                    .is_some(),
+                _ => false,
+            };
+            if name_taken {
+                return Err(PrefabInstantiationError::NameTaken(instance_name));
+            }
+            Ok(())
+        })?;
+
+        let mut instance = PrefabInstance {
+            animations: prefab.instantiate_animations(name_prefix),
+            ..Default::default()
+        };
+        prefab.walk(&transform, None, |node, global_transform, parent_body| {
+            let node_name = match &node.name {
+                Some(name) => name,
+                None => return Ok(parent_body),
+            };
+            let instance_name = format!("{}{}", name_prefix, node_name);
+            let mut node_body = None;
+            match &node.item {
+                PrefabItem::RigidBody {
+                    physics_settings,
+                    geometry,
+                    compound_parts,
+                } => {
+                    let rb_handle = self.insert_gltf_rigid_body(
+                        physics_settings,
+                        geometry.as_ref(),
+                        compound_parts,
+                        global_transform,
+                    )?;
+                    if !physics_settings.is_anonymous {
+                        self.named_rigid_bodies
+                            .insert(instance_name.clone(), rb_handle);
+                    }
+                    instance
+                        .rigid_bodies
+                        .insert(instance_name.clone(), rb_handle);
+                    node_body = Some(rb_handle);
+                }
+                PrefabItem::Sensor { physics_settings } => {
+                    let sensor_handle =
+                        self.insert_gltf_sensor(physics_settings, global_transform, parent_body)?;
+                    self.named_sensors
+                        .insert(instance_name.clone(), sensor_handle);
+                    instance
+                        .sensors
+                        .insert(instance_name.clone(), sensor_handle);
+                }
+                PrefabItem::Empty => {}
+            }
+            if node.is_point_of_interest {
+                instance
+                    .points_of_interest
+                    .insert(instance_name, *global_transform.isometry());
+            }
+            Ok::<_, PrefabInstantiationError>(node_body.or(parent_body))
+        })?;
+
+        Ok(instance)
     }
 
     pub fn listen_to_collider<L: ColliderEventListener + 'static>(
@@ -558,13 +567,85 @@ impl PhysicsWorld {
         }
     }
 
+    /// Listen to a collider's events with colliders on the given collision layers.
+    pub fn listen_to_collider_on_layers<L: ColliderEventListener + 'static, S: AsRef<str>>(
+        &mut self,
+        handle: ColliderHandle,
+        listener: L,
+        layer_names: &[S],
+    ) -> Result<(), CollisionLayerError> {
+        let layers = self.collision_layers.mask(layer_names)?;
+        self.listen_to_collider(handle, LayerFilteredListener { listener, layers });
+        Ok(())
+    }
+
+    pub fn collision_layers(&self) -> &CollisionLayers {
+        &self.collision_layers
+    }
+
+    /// Put a collider on a collision layer, replacing its collision groups.
+    pub fn set_collider_layer(
+        &mut self,
+        handle: ColliderHandle,
+        layer_name: &str,
+    ) -> Result<(), CollisionLayerError> {
+        let collision_groups = self.collision_layers.interaction_groups(layer_name)?;
+        if let Some(collider) = self.collider_set.get_mut(handle) {
+            collider.set_collision_groups(collision_groups);
+        }
+        Ok(())
+    }
+
+    /// A query filter that only finds colliders on the given collision layers.
+    pub fn layer_query_filter<S: AsRef<str>>(
+        &self,
+        layer_names: &[S],
+    ) -> Result<QueryFilter<'static>, CollisionLayerError> {
+        let layers = self.collision_layers.mask(layer_names)?;
+        Ok(QueryFilter::default().groups(InteractionGroups::new(Group::ALL, layers)))
+    }
+
     pub fn rekey_listeners(&mut self, old_handle: ColliderHandle, new_handle: ColliderHandle) {
         if let Some(listeners) = self.collider_event_handlers.remove(&old_handle) {
             self.collider_event_handlers.insert(new_handle, listeners);
         }
+        if let Some(listener_names) = self.registered_listener_names.remove(&old_handle) {
+            self.registered_listener_names
+                .insert(new_handle, listener_names);
+        }
+    }
+
+    /// Set the next kinematic position of every bound body to the pose of its
+    /// animation target. Call this before [step](Self::step) so bodies move smoothly
+    /// and push dynamic bodies out of their way.
+    pub fn drive_kinematic_bodies(
+        &mut self,
+        bindings: &KinematicAnimationBindings,
+        animations: &AnimationManager,
+    ) {
+        for (target_name, body_name) in bindings.bindings() {
+            let body_handle = match self.named_rigid_bodies.handle_with_name(body_name) {
+                Some(handle) => *handle,
+                None => continue,
+            };
+            let next_position = *bindings
+                .global_transform(target_name, animations)
+                .isometry();
+            if let Some(body) = self.rigid_body_set.get_mut(body_handle) {
+                if !body.is_kinematic() {
+                    continue;
+                }
+                if *body.position() != next_position {
+                    body.set_next_kinematic_position(next_position);
+                    body.wake_up(true);
+                }
+            }
+        }
     }
 
     /// Step the physics simulation by the provided number of seconds.
+    ///
+    /// Use a [FixedStepper] to step by variable frame times deterministically.
     pub fn step(&mut self, delta_seconds: f32) {
         self.integration_parameters.dt = delta_seconds;
 
@@ -585,86 +666,72 @@ impl PhysicsWorld {
         );
 
         while let Ok(collision_event) = self.contact_event_manager.get_collider_event() {
-            match collision_event {
-                CollisionEvent::Started(collider_a, collider_b, collision_type) => {
-                    if collision_type != CollisionEventFlags::SENSOR {
-                        if let Some(handlers) = self.collider_event_handlers.get_mut(&collider_a) {
-                            for handler in handlers {
-                                handler.on_collision_start(&collider_b);
-                            }
-                        };
-                        if let Some(handlers) = self.collider_event_handlers.get_mut(&collider_b) {
-                            for handler in handlers {
-                                handler.on_collision_start(&collider_a);
-                            }
-                        }
-                    } else {
-                        if let Some(handlers) = self.collider_event_handlers.get_mut(&collider_a) {
-                            for handler in handlers {
-                                handler.on_intersection_start(&collider_b);
-                            }
-                        };
-                        if let Some(handlers) = self.collider_event_handlers.get_mut(&collider_b) {
-                            for handler in handlers {
-                                handler.on_intersection_start(&collider_a);
-                            }
-                        }
-                    }
+            let (collider_a, collider_b) =
+                (collision_event.collider1(), collision_event.collider2());
+            let is_intersection = match collision_event {
+                CollisionEvent::Started(_, _, flags) | CollisionEvent::Stopped(_, _, flags) => {
+                    flags == CollisionEventFlags::SENSOR
                 }
-                CollisionEvent::Stopped(collider_a, collider_b, collision_type) => {
-                    if collision_type != CollisionEventFlags::SENSOR {
-                        if let Some(handlers) = self.collider_event_handlers.get_mut(&collider_a) {
-                            for handler in handlers {
-                                handler.on_collision_end(&collider_b);
-                            }
-                        };
-                        if let Some(handlers) = self.collider_event_handlers.get_mut(&collider_b) {
-                            for handler in handlers {
-                                handler.on_collision_end(&collider_a);
-                            }
+            };
+            for (listening_collider, other_collider) in
+                [(collider_a, collider_b), (collider_b, collider_a)]
+            {
+                self.notify_listeners(listening_collider, other_collider, |handler| {
+                    match collision_event {
+                        CollisionEvent::Started(..) if is_intersection => {
+                            handler.on_intersection_start(&other_collider)
                         }
-                    } else {
-                        if let Some(handlers) = self.collider_event_handlers.get_mut(&collider_a) {
-                            for handler in handlers {
-                                handler.on_intersection_end(&collider_b);
-                            }
-                        };
-                        if let Some(handlers) = self.collider_event_handlers.get_mut(&collider_b) {
-                            for handler in handlers {
-                                handler.on_intersection_end(&collider_a);
-                            }
+                        CollisionEvent::Started(..) => handler.on_collision_start(&other_collider),
+                        CollisionEvent::Stopped(..) if is_intersection => {
+                            handler.on_intersection_end(&other_collider)
                         }
+                        CollisionEvent::Stopped(..) => handler.on_collision_end(&other_collider),
                     }
-                }
+                });
             }
         }
         while let Ok(contact_force_event) = self.contact_event_manager.get_contact_force_event() {
-            if let Some(handlers) = self
-                .collider_event_handlers
-                .get_mut(&contact_force_event.collider1)
-            {
-                for handler in handlers {
-                    handler.on_contact_force_event(
-                        &contact_force_event.collider2,
-                        contact_force_event,
-                    );
-                }
-            };
-            if let Some(handlers) = self
-                .collider_event_handlers
-                .get_mut(&contact_force_event.collider2)
+            let (collider_a, collider_b) =
+                (contact_force_event.collider1, contact_force_event.collider2);
+            for (listening_collider, other_collider) in
+                [(collider_a, collider_b), (collider_b, collider_a)]
             {
-                for handler in handlers {
-                    handler.on_contact_force_event(
-                        &contact_force_event.collider1,
-                        contact_force_event,
-                    );
-                }
+                self.notify_listeners(listening_collider, other_collider, |handler| {
+                    handler.on_contact_force_event(&other_collider, contact_force_event)
+                });
+            }
+        }
+    }
+
+    /// Pass an event with `other_collider` to the listeners of `listening_collider`
+    /// that listen to its layers. Events with removed colliders are always passed on
+    /// since their layers can't be known.
+    fn notify_listeners(
+        &mut self,
+        listening_collider: ColliderHandle,
+        other_collider: ColliderHandle,
+        mut notify: impl FnMut(&mut Box<dyn ColliderEventListener>),
+    ) {
+        let handlers = match self.collider_event_handlers.get_mut(&listening_collider) {
+            Some(handlers) => handlers,
+            None => return,
+        };
+        let other_memberships = self
+            .collider_set
+            .get(other_collider)
+            .map(|collider| collider.collision_groups().memberships)
+            .unwrap_or(Group::ALL);
+        for handler in handlers {
+            if handler.listened_layers().intersects(other_memberships) {
+                notify(handler);
             }
         }
     }
 
     /// Recover the handle from a RigidBody using its `user_data` field.
+    ///
+    /// # Safety
+    /// - The body's `user_data` must have been written by [store_handle_in_body](Self::store_handle_in_body).
     pub unsafe fn get_body_handle(body: &RigidBody) -> RigidBodyHandle {
         let lower_32_bits_mask = 0xffffffff_u128;
         let body_user_data = body.user_data;
@@ -678,6 +745,9 @@ impl PhysicsWorld {
     }
 
     /// Store the parts of the RigidBody's handle in its `user_data`  field.
+    ///
+    /// # Safety
+    /// - This overwrites anything previously stored in the body's `user_data`.
     pub unsafe fn store_handle_in_body(handle: &RigidBodyHandle, body: &mut RigidBody) {
         let handle_parts = handle.into_raw_parts();
         let handle_index = handle_parts.0;
@@ -707,4 +777,49 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn animations_drive_bound_kinematic_bodies() {
+        use crate::animation::{
+            AnimatedProperty, Animation, AnimationChannel, ChannelType, Keyframe,
+        };
+        use gltf::animation::Interpolation;
+
+        let mut world = PhysicsWorld::default();
+        let platform_handle = world
+            .rigid_body_set
+            .insert(RigidBodyBuilder::kinematic_position_based().build());
+        world.named_rigid_bodies.insert("Platform", platform_handle);
+
+        let mut animations = AnimationManager::new();
+        animations.add(Animation::new(
+            "Rise",
+            HashMap::from([(
+                String::from("Lift"),
+                HashMap::from([(
+                    ChannelType::Translation,
+                    AnimationChannel::new(
+                        vec![
+                            Keyframe::new(0.0, AnimatedProperty::Translation(Vector3::zeros())),
+                            Keyframe::new(
+                                1.0,
+                                AnimatedProperty::Translation(Vector3::new(0.0, 2.0, 0.0)),
+                            ),
+                        ],
+                        Interpolation::Linear,
+                    ),
+                )]),
+            )]),
+        ));
+        animations.play_animation("Rise", None);
+        animations.update(0.5);
+
+        let mut bindings = KinematicAnimationBindings::new();
+        bindings.bind("Lift", "Platform");
+        world.drive_kinematic_bodies(&bindings, &animations);
+        world.step(1.0 / 60.0);
+
+        let platform_y = world.rigid_body_set[platform_handle].translation().y;
+        assert!((platform_y - 1.0).abs() < 1e-5);
+    }
 }
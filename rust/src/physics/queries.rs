@@ -0,0 +1,281 @@
+use crate::physics::PhysicsWorld;
+use rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A collider hit by a ray or shape cast.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryHit {
+    pub collider: ColliderHandle,
+    /// The rigid body the collider is attached to, if any.
+    pub rigid_body: Option<RigidBodyHandle>,
+    /// The name of the sensor that was hit or of the rigid body the collider is attached to.
+    pub name: Option<String>,
+    /// The world-space point of impact.
+    pub point: Point<Real>,
+    /// The world-space normal of the hit collider's surface at the point of impact.
+    pub normal: Vector<Real>,
+    /// How far the ray or shape traveled before hitting the collider.
+    pub distance: Real,
+}
+
+/// A collider overlapping a query shape.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryOverlap {
+    pub collider: ColliderHandle,
+    pub rigid_body: Option<RigidBodyHandle>,
+    pub name: Option<String>,
+}
+
+/// Scene queries that resolve hit colliders to their names. Queries run against
+/// the world as of its last [step](PhysicsWorld::step), so colliders inserted
+/// since then aren't found.
+impl PhysicsWorld {
+    /// The name of a sensor collider, or of the rigid body a collider is attached to.
+    pub fn collider_name(&self, collider_handle: ColliderHandle) -> Option<&String> {
+        self.named_sensors
+            .name_of_handle(&collider_handle)
+            .or_else(|| {
+                let parent_handle = self.collider_set.get(collider_handle)?.parent()?;
+                self.named_rigid_bodies.name_of_handle(&parent_handle)
+            })
+    }
+
+    fn query_hit(
+        &self,
+        collider_handle: ColliderHandle,
+        point: Point<Real>,
+        normal: Vector<Real>,
+        distance: Real,
+    ) -> QueryHit {
+        QueryHit {
+            collider: collider_handle,
+            rigid_body: self
+                .collider_set
+                .get(collider_handle)
+                .and_then(|collider| collider.parent()),
+            name: self.collider_name(collider_handle).cloned(),
+            point,
+            normal,
+            distance,
+        }
+    }
+
+    fn query_overlap(&self, collider_handle: ColliderHandle) -> QueryOverlap {
+        QueryOverlap {
+            collider: collider_handle,
+            rigid_body: self
+                .collider_set
+                .get(collider_handle)
+                .and_then(|collider| collider.parent()),
+            name: self.collider_name(collider_handle).cloned(),
+        }
+    }
+
+    /// Cast a ray and return the closest collider it hits within `max_distance`.
+    /// With `solid` set, rays starting inside a collider hit it at their origin.
+    pub fn raycast(
+        &self,
+        origin: Point<Real>,
+        direction: Vector<Real>,
+        max_distance: Real,
+        solid: bool,
+        filter: QueryFilter,
+    ) -> Option<QueryHit> {
+        let ray = Ray::new(origin, direction.try_normalize(Real::EPSILON)?);
+        let (collider_handle, intersection) = self.query_pipeline.cast_ray_and_get_normal(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &ray,
+            max_distance,
+            solid,
+            filter,
+        )?;
+        Some(self.query_hit(
+            collider_handle,
+            ray.point_at(intersection.toi),
+            intersection.normal,
+            intersection.toi,
+        ))
+    }
+
+    /// Like [raycast](Self::raycast), but returns every collider the ray hits, closest first.
+    pub fn raycast_all(
+        &self,
+        origin: Point<Real>,
+        direction: Vector<Real>,
+        max_distance: Real,
+        solid: bool,
+        filter: QueryFilter,
+    ) -> Vec<QueryHit> {
+        let direction = match direction.try_normalize(Real::EPSILON) {
+            Some(direction) => direction,
+            None => return vec![],
+        };
+        let ray = Ray::new(origin, direction);
+        let mut hits = vec![];
+        self.query_pipeline.intersections_with_ray(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &ray,
+            max_distance,
+            solid,
+            filter,
+            |collider_handle, intersection| {
+                hits.push(self.query_hit(
+                    collider_handle,
+                    ray.point_at(intersection.toi),
+                    intersection.normal,
+                    intersection.toi,
+                ));
+                true
+            },
+        );
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        hits
+    }
+
+    /// Sweep a shape from `shape_position` along `direction` and return the
+    /// first collider it hits within `max_distance`. Shapes that start out
+    /// overlapping a collider hit it at distance 0.
+    pub fn shape_cast(
+        &self,
+        shape: &dyn Shape,
+        shape_position: &Isometry<Real>,
+        direction: Vector<Real>,
+        max_distance: Real,
+        filter: QueryFilter,
+    ) -> Option<QueryHit> {
+        let direction = direction.try_normalize(Real::EPSILON)?;
+        let (collider_handle, toi) = self.query_pipeline.cast_shape(
+            &self.rigid_body_set,
+            &self.collider_set,
+            shape_position,
+            &direction,
+            shape,
+            max_distance,
+            true,
+            filter,
+        )?;
+        // The first witness and normal are on the hit collider, in world space
+        Some(self.query_hit(
+            collider_handle,
+            toi.witness1,
+            toi.normal1.into_inner(),
+            toi.toi,
+        ))
+    }
+
+    /// Every collider that intersects a shape placed at `shape_position`.
+    pub fn overlap_shape(
+        &self,
+        shape: &dyn Shape,
+        shape_position: &Isometry<Real>,
+        filter: QueryFilter,
+    ) -> Vec<QueryOverlap> {
+        let mut overlaps = vec![];
+        self.query_pipeline.intersections_with_shape(
+            &self.rigid_body_set,
+            &self.collider_set,
+            shape_position,
+            shape,
+            filter,
+            |collider_handle| {
+                overlaps.push(self.query_overlap(collider_handle));
+                true
+            },
+        );
+        overlaps
+    }
+
+    /// Every collider that intersects a sphere.
+    pub fn overlap_sphere(
+        &self,
+        center: Point<Real>,
+        radius: Real,
+        filter: QueryFilter,
+    ) -> Vec<QueryOverlap> {
+        self.overlap_shape(
+            &Ball::new(radius),
+            &Isometry::translation(center.x, center.y, center.z),
+            filter,
+        )
+    }
+
+    /// Every collider that intersects a box with the provided half extents.
+    pub fn overlap_box(
+        &self,
+        box_position: &Isometry<Real>,
+        half_extents: Vector<Real>,
+        filter: QueryFilter,
+    ) -> Vec<QueryOverlap> {
+        self.overlap_shape(&Cuboid::new(half_extents), box_position, filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_resolve_names() {
+        let mut world = PhysicsWorld::default();
+        let wall_handle = world.rigid_body_set.insert(
+            RigidBodyBuilder::fixed()
+                .translation(vector![5.0, 0.0, 0.0])
+                .build(),
+        );
+        world.collider_set.insert_with_parent(
+            ColliderBuilder::cuboid(0.5, 2.0, 2.0).build(),
+            wall_handle,
+            &mut world.rigid_body_set,
+        );
+        world.named_rigid_bodies.insert("Wall", wall_handle);
+        let sensor_handle = world.collider_set.insert(
+            ColliderBuilder::ball(1.0)
+                .translation(vector![0.0, 5.0, 0.0])
+                .sensor(true)
+                .build(),
+        );
+        world.named_sensors.insert("Goal", sensor_handle);
+        world.step(1.0 / 60.0);
+
+        let hit = world
+            .raycast(
+                point![0.0, 0.0, 0.0],
+                vector![2.0, 0.0, 0.0],
+                100.0,
+                true,
+                QueryFilter::default(),
+            )
+            .unwrap();
+        assert_eq!(hit.name.as_deref(), Some("Wall"));
+        assert_eq!(hit.rigid_body, Some(wall_handle));
+        assert!((hit.distance - 4.5).abs() < 1e-4);
+        assert!((hit.normal - vector![-1.0, 0.0, 0.0]).norm() < 1e-4);
+
+        let cast_hit = world
+            .shape_cast(
+                &Ball::new(0.5),
+                &Isometry::identity(),
+                vector![1.0, 0.0, 0.0],
+                100.0,
+                QueryFilter::default(),
+            )
+            .unwrap();
+        assert_eq!(cast_hit.name.as_deref(), Some("Wall"));
+        assert!((cast_hit.distance - 4.0).abs() < 1e-3);
+
+        let overlaps = world.overlap_sphere(point![0.0, 4.5, 0.0], 1.0, QueryFilter::default());
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!(overlaps[0].name.as_deref(), Some("Goal"));
+
+        let exclude_sensors = QueryFilter::default().exclude_sensors();
+        assert!(world
+            .overlap_box(
+                &Isometry::translation(0.0, 5.0, 0.0),
+                vector![1.0, 1.0, 1.0],
+                exclude_sensors,
+            )
+            .is_empty());
+    }
+}
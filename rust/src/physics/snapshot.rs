@@ -0,0 +1,220 @@
+use crate::physics::contact_event_mgmt::ContactEventManager;
+use crate::physics::PhysicsWorld;
+use crate::traits::{physics::ColliderEventListener, TryFromBytes, TryToBytes};
+use rapier3d::prelude::*;
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// The bytes every physics world snapshot starts with.
+const SNAPSHOT_MAGIC: [u8; 4] = *b"PGPW";
+/// Bump this whenever the serialized layout of [PhysicsWorld] changes.
+pub const PHYSICS_SNAPSHOT_VERSION: u16 = 3;
+const SNAPSHOT_HEADER_LEN: usize = SNAPSHOT_MAGIC.len() + std::mem::size_of::<u16>();
+
+#[derive(Error, Debug)]
+pub enum ListenerReattachError {
+    #[error("no collider listener factory registered as {0}")]
+    UnregisteredListener(String),
+}
+
+type ListenerFactory = Box<dyn Fn(ColliderHandle) -> Box<dyn ColliderEventListener>>;
+
+/// Named factories for collider event listeners. Listeners can't be
+/// serialized, so snapshots store the names they were created with and
+/// recreate them through the registry after a restore.
+#[derive(Default)]
+pub struct ColliderListenerRegistry {
+    factories: HashMap<String, ListenerFactory>,
+}
+
+impl ColliderListenerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a factory that creates a listener for the collider it's given.
+    pub fn register<L, F>(&mut self, listener_name: impl Into<String>, factory: F)
+    where
+        L: ColliderEventListener + 'static,
+        F: Fn(ColliderHandle) -> L + 'static,
+    {
+        self.factories.insert(
+            listener_name.into(),
+            Box::new(move |handle| Box::new(factory(handle))),
+        );
+    }
+
+    pub fn create(
+        &self,
+        listener_name: &str,
+        handle: ColliderHandle,
+    ) -> Option<Box<dyn ColliderEventListener>> {
+        self.factories
+            .get(listener_name)
+            .map(|factory| factory(handle))
+    }
+}
+
+impl PhysicsWorld {
+    /// Listen to a collider with a listener created by the registry. Unlike
+    /// [listen_to_collider](Self::listen_to_collider), the listener is recorded
+    /// in snapshots and can be reattached after a restore. Returns false if
+    /// no listener is registered with the provided name.
+    pub fn listen_to_collider_from_registry(
+        &mut self,
+        handle: ColliderHandle,
+        listener_name: &str,
+        registry: &ColliderListenerRegistry,
+    ) -> bool {
+        let listener = match registry.create(listener_name, handle) {
+            Some(listener) => listener,
+            None => return false,
+        };
+        self.collider_event_handlers
+            .entry(handle)
+            .or_default()
+            .push(listener);
+        self.registered_listener_names
+            .entry(handle)
+            .or_default()
+            .push(String::from(listener_name));
+        true
+    }
+
+    /// Recreate the registered listeners recorded in a restored world. This replaces
+    /// every listener, including ones attached without the registry.
+    pub fn reattach_listeners(
+        &mut self,
+        registry: &ColliderListenerRegistry,
+    ) -> Result<(), ListenerReattachError> {
+        self.collider_event_handlers.clear();
+        for (handle, listener_names) in &self.registered_listener_names {
+            for listener_name in listener_names {
+                let listener = match registry.create(listener_name, *handle) {
+                    Some(listener) => listener,
+                    None => {
+                        return Err(ListenerReattachError::UnregisteredListener(
+                            listener_name.clone(),
+                        ))
+                    }
+                };
+                self.collider_event_handlers
+                    .entry(*handle)
+                    .or_default()
+                    .push(listener);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TryToBytes for PhysicsWorld {
+    /// Serialize the entire simulation behind a versioned header.
+    fn try_to_bytes(&self) -> Result<Vec<u8>, String> {
+        let world_bytes = bincode::serialize(self).map_err(|err| err.to_string())?;
+        let mut snapshot_bytes = Vec::with_capacity(SNAPSHOT_HEADER_LEN + world_bytes.len());
+        snapshot_bytes.extend_from_slice(&SNAPSHOT_MAGIC);
+        snapshot_bytes.extend_from_slice(&PHYSICS_SNAPSHOT_VERSION.to_le_bytes());
+        snapshot_bytes.extend_from_slice(&world_bytes);
+        Ok(snapshot_bytes)
+    }
+}
+
+impl TryFromBytes for PhysicsWorld {
+    /// Restore a simulation from a snapshot. The physics pipeline, query pipeline
+    /// and event channels are rebuilt, but listeners must be reattached with
+    /// [reattach_listeners](PhysicsWorld::reattach_listeners).
+    fn try_from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < SNAPSHOT_HEADER_LEN || bytes[0..SNAPSHOT_MAGIC.len()] != SNAPSHOT_MAGIC {
+            return Err(String::from("bytes aren't a physics world snapshot"));
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != PHYSICS_SNAPSHOT_VERSION {
+            return Err(format!(
+                "unsupported physics world snapshot version {} (expected {})",
+                version, PHYSICS_SNAPSHOT_VERSION
+            ));
+        }
+
+        let mut world: PhysicsWorld =
+            bincode::deserialize(&bytes[SNAPSHOT_HEADER_LEN..]).map_err(|err| err.to_string())?;
+        world.pipeline = PhysicsPipeline::new();
+        world.contact_event_manager =
+            ContactEventManager::with_capacity(world.event_queue_capacity);
+        world
+            .query_pipeline
+            .update(&world.rigid_body_set, &world.collider_set);
+        Ok(world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    struct CollisionCounter {
+        collisions: Rc<Cell<u32>>,
+    }
+
+    impl ColliderEventListener for CollisionCounter {
+        fn on_collision_start(&mut self, _other: &ColliderHandle) {
+            self.collisions.set(self.collisions.get() + 1);
+        }
+    }
+
+    #[test]
+    fn restored_worlds_continue_identically() {
+        let collisions = Rc::new(Cell::new(0));
+        let mut registry = ColliderListenerRegistry::new();
+        let counted_collisions = collisions.clone();
+        registry.register("count_collisions", move |_| CollisionCounter {
+            collisions: counted_collisions.clone(),
+        });
+
+        let mut world = PhysicsWorld::default();
+        let ground_handle = world
+            .collider_set
+            .insert(ColliderBuilder::cuboid(10.0, 0.1, 10.0).build());
+        let ball_handle = world.rigid_body_set.insert(
+            RigidBodyBuilder::dynamic()
+                .translation(vector![0.0, 2.0, 0.0])
+                .build(),
+        );
+        world.collider_set.insert_with_parent(
+            ColliderBuilder::ball(0.5)
+                .active_events(ActiveEvents::COLLISION_EVENTS)
+                .build(),
+            ball_handle,
+            &mut world.rigid_body_set,
+        );
+        assert!(world.listen_to_collider_from_registry(
+            ground_handle,
+            "count_collisions",
+            &registry
+        ));
+        for _ in 0..10 {
+            world.step(1.0 / 60.0);
+        }
+
+        let snapshot = world.try_to_bytes().unwrap();
+        let mut restored_world = PhysicsWorld::try_from_bytes(&snapshot).unwrap();
+        restored_world.reattach_listeners(&registry).unwrap();
+        for _ in 0..120 {
+            world.step(1.0 / 60.0);
+            restored_world.step(1.0 / 60.0);
+        }
+
+        assert_eq!(
+            world.rigid_body_set[ball_handle].translation(),
+            restored_world.rigid_body_set[ball_handle].translation()
+        );
+        // Both worlds' listeners saw the ball land
+        assert_eq!(collisions.get(), 2);
+
+        let mut wrong_version = snapshot.clone();
+        wrong_version[4] = wrong_version[4].wrapping_add(1);
+        assert!(PhysicsWorld::try_from_bytes(&wrong_version).is_err());
+    }
+}
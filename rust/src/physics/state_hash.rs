@@ -0,0 +1,152 @@
+use crate::physics::PhysicsWorld;
+use rapier3d::prelude::*;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// A 64-bit FNV-1a hasher. Unlike [std::hash::Hasher] implementations, its
+/// output is the same on every platform and Rust version, and every value
+/// is written in a fixed-width little-endian form.
+pub(crate) struct StateHasher(u64);
+
+impl StateHasher {
+    pub(crate) fn new() -> Self {
+        Self(FNV_OFFSET_BASIS)
+    }
+
+    pub(crate) fn write_bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub(crate) fn write_u8(&mut self, value: u8) {
+        self.write_bytes(&[value]);
+    }
+
+    pub(crate) fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub(crate) fn write_bool(&mut self, value: bool) {
+        self.write_u8(u8::from(value));
+    }
+
+    pub(crate) fn write_real(&mut self, value: Real) {
+        // Both zeroes compare equal, so they should hash equally too
+        let value = if value == 0.0 { 0.0 } else { value };
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub(crate) fn write_reals<'a>(&mut self, values: impl IntoIterator<Item = &'a Real>) {
+        for value in values {
+            self.write_real(*value);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl PhysicsWorld {
+    /// A hash of the simulation state: body positions, velocities and sleep
+    /// states, and collider parameters, visited in handle order. Two worlds
+    /// that agree produce the same hash on any platform, including 32-bit wasm
+    /// and 64-bit native targets, so it's cheap to compare instances frame by frame.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = StateHasher::new();
+        hasher.write_reals(self.gravity.iter());
+
+        for (handle, body) in self.rigid_body_set.iter() {
+            let (index, generation) = handle.into_raw_parts();
+            hasher.write_u32(index);
+            hasher.write_u32(generation);
+            hasher.write_u8(body.body_type() as u8);
+            let position = body.position();
+            hasher.write_reals(position.translation.vector.iter());
+            hasher.write_reals(position.rotation.coords.iter());
+            hasher.write_reals(body.linvel().iter());
+            hasher.write_reals(body.angvel().iter());
+            hasher.write_bool(body.is_sleeping());
+        }
+
+        for (handle, collider) in self.collider_set.iter() {
+            let (index, generation) = handle.into_raw_parts();
+            hasher.write_u32(index);
+            hasher.write_u32(generation);
+            match collider.parent() {
+                Some(parent_handle) => {
+                    let (parent_index, parent_generation) = parent_handle.into_raw_parts();
+                    hasher.write_bool(true);
+                    hasher.write_u32(parent_index);
+                    hasher.write_u32(parent_generation);
+                }
+                None => hasher.write_bool(false),
+            }
+            let position = collider.position();
+            hasher.write_reals(position.translation.vector.iter());
+            hasher.write_reals(position.rotation.coords.iter());
+            hasher.write_u8(collider.shape().shape_type() as u8);
+            let local_aabb = collider.shape().compute_local_aabb();
+            hasher.write_reals(local_aabb.mins.iter());
+            hasher.write_reals(local_aabb.maxs.iter());
+            hasher.write_bool(collider.is_sensor());
+            hasher.write_real(collider.friction());
+            hasher.write_real(collider.restitution());
+            hasher.write_real(collider.density());
+            let collision_groups = collider.collision_groups();
+            hasher.write_u32(collision_groups.memberships.bits());
+            hasher.write_u32(collision_groups.filter.bits());
+            let solver_groups = collider.solver_groups();
+            hasher.write_u32(solver_groups.memberships.bits());
+            hasher.write_u32(solver_groups.filter.bits());
+        }
+
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::{TryFromBytes, TryToBytes};
+
+    fn world_with_ball() -> (PhysicsWorld, RigidBodyHandle) {
+        let mut world = PhysicsWorld::default();
+        let handle = world.rigid_body_set.insert(
+            RigidBodyBuilder::dynamic()
+                .translation(vector![0.0, 3.0, 0.0])
+                .build(),
+        );
+        world.collider_set.insert_with_parent(
+            ColliderBuilder::ball(0.5).friction(0.3).build(),
+            handle,
+            &mut world.rigid_body_set,
+        );
+        world.named_rigid_bodies.insert("Ball", handle);
+        (world, handle)
+    }
+
+    #[test]
+    fn state_hash_tracks_simulation_state() {
+        let (mut first_world, _) = world_with_ball();
+        let (mut second_world, second_handle) = world_with_ball();
+        assert_eq!(first_world.state_hash(), second_world.state_hash());
+
+        for _ in 0..10 {
+            first_world.step(1.0 / 60.0);
+            second_world.step(1.0 / 60.0);
+        }
+        assert_eq!(first_world.state_hash(), second_world.state_hash());
+
+        let restored_world =
+            PhysicsWorld::try_from_bytes(&first_world.try_to_bytes().unwrap()).unwrap();
+        assert_eq!(first_world.state_hash(), restored_world.state_hash());
+
+        second_world.rigid_body_set[second_handle].apply_impulse(vector![0.01, 0.0, 0.0], true);
+        second_world.step(1.0 / 60.0);
+        first_world.step(1.0 / 60.0);
+        assert_ne!(first_world.state_hash(), second_world.state_hash());
+    }
+}
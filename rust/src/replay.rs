@@ -0,0 +1,291 @@
+//! Recording the inputs a simulation is fed and replaying them later,
+//! e.g. to attach a reproduction to a bug report or to run it in CI.
+//!
+//! A replay stream is a short header followed by one bincode-encoded
+//! [ReplayStep] per simulation step.
+use crate::time::PassiveClock;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::time::Duration;
+use thiserror::Error;
+
+/// The bytes every replay stream starts with.
+const REPLAY_MAGIC: [u8; 4] = *b"PGRP";
+/// Bump this whenever the encoding of [ReplayStep] changes.
+pub const REPLAY_FORMAT_VERSION: u16 = 1;
+
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    #[error("stream is not a Perigee replay")]
+    NotAReplay,
+    #[error("unsupported replay version {0}")]
+    UnsupportedVersion(u16),
+    #[error("could not read or write replay stream: {0}")]
+    Io(#[from] io::Error),
+    #[error("could not encode or decode replay step: {0}")]
+    Encoding(#[from] bincode::Error),
+    #[error("replay stream ended partway through a step")]
+    Truncated,
+}
+
+/// A simulation that's driven by a step delta and a user-defined input.
+pub trait Replayable {
+    type Input: Serialize + DeserializeOwned;
+
+    fn step(&mut self, delta_seconds: f32, input: &Self::Input);
+}
+
+/// One recorded simulation step.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayStep<I> {
+    /// The simulation time before the step.
+    pub timestamp: Duration,
+    pub delta_seconds: f32,
+    pub input: I,
+}
+
+/// Writes the steps a simulation takes to a replay stream.
+pub struct ReplayRecorder<W: Write> {
+    writer: W,
+    clock: PassiveClock,
+    recorded_steps: u64,
+}
+
+impl<W: Write> ReplayRecorder<W> {
+    /// Start a replay stream by writing its header.
+    pub fn new(mut writer: W) -> Result<Self, ReplayError> {
+        writer.write_all(&REPLAY_MAGIC)?;
+        writer.write_all(&REPLAY_FORMAT_VERSION.to_le_bytes())?;
+        Ok(Self {
+            writer,
+            clock: PassiveClock::new(),
+            recorded_steps: 0,
+        })
+    }
+
+    /// Record a step without running it.
+    pub fn record<I: Serialize>(
+        &mut self,
+        delta_seconds: f32,
+        input: &I,
+    ) -> Result<(), ReplayError> {
+        bincode::serialize_into(
+            &mut self.writer,
+            &ReplayStep {
+                timestamp: self.clock.elapsed(),
+                delta_seconds,
+                input,
+            },
+        )?;
+        self.clock.tick(delta_seconds);
+        self.recorded_steps += 1;
+        Ok(())
+    }
+
+    /// Record a step and then run it on the simulation.
+    pub fn step<S: Replayable>(
+        &mut self,
+        sim: &mut S,
+        delta_seconds: f32,
+        input: &S::Input,
+    ) -> Result<(), ReplayError> {
+        self.record(delta_seconds, input)?;
+        sim.step(delta_seconds, input);
+        Ok(())
+    }
+
+    pub fn recorded_steps(&self) -> u64 {
+        self.recorded_steps
+    }
+
+    /// Flush the stream and hand back the underlying writer.
+    pub fn finish(mut self) -> Result<W, ReplayError> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads the steps of a replay stream, in the order they were recorded.
+pub struct ReplayReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> ReplayReader<R> {
+    /// Open a replay stream by validating its header.
+    pub fn new(mut reader: R) -> Result<Self, ReplayError> {
+        let mut magic = [0u8; REPLAY_MAGIC.len()];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::UnexpectedEof => ReplayError::NotAReplay,
+                _ => ReplayError::Io(err),
+            })?;
+        if magic != REPLAY_MAGIC {
+            return Err(ReplayError::NotAReplay);
+        }
+        let mut version = [0u8; 2];
+        reader.read_exact(&mut version)?;
+        let version = u16::from_le_bytes(version);
+        if version != REPLAY_FORMAT_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        Ok(Self { reader })
+    }
+
+    /// The next recorded step, or `None` once the stream has ended. A stream
+    /// that ends partway through a step is [truncated](ReplayError::Truncated).
+    pub fn next_step<I: DeserializeOwned>(&mut self) -> Result<Option<ReplayStep<I>>, ReplayError> {
+        // The stream may only end between steps, so check for more bytes
+        // before decoding the next one
+        let mut first_byte = [0u8; 1];
+        loop {
+            match self.reader.read(&mut first_byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(ReplayError::Io(err)),
+            }
+        }
+        match bincode::deserialize_from((&first_byte[..]).chain(&mut self.reader)) {
+            Ok(step) => Ok(Some(step)),
+            Err(err) => match err.as_ref() {
+                bincode::ErrorKind::Io(io_err) if io_err.kind() == io::ErrorKind::UnexpectedEof => {
+                    Err(ReplayError::Truncated)
+                }
+                _ => Err(ReplayError::Encoding(err)),
+            },
+        }
+    }
+
+    /// Run every remaining step on the simulation. Returns the number of steps run.
+    pub fn replay<S: Replayable>(&mut self, sim: &mut S) -> Result<u64, ReplayError> {
+        self.replay_with(sim, |_, _| {})
+    }
+
+    /// Like [replay](Self::replay), but calls `after_step` with the simulation and
+    /// the step it just ran, e.g. to compare state against an expected value.
+    pub fn replay_with<S, F>(&mut self, sim: &mut S, mut after_step: F) -> Result<u64, ReplayError>
+    where
+        S: Replayable,
+        F: FnMut(&mut S, &ReplayStep<S::Input>),
+    {
+        let mut replayed_steps = 0;
+        while let Some(step) = self.next_step::<S::Input>()? {
+            sim.step(step.delta_seconds, &step.input);
+            after_step(sim, &step);
+            replayed_steps += 1;
+        }
+        Ok(replayed_steps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::PhysicsWorld;
+    use rapier3d::prelude::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Thrust {
+        up: bool,
+        sideways: f32,
+    }
+
+    struct ThrustSim {
+        world: PhysicsWorld,
+        body_handle: RigidBodyHandle,
+    }
+
+    impl ThrustSim {
+        fn new() -> Self {
+            let mut world = PhysicsWorld::default();
+            let body_handle = world
+                .rigid_body_set
+                .insert(RigidBodyBuilder::dynamic().build());
+            world.collider_set.insert_with_parent(
+                ColliderBuilder::ball(0.5).build(),
+                body_handle,
+                &mut world.rigid_body_set,
+            );
+            Self { world, body_handle }
+        }
+
+        fn position(&self) -> Vector<Real> {
+            *self.world.rigid_body_set[self.body_handle].translation()
+        }
+    }
+
+    impl Replayable for ThrustSim {
+        type Input = Thrust;
+
+        fn step(&mut self, delta_seconds: f32, input: &Thrust) {
+            let up_impulse = if input.up { 1.0 } else { 0.0 };
+            self.world.rigid_body_set[self.body_handle]
+                .apply_impulse(vector![input.sideways, up_impulse, 0.0], true);
+            self.world.step(delta_seconds);
+        }
+    }
+
+    #[test]
+    fn replay_reproduces_recorded_sim() {
+        let mut recorded_sim = ThrustSim::new();
+        let mut recorder = ReplayRecorder::new(Vec::new()).unwrap();
+        for step in 0..45 {
+            let input = Thrust {
+                up: step % 3 == 0,
+                sideways: (step % 4) as f32 - 1.5,
+            };
+            let delta_seconds = if step % 2 == 0 {
+                1.0 / 60.0
+            } else {
+                1.0 / 30.0
+            };
+            recorder
+                .step(&mut recorded_sim, delta_seconds, &input)
+                .unwrap();
+        }
+        let replay_bytes = recorder.finish().unwrap();
+
+        let mut replayed_sim = ThrustSim::new();
+        let mut reader = ReplayReader::new(replay_bytes.as_slice()).unwrap();
+        let mut last_timestamp = Duration::ZERO;
+        let replayed_steps = reader
+            .replay_with(&mut replayed_sim, |_, step| last_timestamp = step.timestamp)
+            .unwrap();
+
+        assert_eq!(replayed_steps, 45);
+        assert!(last_timestamp > Duration::from_secs_f32(1.0));
+        assert_eq!(recorded_sim.position(), replayed_sim.position());
+        assert!(matches!(
+            ReplayReader::new(&b"nope"[..]),
+            Err(ReplayError::NotAReplay)
+        ));
+    }
+
+    #[test]
+    fn truncated_replays_are_errors() {
+        let mut recorder = ReplayRecorder::new(Vec::new()).unwrap();
+        for step in 0..3 {
+            let input = Thrust {
+                up: step % 2 == 0,
+                sideways: step as f32,
+            };
+            recorder.record(1.0 / 60.0, &input).unwrap();
+        }
+        let replay_bytes = recorder.finish().unwrap();
+
+        for chopped_bytes in 1..8 {
+            let truncated_bytes = &replay_bytes[..replay_bytes.len() - chopped_bytes];
+            let mut reader = ReplayReader::new(truncated_bytes).unwrap();
+            assert!(matches!(
+                reader.replay(&mut ThrustSim::new()),
+                Err(ReplayError::Truncated)
+            ));
+        }
+
+        // Ending exactly between steps is a clean end
+        let mut reader = ReplayReader::new(replay_bytes.as_slice()).unwrap();
+        assert_eq!(reader.replay(&mut ThrustSim::new()).unwrap(), 3);
+        assert!(reader.next_step::<Thrust>().unwrap().is_none());
+    }
+}
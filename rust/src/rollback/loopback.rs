@@ -0,0 +1,231 @@
+//! Two rollback sessions connected by an in-memory link with artificial
+//! latency, for testing determinism and desync detection without a network.
+use crate::rollback::{RollbackError, RollbackGame, RollbackSession};
+use std::collections::VecDeque;
+
+struct InFlightInput<I> {
+    deliver_at_tick: u32,
+    player: usize,
+    frame: u32,
+    input: I,
+}
+
+/// Two peers where peer 0 controls player 0 and peer 1 controls player 1.
+pub struct LoopbackPeers<G: RollbackGame> {
+    peers: [RollbackSession<G>; 2],
+    latency_ticks: u32,
+    tick: u32,
+    in_flight: [VecDeque<InFlightInput<G::Input>>; 2],
+}
+
+impl<G: RollbackGame> LoopbackPeers<G> {
+    /// Connect two sessions. Inputs reach the other peer `latency_ticks` ticks after being sent.
+    pub fn new(
+        first_peer: RollbackSession<G>,
+        second_peer: RollbackSession<G>,
+        latency_ticks: u32,
+    ) -> Self {
+        Self {
+            peers: [first_peer, second_peer],
+            latency_ticks,
+            tick: 0,
+            in_flight: [VecDeque::new(), VecDeque::new()],
+        }
+    }
+
+    pub fn peer(&self, peer: usize) -> &RollbackSession<G> {
+        &self.peers[peer]
+    }
+
+    /// Give each peer its local player's input for its current frame, deliver
+    /// inputs whose latency has passed and advance both peers by one frame.
+    pub fn tick(&mut self, local_inputs: [G::Input; 2]) -> Result<(), RollbackError> {
+        for (player, input) in local_inputs.into_iter().enumerate() {
+            let frame = self.peers[player].current_frame();
+            self.peers[player].add_input(player, frame, input.clone())?;
+            self.in_flight[1 - player].push_back(InFlightInput {
+                deliver_at_tick: self.tick + self.latency_ticks,
+                player,
+                frame,
+                input,
+            });
+        }
+        self.deliver(false)?;
+        for peer in &mut self.peers {
+            peer.advance_frame()?;
+        }
+        self.tick += 1;
+        Ok(())
+    }
+
+    /// Deliver every input still in flight, then simulate a frame so that
+    /// pending rollbacks are resolved.
+    pub fn flush(&mut self) -> Result<(), RollbackError> {
+        self.deliver(true)?;
+        for (player, peer) in self.peers.iter_mut().enumerate() {
+            let frame = peer.current_frame();
+            peer.add_input(player, frame, G::Input::default())?;
+        }
+        for (player, input) in [(0, G::Input::default()), (1, G::Input::default())] {
+            let frame = self.peers[player].current_frame();
+            self.peers[1 - player].add_input(player, frame, input)?;
+        }
+        for peer in &mut self.peers {
+            peer.advance_frame()?;
+        }
+        Ok(())
+    }
+
+    fn deliver(&mut self, deliver_all: bool) -> Result<(), RollbackError> {
+        for peer_index in 0..2 {
+            while let Some(in_flight_input) = self.in_flight[peer_index].front() {
+                if !deliver_all && in_flight_input.deliver_at_tick > self.tick {
+                    break;
+                }
+                let in_flight_input = self.in_flight[peer_index]
+                    .pop_front()
+                    .expect("In-flight input disappeared after peeking at it");
+                self.peers[peer_index].add_input(
+                    in_flight_input.player,
+                    in_flight_input.frame,
+                    in_flight_input.input,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The confirmed frames whose state checksums differ between the peers.
+    pub fn desynced_frames(&self) -> Vec<u32> {
+        let confirmed_frames = self.peers[0]
+            .confirmed_frames()
+            .min(self.peers[1].confirmed_frames());
+        (0..confirmed_frames)
+            .filter(|frame| {
+                match (
+                    self.peers[0].checksum(*frame),
+                    self.peers[1].checksum(*frame),
+                ) {
+                    (Some(first_checksum), Some(second_checksum)) => {
+                        first_checksum != second_checksum
+                    }
+                    _ => false,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::PhysicsWorld;
+    use rapier3d::prelude::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    struct PushGame {
+        pushes: [u32; 2],
+        impulse_scale: f32,
+    }
+
+    impl RollbackGame for PushGame {
+        type Input = i8;
+
+        fn advance_frame(&mut self, world: &mut PhysicsWorld, inputs: &[i8]) {
+            for (player, input) in inputs.iter().enumerate() {
+                if *input == 0 {
+                    continue;
+                }
+                self.pushes[player] += 1;
+                let body_name = if player == 0 { "Left" } else { "Right" };
+                let handle = *world
+                    .named_rigid_bodies
+                    .handle_with_name(body_name)
+                    .unwrap();
+                world.rigid_body_set[handle].apply_impulse(
+                    vector![f32::from(*input) * self.impulse_scale, 0.0, 0.0],
+                    true,
+                );
+            }
+        }
+    }
+
+    fn session(impulse_scale: f32) -> RollbackSession<PushGame> {
+        let mut world = PhysicsWorld::default();
+        world.gravity = vector![0.0, 0.0, 0.0];
+        for (name, x) in [("Left", -2.0), ("Right", 2.0)] {
+            let handle = world.rigid_body_set.insert(
+                RigidBodyBuilder::dynamic()
+                    .translation(vector![x, 0.0, 0.0])
+                    .build(),
+            );
+            world.collider_set.insert_with_parent(
+                ColliderBuilder::ball(0.5).build(),
+                handle,
+                &mut world.rigid_body_set,
+            );
+            world.named_rigid_bodies.insert(name, handle);
+        }
+        RollbackSession::new(
+            world,
+            PushGame {
+                pushes: [0, 0],
+                impulse_scale,
+            },
+            2,
+            1.0 / 60.0,
+            8,
+        )
+    }
+
+    fn inputs(tick: u32) -> [i8; 2] {
+        [(tick % 7) as i8 - 3, (tick % 5) as i8 - 2]
+    }
+
+    #[test]
+    fn peers_agree_despite_latency() {
+        let mut peers = LoopbackPeers::new(session(1.0), session(1.0), 4);
+        for tick in 0..90 {
+            peers.tick(inputs(tick)).unwrap();
+        }
+        peers.flush().unwrap();
+
+        assert!(peers.peer(0).confirmed_frames() > 80);
+        assert!(peers.desynced_frames().is_empty());
+        assert_eq!(peers.peer(0).game().pushes, peers.peer(1).game().pushes);
+    }
+
+    #[test]
+    fn diverging_peers_are_detected() {
+        // Nondeterminism on one peer, like a platform's float rounding differing
+        let mut peers = LoopbackPeers::new(session(1.0), session(1.001), 2);
+        for tick in 0..30 {
+            peers.tick(inputs(tick)).unwrap();
+        }
+        peers.flush().unwrap();
+
+        assert!(!peers.desynced_frames().is_empty());
+    }
+
+    #[test]
+    fn inputs_at_the_edges_of_the_frame_range() {
+        let mut session = session(1.0);
+        // Frames far in the future are accepted without overflowing
+        session.add_input(0, u32::MAX, 1).unwrap();
+        session.add_input(1, u32::MAX, 1).unwrap();
+
+        for frame in 0..12 {
+            session.add_input(0, frame, 1).unwrap();
+            session.add_input(1, frame, -1).unwrap();
+            session.advance_frame().unwrap();
+        }
+        assert_eq!(session.confirmed_frames(), 12);
+        assert_eq!(session.game().pushes, [12, 12]);
+        assert!(matches!(
+            session.add_input(0, 0, 0),
+            Err(RollbackError::InputTooOld(0))
+        ));
+        session.add_input(0, 4, 1).unwrap();
+    }
+}
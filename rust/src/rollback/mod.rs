@@ -0,0 +1,249 @@
+//! Rollback netcode built on [PhysicsWorld] snapshots.
+//!
+//! Every peer runs the same deterministic simulation. Frames are simulated
+//! right away with predicted inputs for remote players, and when a remote
+//! input turns out to differ from its prediction the session restores the
+//! snapshot of that frame and re-simulates up to the present.
+//!
+//! Collider listeners aren't part of snapshots, so games should react to
+//! collisions from their [RollbackGame::advance_frame] instead.
+use crate::physics::{PhysicsWorld, StateHasher};
+use crate::traits::{TryFromBytes, TryToBytes};
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+pub mod loopback;
+
+#[derive(Error, Debug)]
+pub enum RollbackError {
+    #[error("player {0} isn't part of this session")]
+    InvalidPlayer(usize),
+    #[error("input for frame {0} is too old to roll back to")]
+    InputTooOld(u32),
+    #[error("can't predict further ahead of the last confirmed frame")]
+    PredictionWindowFull,
+    #[error("could not save or load a snapshot: {0}")]
+    Snapshot(String),
+}
+
+/// Game state that can be simulated and rolled back alongside a [PhysicsWorld].
+pub trait RollbackGame: Serialize + DeserializeOwned {
+    type Input: Clone + PartialEq + Default;
+
+    /// Apply one frame of every player's input, indexed by player. The
+    /// session steps the physics world afterwards.
+    fn advance_frame(&mut self, world: &mut PhysicsWorld, inputs: &[Self::Input]);
+}
+
+struct FrameSnapshot {
+    frame: u32,
+    world_bytes: Vec<u8>,
+    game_bytes: Vec<u8>,
+}
+
+/// A rollback session for one peer.
+pub struct RollbackSession<G: RollbackGame> {
+    world: PhysicsWorld,
+    game: G,
+    timestep: f32,
+    num_players: usize,
+    max_rollback_frames: u32,
+    current_frame: u32,
+    snapshots: Vec<Option<FrameSnapshot>>,
+    confirmed_inputs: Vec<BTreeMap<u32, G::Input>>,
+    /// The first frame of each player whose input hasn't arrived yet.
+    next_unconfirmed_frames: Vec<u32>,
+    /// The inputs each frame was last simulated with.
+    simulated_inputs: BTreeMap<u32, Vec<G::Input>>,
+    /// The state checksum after each simulated frame.
+    checksums: BTreeMap<u32, u64>,
+    rollback_frame: Option<u32>,
+}
+
+impl<G: RollbackGame> RollbackSession<G> {
+    /// Start a session at frame 0. Every frame steps the world by `timestep`
+    /// seconds, and up to `max_rollback_frames` frames can be re-simulated.
+    pub fn new(
+        world: PhysicsWorld,
+        game: G,
+        num_players: usize,
+        timestep: f32,
+        max_rollback_frames: u32,
+    ) -> Self {
+        Self {
+            world,
+            game,
+            timestep,
+            num_players,
+            max_rollback_frames,
+            current_frame: 0,
+            snapshots: (0..=max_rollback_frames).map(|_| None).collect(),
+            confirmed_inputs: vec![BTreeMap::new(); num_players],
+            next_unconfirmed_frames: vec![0; num_players],
+            simulated_inputs: BTreeMap::new(),
+            checksums: BTreeMap::new(),
+            rollback_frame: None,
+        }
+    }
+
+    pub fn world(&self) -> &PhysicsWorld {
+        &self.world
+    }
+
+    pub fn game(&self) -> &G {
+        &self.game
+    }
+
+    /// The next frame to be simulated.
+    pub fn current_frame(&self) -> u32 {
+        self.current_frame
+    }
+
+    /// The number of frames (counting from frame 0) for which every player's input has arrived.
+    pub fn confirmed_frames(&self) -> u32 {
+        self.next_unconfirmed_frames
+            .iter()
+            .copied()
+            .min()
+            .unwrap_or(0)
+            .min(self.current_frame)
+    }
+
+    /// The checksum of the simulation's state after the frame was simulated.
+    /// Only checksums of [confirmed](Self::confirmed_frames) frames are final.
+    pub fn checksum(&self, frame: u32) -> Option<u64> {
+        self.checksums.get(&frame).copied()
+    }
+
+    /// Record a player's input for a frame. Inputs for frames that were already
+    /// simulated with a wrong prediction schedule a rollback on the next
+    /// [advance_frame](Self::advance_frame).
+    pub fn add_input(
+        &mut self,
+        player: usize,
+        frame: u32,
+        input: G::Input,
+    ) -> Result<(), RollbackError> {
+        if player >= self.num_players {
+            return Err(RollbackError::InvalidPlayer(player));
+        }
+        if frame < self.current_frame.saturating_sub(self.max_rollback_frames) {
+            return Err(RollbackError::InputTooOld(frame));
+        }
+
+        if let Some(simulated_inputs) = self.simulated_inputs.get(&frame) {
+            if simulated_inputs[player] != input {
+                self.rollback_frame = Some(
+                    self.rollback_frame
+                        .map_or(frame, |rollback_frame| rollback_frame.min(frame)),
+                );
+            }
+        }
+        let player_inputs = &mut self.confirmed_inputs[player];
+        player_inputs.insert(frame, input);
+        let next_unconfirmed_frame = &mut self.next_unconfirmed_frames[player];
+        while player_inputs.contains_key(next_unconfirmed_frame) {
+            *next_unconfirmed_frame += 1;
+        }
+        Ok(())
+    }
+
+    /// Repeat the player's most recent input at or before the frame.
+    fn input_for(&self, player: usize, frame: u32) -> G::Input {
+        self.confirmed_inputs[player]
+            .range(..=frame)
+            .next_back()
+            .map(|(_, input)| input.clone())
+            .unwrap_or_default()
+    }
+
+    fn save_snapshot(&mut self) -> Result<(), RollbackError> {
+        let world_bytes = self.world.try_to_bytes().map_err(RollbackError::Snapshot)?;
+        let game_bytes = bincode::serialize(&self.game)
+            .map_err(|err| RollbackError::Snapshot(err.to_string()))?;
+        let slot = self.current_frame as usize % self.snapshots.len();
+        self.snapshots[slot] = Some(FrameSnapshot {
+            frame: self.current_frame,
+            world_bytes,
+            game_bytes,
+        });
+        Ok(())
+    }
+
+    fn load_snapshot(&mut self, frame: u32) -> Result<(), RollbackError> {
+        let slot = frame as usize % self.snapshots.len();
+        let snapshot = match &self.snapshots[slot] {
+            Some(snapshot) if snapshot.frame == frame => snapshot,
+            _ => return Err(RollbackError::InputTooOld(frame)),
+        };
+        self.world =
+            PhysicsWorld::try_from_bytes(&snapshot.world_bytes).map_err(RollbackError::Snapshot)?;
+        self.game = bincode::deserialize(&snapshot.game_bytes)
+            .map_err(|err| RollbackError::Snapshot(err.to_string()))?;
+        self.current_frame = frame;
+        Ok(())
+    }
+
+    fn state_checksum(&self) -> Result<u64, RollbackError> {
+        let game_bytes = bincode::serialize(&self.game)
+            .map_err(|err| RollbackError::Snapshot(err.to_string()))?;
+        let mut hasher = StateHasher::new();
+        hasher.write_bytes(&self.world.state_hash().to_le_bytes());
+        hasher.write_bytes(&game_bytes);
+        Ok(hasher.finish())
+    }
+
+    fn simulate_frame(&mut self) -> Result<(), RollbackError> {
+        self.save_snapshot()?;
+        let frame = self.current_frame;
+        let inputs: Vec<G::Input> = (0..self.num_players)
+            .map(|player| self.input_for(player, frame))
+            .collect();
+        self.game.advance_frame(&mut self.world, &inputs);
+        self.world.step(self.timestep);
+        self.simulated_inputs.insert(frame, inputs);
+        self.checksums.insert(frame, self.state_checksum()?);
+        self.current_frame += 1;
+        Ok(())
+    }
+
+    /// Roll back and re-simulate if needed, then simulate the current frame.
+    pub fn advance_frame(&mut self) -> Result<(), RollbackError> {
+        if self.current_frame
+            >= self
+                .confirmed_frames()
+                .saturating_add(self.max_rollback_frames)
+        {
+            return Err(RollbackError::PredictionWindowFull);
+        }
+
+        if let Some(rollback_frame) = self.rollback_frame.take() {
+            let present_frame = self.current_frame;
+            self.load_snapshot(rollback_frame)?;
+            while self.current_frame < present_frame {
+                self.simulate_frame()?;
+            }
+        }
+        self.simulate_frame()?;
+
+        // Forget what can no longer be rolled back to, keeping each
+        // player's latest input around for predictions
+        if let Some(oldest_frame) = self
+            .current_frame
+            .checked_sub(self.max_rollback_frames.saturating_add(1))
+        {
+            self.simulated_inputs = self.simulated_inputs.split_off(&oldest_frame);
+            self.checksums = self.checksums.split_off(&oldest_frame);
+            for player_inputs in &mut self.confirmed_inputs {
+                let newer_inputs = player_inputs.split_off(&oldest_frame);
+                let latest_older_input = player_inputs.pop_last();
+                *player_inputs = newer_inputs;
+                if let Some((frame, input)) = latest_older_input {
+                    player_inputs.entry(frame).or_insert(input);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -6,4 +6,9 @@ pub trait ColliderEventListener {
     fn on_intersection_start(&mut self, _other: &ColliderHandle) {}
     fn on_intersection_end(&mut self, _other: &ColliderHandle) {}
     fn on_contact_force_event(&mut self, _other: &ColliderHandle, _details: ContactForceEvent) {}
+    /// The collision layer groups of the colliders this listener hears about.
+    /// Events with colliders outside of them aren't passed to the listener.
+    fn listened_layers(&self) -> Group {
+        Group::ALL
+    }
 }
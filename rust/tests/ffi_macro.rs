@@ -0,0 +1,6 @@
+#[test]
+fn ffi_macro_expansions() {
+    let test_cases = trybuild::TestCases::new();
+    test_cases.pass("tests/ui/pass/*.rs");
+    test_cases.compile_fail("tests/ui/*.rs");
+}
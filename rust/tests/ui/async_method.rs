@@ -0,0 +1,10 @@
+use perigee::macros::ffi;
+
+struct Sim;
+
+#[ffi]
+impl Sim {
+    pub async fn load(&mut self) {}
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use perigee::macros::ffi;
+
+struct Sim;
+
+#[ffi]
+impl Sim {
+    #[ffi_only]
+    #[ffi_never]
+    pub fn reset(&mut self) {}
+}
+
+fn main() {}
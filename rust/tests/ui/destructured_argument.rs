@@ -0,0 +1,10 @@
+use perigee::macros::ffi;
+
+struct Sim;
+
+#[ffi]
+impl Sim {
+    pub fn move_to(&mut self, (_x, _y): (f32, f32)) {}
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+mod sim {
+    use perigee::macros::ffi;
+
+    #[derive(Default)]
+    pub struct Sim {
+        score: u32,
+    }
+
+    #[ffi]
+    impl Sim {
+        #[ffi_only]
+        pub fn reset_score(&mut self) {
+            self.score = 0;
+        }
+    }
+}
+
+fn main() {
+    let mut sim = sim::Sim::default();
+    // Only the host can call methods marked #[ffi_only]
+    sim.reset_score();
+}
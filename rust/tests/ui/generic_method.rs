@@ -0,0 +1,10 @@
+use perigee::macros::ffi;
+
+struct Sim;
+
+#[ffi]
+impl Sim {
+    pub fn apply<T: Copy>(&mut self, _value: T) {}
+}
+
+fn main() {}
@@ -0,0 +1,45 @@
+use perigee::ffi::manifest::FfiManifest;
+use perigee::macros::ffi;
+
+#[derive(Default)]
+struct Sim {
+    score: u32,
+}
+
+#[ffi]
+impl Sim {
+    pub fn add_score(&mut self, points: u32) {
+        self.score += points;
+    }
+
+    #[slot_return]
+    pub fn score(&self) -> &u32 {
+        &self.score
+    }
+
+    pub fn score_label(&self) -> String {
+        format!("{} points", self.score)
+    }
+}
+
+#[ffi(manifest = BONUS_FFI_MANIFEST)]
+impl Sim {
+    #[slot_return]
+    pub fn bonus(&self) -> u64 {
+        u64::from(self.score) * 2
+    }
+}
+
+fn main() {
+    let mut sim = Sim::default();
+    add_score(&mut sim, 5);
+    let bonus_slot = allocate_u64_space();
+    bonus(&sim, bonus_slot);
+    assert_eq!(unsafe { *bonus_slot }, 10);
+    free_u64_space(bonus_slot);
+
+    let mut manifest = FfiManifest::from_json(Sim::FFI_MANIFEST).unwrap();
+    manifest.merge(FfiManifest::from_json(Sim::BONUS_FFI_MANIFEST).unwrap());
+    assert_eq!(manifest.functions.len(), 4);
+    assert_eq!(manifest.slotted_types.len(), 2);
+}
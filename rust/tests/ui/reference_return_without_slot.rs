@@ -0,0 +1,14 @@
+use perigee::macros::ffi;
+
+struct Sim {
+    score: u32,
+}
+
+#[ffi]
+impl Sim {
+    pub fn score(&self) -> &u32 {
+        &self.score
+    }
+}
+
+fn main() {}
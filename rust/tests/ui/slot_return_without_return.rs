@@ -0,0 +1,11 @@
+use perigee::macros::ffi;
+
+struct Sim;
+
+#[ffi]
+impl Sim {
+    #[slot_return]
+    pub fn reset(&mut self) {}
+}
+
+fn main() {}
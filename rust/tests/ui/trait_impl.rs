@@ -0,0 +1,13 @@
+use perigee::macros::ffi;
+
+#[derive(Clone)]
+struct Sim;
+
+#[ffi]
+impl Default for Sim {
+    fn default() -> Self {
+        Sim
+    }
+}
+
+fn main() {}
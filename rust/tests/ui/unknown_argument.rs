@@ -0,0 +1,10 @@
+use perigee::macros::ffi;
+
+struct Sim;
+
+#[ffi(name = SIM_MANIFEST)]
+impl Sim {
+    pub fn reset(&mut self) {}
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use perigee::macros::ffi;
+
+struct Sim;
+
+#[ffi]
+impl Sim {
+    pub fn load_level(&mut self, _level_bytes: Vec<u8>) {}
+}
+
+fn main() {}
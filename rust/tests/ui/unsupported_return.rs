@@ -0,0 +1,12 @@
+use perigee::macros::ffi;
+
+struct Sim;
+
+#[ffi]
+impl Sim {
+    pub fn score(&self) -> Option<u32> {
+        None
+    }
+}
+
+fn main() {}